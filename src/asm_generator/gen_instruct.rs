@@ -1,8 +1,6 @@
-use std::fmt::Write;
-
 use koopa::ir::{
-    values::{Binary, Return, Store},
-    BinaryOp, FunctionData, Value, ValueKind,
+    values::{Binary, Branch, Call, Jump, Load, Return, Store},
+    FunctionData, Type, TypeKind, Value, ValueKind,
 };
 
 use crate::traits::instruct_generator::InstructionGenerator;
@@ -15,22 +13,27 @@ impl AsmGenerator {
     // }
 
     fn get_or_generate_value_reg(&mut self, func: &FunctionData, val: Value) -> String {
-        if let Some(&offset) = self.reg_manager.stack_slots.get(&val) {
-            let reg = match self.reg_manager.allocate_tmp() {
-                Some(r) => r,
-                None => self
-                    .spill_and_get_reg(func, val)
-                    .expect("Failed to allocate register even after spilling"),
-            };
-            self.output
-                .push_str(&format!("  lw {}, {}(sp)\n", reg, offset));
+        self.reg_manager.pinned.insert(val);
+        if let Some(reg) = self.reg_manager.value_reg_map.get(&val) {
+            return reg.clone();
+        }
+        if self.reg_manager.spilled.contains(&val) {
+            let offset = self.reg_manager.spill_slots[&val];
+            let reg = self.allocate_reg();
+            let inst = self.inst_generator.generate_load(&reg, offset, "sp");
+            self.output.push_str(&inst);
             self.reg_manager.value_reg_map.insert(val, reg.clone());
             return reg;
         }
-        if let Some(reg) = self.reg_manager.value_reg_map.get(&val) {
-            reg.clone()
-        } else {
-            self.generate_value_and_get_reg(func, val)
+        self.generate_value_and_get_reg(func, val)
+    }
+
+    fn allocate_reg(&mut self) -> String {
+        match self.reg_manager.allocate_tmp() {
+            Some(r) => r,
+            None => self
+                .spill_and_get_reg()
+                .expect("Failed to allocate register even after spilling"),
         }
     }
 
@@ -39,7 +42,7 @@ impl AsmGenerator {
         let mut max_next_use = 0;
 
         for (old_val, reg) in self.reg_manager.value_reg_map.iter() {
-            if reg.starts_with('t') {
+            if reg.starts_with('t') && !self.reg_manager.pinned.contains(old_val) {
                 if let Some(use_count) = self.reg_manager.value_use_count.get(old_val) {
                     if *use_count > 0 && *use_count > max_next_use {
                         max_next_use = *use_count;
@@ -51,28 +54,84 @@ impl AsmGenerator {
         candidate
     }
 
-    fn spill_and_get_reg(&mut self, func: &FunctionData, val: Value) -> Option<String> {
+    fn spill_and_get_reg(&mut self) -> Option<String> {
         let (old_val, reg) = self.find_reg_to_spill()?;
-        let offset = self.reg_manager.spill_to_stack(old_val);
-        self.output
-            .push_str(&format!("  sw {}, {}(sp)\n", &reg, offset));
-        self.reg_manager.free_register(&reg, old_val);
+        self.spill_value(old_val, &reg);
         self.reg_manager.value_reg_map.remove(&old_val);
         Some(reg)
     }
+
+    fn spill_value(&mut self, val: Value, reg: &str) {
+        // 常量和变量地址没有溢出槽, 需要时重新生成即可
+        if !self.reg_manager.spill_slots.contains_key(&val) {
+            return;
+        }
+        if !self.reg_manager.spilled.contains(&val) {
+            let offset = self.reg_manager.spill_to_stack(val);
+            let inst = self.inst_generator.generate_store(reg, offset, "sp");
+            self.output.push_str(&inst);
+        }
+    }
+
+    /// 把所有仍然活跃的寄存器值写回溢出槽, 并清空寄存器分配状态.
+    /// 在基本块结束前调用, 保证跨块使用的值总能从栈上重新加载.
+    fn spill_live_values(&mut self) {
+        let mut live: Vec<(Value, String)> = self
+            .reg_manager
+            .value_reg_map
+            .iter()
+            .filter(|(val, _)| self.get_use_count(val) > 0)
+            .map(|(val, reg)| (*val, reg.clone()))
+            .collect();
+        live.sort_by(|a, b| a.1.cmp(&b.1));
+        for (val, reg) in live {
+            self.spill_value(val, &reg);
+        }
+        self.reg_manager.reset_registers();
+    }
+
+    /// 调用前 a0-a7 会被实参覆盖, 先把仍然活跃的形参写回溢出槽
+    fn spill_arg_regs(&mut self) {
+        let mut in_arg_regs: Vec<(Value, String)> = self
+            .reg_manager
+            .value_reg_map
+            .iter()
+            .filter(|(_, reg)| reg.starts_with('a'))
+            .map(|(val, reg)| (*val, reg.clone()))
+            .collect();
+        in_arg_regs.sort_by(|a, b| a.1.cmp(&b.1));
+        for (val, reg) in in_arg_regs {
+            if self.get_use_count(&val) > 0 {
+                self.spill_value(val, &reg);
+            }
+            self.reg_manager.value_reg_map.remove(&val);
+            self.reg_manager.release_register(&reg);
+        }
+    }
+
+    /// 记录指令结果所在的寄存器, 没有后续使用的结果直接释放
+    fn define_value(&mut self, val: Value, reg: String) {
+        if self.get_use_count(&val) == 0 {
+            self.reg_manager.release_register(&reg);
+        } else {
+            self.reg_manager.value_reg_map.insert(val, reg);
+        }
+    }
+
     fn generate_value_and_get_reg(&mut self, func: &FunctionData, val: Value) -> String {
+        if let Some(name) = self.global_names.get(&val).cloned() {
+            let reg = self.allocate_reg();
+            self.output.push_str(&format!("  la {}, {}\n", reg, name));
+            self.reg_manager.value_reg_map.insert(val, reg.clone());
+            return reg;
+        }
         let data = func.dfg().value(val);
         match data.kind() {
             ValueKind::Integer(c) => {
                 if c.value() == 0 {
                     return "x0".to_string();
                 }
-                let reg = match self.reg_manager.allocate_tmp() {
-                    Some(r) => r,
-                    None => self
-                        .spill_and_get_reg(func, val)
-                        .expect("Failed to allocate register even after spilling"),
-                };
+                let reg = self.allocate_reg();
 
                 self.output.push_str(
                     self.inst_generator
@@ -88,35 +147,13 @@ impl AsmGenerator {
                     self.reg_manager.stack_slots.get(&val).copied().expect(
                         "Alloc value must have a stack slot assigned during initialization",
                     );
-                let reg = match self.reg_manager.allocate_tmp() {
-                    Some(r) => r,
-                    None => self
-                        .spill_and_get_reg(func, val)
-                        .expect("Failed to allocate register even after spilling"),
-                };
-
-                self.output
-                    .push_str(&format!("  addi {}, {}, {}\n", reg, "sp", offset));
+                let reg = self.allocate_reg();
+                let inst = self.inst_generator.generate_addi(&reg, "sp", offset);
+                self.output.push_str(&inst);
                 self.reg_manager.value_reg_map.insert(val, reg.clone());
                 reg
             }
 
-            ValueKind::Load(load) => {
-                let src = load.src();
-                if let Some(&offset) = self.reg_manager.stack_slots.get(&src) {
-                    let dst_reg = self
-                        .reg_manager
-                        .allocate_tmp()
-                        .or_else(|| self.spill_and_get_reg(func, val))
-                        .expect("Failed to allocate register");
-                    self.output
-                        .push_str(&format!("  lw {}, {}(sp)\n", dst_reg, offset));
-                    self.reg_manager.value_reg_map.insert(val, dst_reg.clone());
-                    return dst_reg;
-                }
-                unreachable!("Load source must be allocated");
-            }
-
             _ => {
                 unreachable!("Unsupported value kind {:?}", data.kind());
             }
@@ -125,14 +162,13 @@ impl AsmGenerator {
 
     fn handle_return(&mut self, func: &FunctionData, ret: &Return) {
         if let Some(val) = ret.value() {
-            if let Some(offset) = self.reg_manager.stack_slots.get(&val) {
-                self.output.push_str(&format!("  lw a0, {}(sp)\n", offset));
-            } else {
-                let reg = self.get_or_generate_value_reg(func, val);
-                if reg != "a0" {
-                    self.output.push_str(&format!("  mv a0, {}\n", reg));
-                }
+            let reg = self.get_or_generate_value_reg(func, val);
+            let ret_reg = self.reg_manager.get_return_reg();
+            if reg != ret_reg {
+                let inst = self.inst_generator.generate_move(&ret_reg, &reg);
+                self.output.push_str(&inst);
             }
+            self.reg_manager.after_value_use(val);
         }
 
         let epilogue = self.reg_manager.generate_epilogue();
@@ -149,26 +185,17 @@ impl AsmGenerator {
             .generate_binary(binary.op(), &dst_reg, &lhs_reg, &rhs_reg);
         self.output.push_str(&inst);
 
-        if let Some(offset) = self.reg_manager.stack_slots.get(&val) {
-            self.output
-                .push_str(&format!("  sw {}, {}({})\n", dst_reg, offset, "sp"));
-            self.reg_manager.free_register(&dst_reg, val);
-        }
-
-        self.reg_manager.value_reg_map.insert(val, dst_reg);
         if lhs_reg != "x0" {
             self.reg_manager.after_value_use(binary.lhs());
         }
         if rhs_reg != "x0" {
             self.reg_manager.after_value_use(binary.rhs());
         }
+        self.define_value(val, dst_reg);
     }
 
-    fn can_reuse_register(&self, val: Value) -> bool {
-        self.reg_manager
-            .value_use_count
-            .get(&val)
-            .map_or(false, |&count| count <= 1)
+    fn can_reuse_register(&self, val: Value, reg: &str) -> bool {
+        reg.starts_with('t') && self.is_last_use(&val)
     }
 
     fn prepare_binary_ops(
@@ -181,11 +208,14 @@ impl AsmGenerator {
         let lhs_reg = self.get_or_generate_value_reg(func, lhs);
         let rhs_reg = self.get_or_generate_value_reg(func, rhs);
 
-        let dst_reg = if self.can_reuse_register(lhs) && lhs_reg != "x0" {
+        let dst_reg = if lhs != rhs && self.can_reuse_register(lhs, &lhs_reg) {
             self.reg_manager.value_reg_map.remove(&lhs);
             lhs_reg.clone()
+        } else if lhs != rhs && self.can_reuse_register(rhs, &rhs_reg) {
+            self.reg_manager.value_reg_map.remove(&rhs);
+            rhs_reg.clone()
         } else {
-            self.reg_manager.allocate_tmp().unwrap()
+            self.allocate_reg()
         };
 
         (dst_reg, lhs_reg, rhs_reg)
@@ -197,43 +227,226 @@ impl AsmGenerator {
             ValueKind::Return(ret) => self.handle_return(func, ret),
             ValueKind::Binary(binary) => self.handle_binary(func, val, binary),
             ValueKind::Store(store) => self.handle_store(func, store),
+            ValueKind::Load(load) => self.handle_load(func, val, load),
+            ValueKind::Branch(branch) => self.handle_branch(func, branch),
+            ValueKind::Jump(jump) => self.handle_jump(jump),
+            ValueKind::Call(call) => self.handle_call(func, val, call),
+            // 结果类型都是 *T, 下标的步长就是 T 的大小
+            ValueKind::GetElemPtr(gep) => {
+                let elem_size = Self::pointee_size(data.ty());
+                self.handle_elem_ptr(func, val, gep.src(), gep.index(), elem_size)
+            }
+            ValueKind::GetPtr(gp) => {
+                let elem_size = Self::pointee_size(data.ty());
+                self.handle_elem_ptr(func, val, gp.src(), gp.index(), elem_size)
+            }
             _ => {}
         }
+        self.reg_manager.pinned.clear();
     }
 
     fn handle_store(&mut self, func: &FunctionData, store: &Store) {
         let val_reg = self.get_or_generate_value_reg(func, store.value());
         let dest = store.dest();
-        if let Some(&offset) = self.reg_manager.stack_slots.get(&dest) {
-            self.output
-                .push_str(&format!("  sw {}, {}(sp)\n", val_reg, offset));
-        }
+        let inst = if let Some(&offset) = self.reg_manager.stack_slots.get(&dest) {
+            self.inst_generator.generate_store(&val_reg, offset, "sp")
+        } else {
+            let addr_reg = self.get_or_generate_value_reg(func, dest);
+            self.inst_generator.generate_store(&val_reg, 0, &addr_reg)
+        };
+        self.output.push_str(&inst);
         self.reg_manager.after_value_use(store.value());
         self.reg_manager.after_value_use(store.dest());
     }
 
+    fn handle_load(&mut self, func: &FunctionData, val: Value, load: &Load) {
+        let src = load.src();
+        let (offset, base) = if let Some(&offset) = self.reg_manager.stack_slots.get(&src) {
+            (offset, "sp".to_string())
+        } else {
+            (0, self.get_or_generate_value_reg(func, src))
+        };
+        self.reg_manager.after_value_use(src);
+        let dst_reg = self.allocate_reg();
+        let inst = self.inst_generator.generate_load(&dst_reg, offset, &base);
+        self.output.push_str(&inst);
+        self.define_value(val, dst_reg);
+    }
+
+    fn pointee_size(ty: &Type) -> i32 {
+        match ty.kind() {
+            TypeKind::Pointer(elem) => elem.size() as i32,
+            _ => unreachable!("Address must have a pointer type"),
+        }
+    }
+
+    /// 计算 `src + index * elem_size`, 局部数组直接以 sp 为基址
+    fn handle_elem_ptr(
+        &mut self,
+        func: &FunctionData,
+        val: Value,
+        src: Value,
+        index: Value,
+        elem_size: i32,
+    ) {
+        let (base, base_offset) = match self.reg_manager.stack_slots.get(&src) {
+            Some(&offset) => ("sp".to_string(), offset),
+            None => (self.get_or_generate_value_reg(func, src), 0),
+        };
+        let const_index = match func.dfg().value(index).kind() {
+            ValueKind::Integer(c) => Some(c.value()),
+            _ => None,
+        };
+        let index_reg = match const_index {
+            Some(_) => None,
+            None => Some(self.get_or_generate_value_reg(func, index)),
+        };
+        let dst_reg = self.allocate_reg();
+        let inst = match (const_index, index_reg) {
+            (Some(c), _) => {
+                self.inst_generator
+                    .generate_addi(&dst_reg, &base, base_offset + c * elem_size)
+            }
+            (None, Some(index_reg)) if base_offset == 0 => self
+                .inst_generator
+                .generate_scaled_add(&dst_reg, &base, &index_reg, elem_size),
+            (None, Some(index_reg)) => {
+                let mut inst = self
+                    .inst_generator
+                    .generate_addi(&dst_reg, &base, base_offset);
+                inst += &self
+                    .inst_generator
+                    .generate_scaled_add(&dst_reg, &dst_reg, &index_reg, elem_size);
+                inst
+            }
+            (None, None) => unreachable!(),
+        };
+        self.output.push_str(&inst);
+        self.reg_manager.after_value_use(src);
+        self.reg_manager.after_value_use(index);
+        self.define_value(val, dst_reg);
+    }
+
+    fn handle_branch(&mut self, func: &FunctionData, branch: &Branch) {
+        let cond_reg = self.get_or_generate_value_reg(func, branch.cond());
+        self.reg_manager.after_value_use(branch.cond());
+        self.spill_live_values();
+        let true_label = self.bb_labels[&branch.true_bb()].clone();
+        let false_label = self.bb_labels[&branch.false_bb()].clone();
+        let inst = self
+            .inst_generator
+            .generate_branch(&cond_reg, &true_label, &false_label);
+        self.output.push_str(&inst);
+    }
+
+    fn handle_jump(&mut self, jump: &Jump) {
+        self.spill_live_values();
+        let label = self.bb_labels[&jump.target()].clone();
+        let inst = self.inst_generator.generate_jump(&label);
+        self.output.push_str(&inst);
+    }
+
+    fn handle_call(&mut self, func: &FunctionData, val: Value, call: &Call) {
+        self.spill_arg_regs();
+        self.reg_manager.reserve_for_call(call.args().len());
+        for (i, &arg) in call.args().iter().enumerate() {
+            let reg = self.get_or_generate_value_reg(func, arg);
+            let inst = match self.reg_manager.get_calling_convention_reg(i) {
+                Some(arg_reg) => self.inst_generator.generate_move(&arg_reg, &reg),
+                // 第 9 个及之后的实参放在栈帧底部的传参区
+                None => self
+                    .inst_generator
+                    .generate_store(&reg, (i as i32 - 8) * 4, "sp"),
+            };
+            self.output.push_str(&inst);
+            self.reg_manager.after_value_use(arg);
+            self.reg_manager.pinned.remove(&arg);
+        }
+        // t0-t6 由调用者保存
+        self.spill_live_values();
+
+        let callee = self.func_names[&call.callee()].clone();
+        self.output.push_str(&format!("  call {}\n", callee));
+
+        if !func.dfg().value(val).ty().is_unit() {
+            let reg = self.allocate_reg();
+            let ret_reg = self.reg_manager.get_return_reg();
+            let inst = self.inst_generator.generate_move(&reg, &ret_reg);
+            self.output.push_str(&inst);
+            self.define_value(val, reg);
+        }
+    }
+
     pub fn init_function(&mut self, func: &FunctionData) {
         self.reg_manager.reset_stack();
-        let mut stack_size = 0;
-        for (bb, _) in func.layout().bbs() {
-            let bb_node = func.layout().bbs().node(&bb).unwrap();
+        self.bb_labels.clear();
+        let func_name = &func.name()[1..];
+
+        // 栈帧布局 (从 sp 向上): 传参区, 局部变量, 溢出槽, ra
+        let mut max_call_args = None;
+        for (_, bb_node) in func.layout().bbs() {
+            for (inst, _) in bb_node.insts() {
+                if let ValueKind::Call(call) = func.dfg().value(*inst).kind() {
+                    max_call_args = max_call_args.max(Some(call.args().len()));
+                }
+            }
+        }
+        let mut stack_size = max_call_args.map_or(0, |n| n.saturating_sub(8) as i32 * 4);
+
+        for (i, (bb, bb_node)) in func.layout().bbs().iter().enumerate() {
+            let bb_name = match func.dfg().bb(*bb).name() {
+                Some(name) => name[1..].to_string(),
+                None => format!("bb{}", i),
+            };
+            self.bb_labels
+                .insert(*bb, format!(".L{}_{}", func_name, bb_name));
             for (inst, _) in bb_node.insts() {
                 let data = func.dfg().value(*inst);
                 match data.kind() {
                     ValueKind::Alloc(_) => {
+                        let size = match data.ty().kind() {
+                            TypeKind::Pointer(base) => base.size() as i32,
+                            _ => unreachable!("Alloc must have a pointer type"),
+                        };
                         self.reg_manager.stack_slots.insert(*inst, stack_size);
+                        stack_size += size;
+                    }
+                    _ if !data.ty().is_unit() => {
+                        self.reg_manager.spill_slots.insert(*inst, stack_size);
                         stack_size += 4;
                     }
-
                     _ => {}
                 }
             }
         }
 
+        for &param in func.params().iter().take(8) {
+            self.reg_manager.spill_slots.insert(param, stack_size);
+            stack_size += 4;
+        }
+        if max_call_args.is_some() {
+            self.reg_manager.ra_offset = Some(stack_size);
+            stack_size += 4;
+        }
+
         let aligned_size = (stack_size + 15) / 16 * 16;
         self.reg_manager.current_stack_offset = aligned_size;
 
         self.reg_manager.reset_registers();
+        self.reg_manager.reserve_for_call(func.params().len());
+        for (i, &param) in func.params().iter().enumerate() {
+            match self.reg_manager.get_calling_convention_reg(i) {
+                Some(reg) => {
+                    self.reg_manager.value_reg_map.insert(param, reg);
+                }
+                // 多余的实参位于调用者栈帧的传参区, 视作已经溢出
+                None => {
+                    let offset = aligned_size + (i as i32 - 8) * 4;
+                    self.reg_manager.spill_slots.insert(param, offset);
+                    self.reg_manager.spilled.insert(param);
+                }
+            }
+        }
 
         let prologue = self.reg_manager.generate_prologue();
         self.output
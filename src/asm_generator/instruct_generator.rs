@@ -39,4 +39,54 @@ impl InstructionGenerator for RiscvInstructionGenerator {
     fn generate_move(&mut self, dst: &str, src: &str) -> String {
         format!("  mv {}, {}\n", dst, src)
     }
+
+    fn generate_load(&mut self, dst: &str, offset: i32, base: &str) -> String {
+        if is_imm12(offset) {
+            format!("  lw {}, {}({})\n", dst, offset, base)
+        } else {
+            format!(
+                "  li t6, {}\n  add t6, {}, t6\n  lw {}, 0(t6)\n",
+                offset, base, dst
+            )
+        }
+    }
+
+    fn generate_store(&mut self, src: &str, offset: i32, base: &str) -> String {
+        if is_imm12(offset) {
+            format!("  sw {}, {}({})\n", src, offset, base)
+        } else {
+            format!(
+                "  li t6, {}\n  add t6, {}, t6\n  sw {}, 0(t6)\n",
+                offset, base, src
+            )
+        }
+    }
+
+    fn generate_addi(&mut self, dst: &str, src: &str, imm: i32) -> String {
+        if is_imm12(imm) {
+            format!("  addi {}, {}, {}\n", dst, src, imm)
+        } else {
+            format!("  li t6, {}\n  add {}, {}, t6\n", imm, dst, src)
+        }
+    }
+
+    fn generate_scaled_add(&mut self, dst: &str, base: &str, index: &str, scale: i32) -> String {
+        format!(
+            "  li t6, {}\n  mul t6, {}, t6\n  add {}, {}, t6\n",
+            scale, index, dst, base
+        )
+    }
+
+    fn generate_branch(&mut self, cond: &str, true_label: &str, false_label: &str) -> String {
+        format!("  bnez {}, {}\n  j {}\n", cond, true_label, false_label)
+    }
+
+    fn generate_jump(&mut self, label: &str) -> String {
+        format!("  j {}\n", label)
+    }
+}
+
+/// 能否放进 12 位有符号立即数
+fn is_imm12(value: i32) -> bool {
+    (-2048..=2047).contains(&value)
 }
@@ -2,13 +2,22 @@ mod gen_instruct;
 mod instruct_generator;
 mod register_manager;
 mod riscv_asm_generator;
+mod support;
 use instruct_generator::RiscvInstructionGenerator;
+use std::collections::HashMap;
+
+use koopa::ir::{BasicBlock, Function, Value};
 use register_manager::RiscvRegisterManager;
 
+pub use support::check_supported;
+
 pub struct AsmGenerator {
     reg_manager: RiscvRegisterManager,
     inst_generator: RiscvInstructionGenerator,
     output: String,
+    bb_labels: HashMap<BasicBlock, String>,
+    func_names: HashMap<Function, String>,
+    global_names: HashMap<Value, String>,
 }
 
 impl AsmGenerator {
@@ -17,6 +26,15 @@ impl AsmGenerator {
             reg_manager: RiscvRegisterManager::new(),
             inst_generator: RiscvInstructionGenerator,
             output: String::new(),
+            bb_labels: HashMap::new(),
+            func_names: HashMap::new(),
+            global_names: HashMap::new(),
         }
     }
 }
+
+impl Default for AsmGenerator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
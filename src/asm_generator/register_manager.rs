@@ -1,6 +1,6 @@
 use std::collections::{HashMap, HashSet};
 
-use koopa::ir::{layout::BasicBlockNode, BasicBlock, FunctionData, Value, ValueKind};
+use koopa::ir::Value;
 
 // #[derive(Default)]
 // struct LivenessInfo {
@@ -47,8 +47,17 @@ pub struct RiscvRegisterManager {
     arg_regs: [bool; 8],    // a0-a7
     pub(crate) value_reg_map: HashMap<Value, String>,
     pub(crate) value_use_count: HashMap<Value, usize>,
+    // alloc 指令对应的栈上变量
     pub(crate) stack_slots: HashMap<Value, i32>,
+    // 每个有结果的指令都预留一个溢出槽, 栈帧大小在生成函数体之前就确定
+    pub(crate) spill_slots: HashMap<Value, i32>,
+    // 当前在溢出槽里有有效副本的值
+    pub(crate) spilled: HashSet<Value>,
+    // 当前指令正在使用的操作数, 不能被溢出
+    pub(crate) pinned: HashSet<Value>,
     pub(crate) current_stack_offset: i32,
+    // 函数内有调用时 ra 的保存位置
+    pub(crate) ra_offset: Option<i32>,
     // interference_edge: InterferenceEdge,
 }
 
@@ -56,11 +65,11 @@ impl RiscvRegisterManager {
     fn is_value_dead(&self, val: &Value) -> bool {
         self.value_use_count
             .get(val)
-            .map_or(true, |&count| count == 0)
+            .is_none_or(|&count| count == 0)
     }
     pub fn reset_registers(&mut self) {
         self.value_reg_map.clear();
-        self.value_use_count.clear();
+        self.pinned.clear();
         self.temp_regs = [false; 7];
         self.saved_regs = [false; 12];
         self.arg_regs = [false; 8];
@@ -68,7 +77,10 @@ impl RiscvRegisterManager {
 
     pub fn reset_stack(&mut self) {
         self.stack_slots.clear();
+        self.spill_slots.clear();
+        self.spilled.clear();
         self.current_stack_offset = 0;
+        self.ra_offset = None;
     }
 
     pub fn new() -> Self {
@@ -79,35 +91,26 @@ impl RiscvRegisterManager {
             value_reg_map: HashMap::new(),
             value_use_count: HashMap::new(),
             stack_slots: HashMap::new(),
+            spill_slots: HashMap::new(),
+            spilled: HashSet::new(),
+            pinned: HashSet::new(),
             current_stack_offset: 0,
+            ra_offset: None,
             // interference_edge: InterferenceEdge::new(),
         }
     }
 
+    /// 只分配 t0-t5, t6 留作计算大偏移量地址的临时寄存器
     pub(crate) fn allocate_tmp(&mut self) -> Option<String> {
-        for (i, used) in self.temp_regs.iter_mut().enumerate() {
+        for (i, used) in self.temp_regs.iter_mut().take(6).enumerate() {
             if !*used {
                 *used = true;
                 return Some(format!("t{}", i));
             }
         }
-        // spill to stack
-        for reg in &["t0", "t1", "t2", "t3", "t4", "t5", "t6"] {
-            if let Some((val, _)) = self
-                .value_reg_map
-                .iter()
-                .find(|(_, r)| r == reg)
-                .and_then(|(v, r)| Some((*v, r.clone())))
-            {
-                // 溢出到栈
-                let offset = self.spill_to_stack(val);
-                self.stack_slots.insert(val, offset);
-                self.free_register(reg, val);
-                return Some(reg.to_string());
-            }
-        }
         None
     }
+    #[allow(dead_code)]
     pub(crate) fn allocate_saved(&mut self) -> Option<String> {
         for (i, used) in self.saved_regs.iter_mut().enumerate() {
             if !*used {
@@ -120,34 +123,30 @@ impl RiscvRegisterManager {
 
     pub(crate) fn free_register(&mut self, reg: &str, val: Value) {
         if self.is_value_dead(&val) {
-            let reg_type = reg.chars().next().unwrap();
-            let reg_num: usize = reg[1..].parse().unwrap();
-            match reg_type {
-                't' => self.temp_regs[reg_num] = false,
-                's' => self.saved_regs[reg_num] = false,
-                'a' => self.arg_regs[reg_num] = false,
-                _ => {}
-            }
+            self.release_register(reg);
         }
     }
 
-    pub fn try_reuse_stack_slot(&mut self) -> Option<i32> {
-        for (&val, &offset) in &self.stack_slots {
-            if self.is_value_dead(&val) {
-                return Some(offset);
-            }
+    pub(crate) fn release_register(&mut self, reg: &str) {
+        let reg_type = reg.chars().next().unwrap();
+        let Ok(reg_num) = reg[1..].parse::<usize>() else {
+            return;
+        };
+        match reg_type {
+            't' => self.temp_regs[reg_num] = false,
+            's' => self.saved_regs[reg_num] = false,
+            'a' => self.arg_regs[reg_num] = false,
+            _ => {}
         }
-        None
     }
 
+    /// 把值标记为已溢出并返回它的溢出槽, 调用者负责生成 `sw`
     pub fn spill_to_stack(&mut self, val: Value) -> i32 {
-        if let Some(offset) = self.try_reuse_stack_slot() {
-            self.stack_slots.insert(val, offset);
-            return offset;
-        }
-        let offset = self.current_stack_offset;
-        self.stack_slots.insert(val, offset);
-        self.current_stack_offset += 4;
+        let offset = *self
+            .spill_slots
+            .get(&val)
+            .expect("Spilled value must have a reserved slot");
+        self.spilled.insert(val);
         offset
     }
     pub fn get_calling_convention_reg(&self, index: usize) -> Option<String> {
@@ -167,6 +166,7 @@ impl RiscvRegisterManager {
         "a0".to_string()
     }
 
+    #[allow(dead_code)]
     pub fn mark_register_live(&mut self, reg: &str) {
         let reg_type = reg.chars().next().unwrap();
         let reg_num: usize = reg[1..].parse().unwrap();
@@ -189,31 +189,33 @@ impl RiscvRegisterManager {
     pub(crate) fn after_value_use(&mut self, val: Value) {
         self.decrease_use_count(&val);
         if self.is_value_dead(&val) {
-            if let Some(reg) = self.value_reg_map.get(&val).cloned() {
+            if let Some(reg) = self.value_reg_map.remove(&val) {
                 self.free_register(&reg, val);
             }
         }
     }
-    pub(crate) fn should_allocate_register(&self, val: Value) -> bool {
-        if let Some(&use_count) = self.value_use_count.get(&val) {
-            use_count > 1
-        } else {
-            false
-        }
-    }
 
     pub fn generate_prologue(&self) -> Vec<String> {
         let mut prologue = Vec::new();
         let stack_size = self.current_stack_offset;
         let aligned_size = (stack_size + 15) / 16 * 16;
         if aligned_size > 0 {
-            if aligned_size <= 2047 {
+            if aligned_size <= 2048 {
                 prologue.push(format!("  addi sp, sp, -{}", aligned_size));
             } else {
-                prologue.push(format!("  li t0 , {}", aligned_size));
+                prologue.push(format!("  li t0, {}", aligned_size));
                 prologue.push("  sub sp, sp, t0".to_string());
             }
         }
+        if let Some(offset) = self.ra_offset {
+            if offset <= 2047 {
+                prologue.push(format!("  sw ra, {}(sp)", offset));
+            } else {
+                prologue.push(format!("  li t0, {}", offset));
+                prologue.push("  add t0, sp, t0".to_string());
+                prologue.push("  sw ra, 0(t0)".to_string());
+            }
+        }
 
         let mut to_save = Vec::new();
 
@@ -222,7 +224,7 @@ impl RiscvRegisterManager {
                 to_save.push(format!("s{}", i));
             }
         }
-        if to_save.len() > 0 {
+        if !to_save.is_empty() {
             prologue.push(format!("  addi sp, sp, -{}", (to_save.len() * 4) as i32));
             for (i, reg) in to_save.iter().enumerate() {
                 prologue.push(format!("  sw {}, {}(sp)", reg, i * 4));
@@ -233,15 +235,6 @@ impl RiscvRegisterManager {
 
     pub fn generate_epilogue(&self) -> Vec<String> {
         let mut epilogue = Vec::new();
-        let mut aligned_size = self.current_stack_offset;
-        if aligned_size > 0 {
-            if aligned_size <= 2047 {
-                epilogue.push(format!("  addi sp, sp, {}", aligned_size));
-            } else {
-                epilogue.push(format!("  li t0 , {}", aligned_size));
-                epilogue.push("  add sp, sp, t0".to_string());
-            }
-        }
         let mut to_restore = Vec::new();
 
         for (i, &used) in self.saved_regs.iter().enumerate() {
@@ -249,12 +242,30 @@ impl RiscvRegisterManager {
                 to_restore.push(format!("s{}", i));
             }
         }
-        if to_restore.len() > 0 {
+        if !to_restore.is_empty() {
             for (i, reg) in to_restore.iter().enumerate() {
                 epilogue.push(format!("  lw {}, {}(sp)", reg, i * 4));
             }
             epilogue.push(format!("  addi sp, sp, {}", (to_restore.len() * 4) as i32));
         }
+        if let Some(offset) = self.ra_offset {
+            if offset <= 2047 {
+                epilogue.push(format!("  lw ra, {}(sp)", offset));
+            } else {
+                epilogue.push(format!("  li t0, {}", offset));
+                epilogue.push("  add t0, sp, t0".to_string());
+                epilogue.push("  lw ra, 0(t0)".to_string());
+            }
+        }
+        let aligned_size = self.current_stack_offset;
+        if aligned_size > 0 {
+            if aligned_size <= 2047 {
+                epilogue.push(format!("  addi sp, sp, {}", aligned_size));
+            } else {
+                epilogue.push(format!("  li t0, {}", aligned_size));
+                epilogue.push("  add sp, sp, t0".to_string());
+            }
+        }
         epilogue
     }
 }
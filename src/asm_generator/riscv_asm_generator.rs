@@ -1,29 +1,97 @@
 use std::fmt::Write;
 
-use koopa::ir::{FunctionData, Program, Value, ValueKind};
+use koopa::ir::{FunctionData, Program, Type, Value, ValueKind};
 
 use super::AsmGenerator;
 
 impl AsmGenerator {
     pub fn generate_program(&mut self, program: &Program) -> String {
         self.output.clear();
+        Type::set_ptr_size(4);
+
+        self.func_names = program
+            .funcs()
+            .iter()
+            .map(|(&func, data)| (func, data.name()[1..].to_string()))
+            .collect();
+
+        self.generate_globals(program);
 
         writeln!(&mut self.output, ".text").unwrap();
 
+        // 只有声明的函数由运行时库提供
+        for func in program.func_layout() {
+            let data = program.func(*func);
+            if data.layout().entry_bb().is_none() {
+                writeln!(&mut self.output, ".extern {}", &data.name()[1..]).unwrap();
+            }
+        }
+
         for func in program.func_layout() {
-            self.generate_function(program.func(*func));
+            let data = program.func(*func);
+            if data.layout().entry_bb().is_some() {
+                self.generate_function(data);
+            }
         }
         self.output.clone()
     }
 
+    /// 有初始值的全局变量放在 .data, 零初始化的放在 .bss
+    fn generate_globals(&mut self, program: &Program) {
+        self.global_names.clear();
+        for &value in program.inst_layout() {
+            let data = program.borrow_value(value);
+            let name = data.name().as_ref().expect("Global value without name")[1..].to_string();
+            let ValueKind::GlobalAlloc(alloc) = data.kind() else {
+                unreachable!("Global value must be an alloc");
+            };
+            let init = program.borrow_value(alloc.init());
+            let section = match init.kind() {
+                ValueKind::ZeroInit(_) => ".bss",
+                _ => ".data",
+            };
+            writeln!(&mut self.output, "{}", section).unwrap();
+            writeln!(&mut self.output, ".global {}", name).unwrap();
+            writeln!(&mut self.output, "{}:", name).unwrap();
+            self.generate_global_init(program, alloc.init());
+            self.global_names.insert(value, name);
+        }
+        if !program.inst_layout().is_empty() {
+            self.output.push('\n');
+        }
+    }
+
+    fn generate_global_init(&mut self, program: &Program, init: Value) {
+        let data = program.borrow_value(init);
+        match data.kind() {
+            ValueKind::Integer(int) => {
+                writeln!(&mut self.output, "  .word {}", int.value()).unwrap()
+            }
+            ValueKind::ZeroInit(_) => {
+                writeln!(&mut self.output, "  .zero {}", data.ty().size()).unwrap()
+            }
+            ValueKind::Aggregate(agg) => {
+                for &elem in agg.elems() {
+                    self.generate_global_init(program, elem);
+                }
+            }
+            _ => unreachable!("Unsupported global initializer {:?}", data.kind()),
+        }
+    }
+
     pub fn generate_function(&mut self, func: &FunctionData) {
         self.count_value_uses(func);
         let func_name = func.name().strip_prefix("@").unwrap();
         writeln!(&mut self.output, ".global {}", func_name).unwrap(); // .global function
         writeln!(&mut self.output, "{}:", func_name).unwrap(); // function
         self.init_function(func);
-        for (bb, _) in func.layout().bbs() {
-            for (inst, _) in func.layout().bbs().node(bb).unwrap().insts() {
+        for (i, (bb, bb_node)) in func.layout().bbs().iter().enumerate() {
+            if i > 0 {
+                writeln!(&mut self.output, "{}:", self.bb_labels[bb]).unwrap();
+                // 前一个块可能以 ret 结束, 寄存器中的值不能带到这里
+                self.reg_manager.reset_registers();
+            }
+            for (inst, _) in bb_node.insts() {
                 self.generate_instruction(func, *inst);
             }
         }
@@ -55,6 +123,22 @@ impl AsmGenerator {
                         self.increment_use_count(store.value());
                         self.increment_use_count(store.dest());
                     }
+                    ValueKind::Branch(branch) => {
+                        self.increment_use_count(branch.cond());
+                    }
+                    ValueKind::Call(call) => {
+                        for &arg in call.args() {
+                            self.increment_use_count(arg);
+                        }
+                    }
+                    ValueKind::GetElemPtr(gep) => {
+                        self.increment_use_count(gep.src());
+                        self.increment_use_count(gep.index());
+                    }
+                    ValueKind::GetPtr(gp) => {
+                        self.increment_use_count(gp.src());
+                        self.increment_use_count(gp.index());
+                    }
 
                     _ => {}
                 }
@@ -66,7 +150,7 @@ impl AsmGenerator {
         *self.reg_manager.value_use_count.entry(val).or_insert(0) += 1;
     }
 
-    fn get_use_count(&self, val: &Value) -> usize {
+    pub(crate) fn get_use_count(&self, val: &Value) -> usize {
         self.reg_manager
             .value_use_count
             .get(val)
@@ -74,15 +158,7 @@ impl AsmGenerator {
             .unwrap_or(0)
     }
 
-    fn is_last_use(&self, val: &Value) -> bool {
+    pub(crate) fn is_last_use(&self, val: &Value) -> bool {
         self.get_use_count(val) <= 1
     }
-
-    fn decrease_use_count(&mut self, val: &Value) {
-        if let Some(count) = self.reg_manager.value_use_count.get_mut(val) {
-            if *count > 0 {
-                *count -= 1;
-            }
-        }
-    }
 }
@@ -0,0 +1,62 @@
+use anyhow::{bail, ensure, Context};
+use koopa::ir::{FunctionData, Program, ValueKind};
+
+use crate::Result;
+
+// IRBuilder 生成的 IR 只用到 Koopa 的一个子集, 后端也只实现了这个子集.
+// 手写或外部工具生成的 Koopa IR 在进入后端之前先用这里的检查给出明确的错误
+
+/// 检查 `program` 只用到了后端支持的 Koopa 写法
+pub fn check_supported(program: &Program) -> Result<()> {
+    for &value in program.inst_layout() {
+        let data = program.borrow_value(value);
+        let name = data.name().clone().unwrap_or_default();
+        let ValueKind::GlobalAlloc(alloc) = data.kind() else {
+            bail!("Global {} is not an alloc", name);
+        };
+        ensure!(
+            !matches!(
+                program.borrow_value(alloc.init()).kind(),
+                ValueKind::Undef(_)
+            ),
+            "Global {} is initialized with undef",
+            name
+        );
+    }
+    for func in program.func_layout() {
+        let data = program.func(*func);
+        check_function(data).with_context(|| format!("In function {}", data.name()))?;
+    }
+    Ok(())
+}
+
+fn check_function(func: &FunctionData) -> Result<()> {
+    for (&bb, node) in func.layout().bbs() {
+        ensure!(
+            func.dfg().bb(bb).params().is_empty(),
+            "Basic block parameters are not supported"
+        );
+        for &inst in node.insts().keys() {
+            let kind = func.dfg().value(inst).kind();
+            let has_args = match kind {
+                ValueKind::Branch(br) => !br.true_args().is_empty() || !br.false_args().is_empty(),
+                ValueKind::Jump(jump) => !jump.args().is_empty(),
+                _ => false,
+            };
+            ensure!(!has_args, "Basic block arguments are not supported");
+            for value in kind.value_uses() {
+                if value.is_global() {
+                    continue;
+                }
+                match func.dfg().value(value).kind() {
+                    ValueKind::Undef(_) => bail!("undef operands are not supported"),
+                    ValueKind::ZeroInit(_) | ValueKind::Aggregate(_) => {
+                        bail!("Aggregate operands are not supported")
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+    Ok(())
+}
@@ -1,15 +1,16 @@
-use crate::traits::semantic::SymbolTable;
 use crate::{ir_builder::IRBuilder, traits::ToIr};
 
 use super::refactor::{Block, BlockItem};
 use super::Result;
 impl ToIr for Block {
     fn to_ir(&self, builder: &mut IRBuilder) -> Result<()> {
-        builder.enter_scope()?;
         for item in &self.items {
+            // return/break/continue 之后的语句不可达, 直接丢弃, 保证每个基本块只有一条终结指令
+            if builder.is_current_bb_terminated() {
+                break;
+            }
             item.to_ir(builder)?;
         }
-        builder.exit_scope()?;
         Ok(())
     }
 }
@@ -13,7 +13,7 @@ impl ToIr for CompUnitItem {
     fn to_ir(&self, builder: &mut IRBuilder) -> Result<()> {
         match self {
             CompUnitItem::FuncDef(func_def) => func_def.to_ir(builder),
-            CompUnitItem::Decl(_) => unimplemented!(),
+            CompUnitItem::Decl(decl) => decl.to_ir(builder),
         }
     }
 }
@@ -1,30 +1,26 @@
-use anyhow::ensure;
-use anyhow::Context;
 use koopa::ir::Type;
 
+use super::resolved;
 use super::BType;
 use super::ConstDef;
 use super::Decl;
+use super::Exp;
 use super::Result;
 use super::VarDef;
-use crate::semantic::SymbolKind;
-use crate::traits::semantic::ConstEval;
-use crate::traits::semantic::SymbolTable;
+use crate::semantic::{flatten_init, SymbolKind};
 use crate::traits::to_ir::ExpToIr;
 use crate::{ir_builder::IRBuilder, traits::ToIr};
 
 impl ToIr for Decl {
     fn to_ir(&self, builder: &mut IRBuilder) -> Result<()> {
         match self {
-            Decl::VarDecl(_var_decl, vec_defs) => {
+            Decl::VarDecl(_var_decl, vec_defs, _) => {
                 for var_def in vec_defs {
-                    var_def.to_ir(builder).with_context(|| {
-                        format!("Failed to convert variable definition to IR: {:?}", var_def)
-                    })?;
+                    var_def.to_ir(builder)?;
                 }
                 Ok(())
             }
-            Decl::ConstDecl(_const_decl, vec_defs) => {
+            Decl::ConstDecl(_const_decl, vec_defs, _) => {
                 for const_def in vec_defs {
                     const_def.to_ir(builder)?;
                 }
@@ -35,66 +31,77 @@ impl ToIr for Decl {
 }
 
 impl ToIr for ConstDef {
+    /// 标量常量在语义分析时已经折叠进表达式, 只有常量数组需要分配内存,
+    /// 以支持变量下标访问
     fn to_ir(&self, builder: &mut IRBuilder) -> Result<()> {
-        let val = self
-            .value
-            .eval_const(builder)
-            .with_context(|| format!("Invalid const initializer for {}", self.id))?;
-        if builder.contains_var_in_current_scope(&self.id) {
-            return Err(anyhow::anyhow!("Duplicate const definition: {}", self.id));
-        }
-        builder
-            .add_symbol(
-                &self.id,
-                SymbolKind::Const {
-                    value: val,
-                    scope_level: builder.current_scope_level(),
-                },
-            )
-            .with_context(|| format!("Failed to add const symbol {}", self.id))
+        let id = resolved(self.symbol);
+        let symbol = builder.symbol(id).clone();
+        let SymbolKind::ConstArray(values) = &symbol.kind else {
+            return Ok(());
+        };
+        let ty = symbol.ty.to_koop();
+        let value = if symbol.is_global() {
+            builder.create_global_alloc(&format!("@{}", symbol.name), ty, Some(values))
+        } else {
+            let scoped_name = format!("@{}_{}", symbol.name, symbol.scope_level);
+            let alloc = builder.create_alloc(ty, scoped_name)?;
+            let dims = symbol.ty.dims();
+            for (i, val) in values.iter().enumerate() {
+                let ptr = builder.create_elem_ptr(alloc, i, &dims)?;
+                let val = builder.create_constant(*val);
+                builder.create_store(ptr, val)?;
+            }
+            alloc
+        };
+        builder.bind_value(id, value);
+        Ok(())
     }
 }
 
 impl ToIr for VarDef {
     fn to_ir(&self, builder: &mut IRBuilder) -> Result<()> {
-        let ty = match self.ty {
-            BType::Int => Type::get_i32(),
-        };
-        if builder.contains_var_in_current_scope(&self.id) {
-            return Err(anyhow::anyhow!(
-                "Duplicate variable definition: {}",
-                self.id
-            ));
+        let id = resolved(self.symbol);
+        let symbol = builder.symbol(id).clone();
+        let dims = symbol.ty.dims();
+        if symbol.is_global() {
+            // 全局变量的初始值在语义分析时已经折叠成整数
+            let init = match &self.init_val {
+                Some(init) => Some(
+                    flatten_init(init, &dims)?
+                        .into_iter()
+                        .map(|exp| exp.and_then(Exp::as_number).unwrap_or(0))
+                        .collect::<Vec<_>>(),
+                ),
+                None => None,
+            };
+            let ty = symbol.ty.to_koop();
+            let alloc =
+                builder.create_global_alloc(&format!("@{}", symbol.name), ty, init.as_deref());
+            builder.bind_value(id, alloc);
+            return Ok(());
         }
-        let scoped_name = format!("@{}_{}", self.id, builder.current_scope_level());
-
-        let alloc = builder
-            .create_alloc(ty.clone(), scoped_name)
-            .with_context(|| format!("Failed to create alloc for {}", self.id))?;
-        builder
-            .add_symbol(
-                &self.id,
-                SymbolKind::Variable {
-                    value: alloc,
-                    scope_level: builder.current_scope_level(),
-                },
-            )
-            .with_context(|| format!("Failed to add var symbol {}", self.id))?;
 
+        let alloc = builder.create_variable(id, None)?;
         if let Some(init) = &self.init_val {
-            let init_val = init.exp.to_ir(builder)?;
-            let init_ty = builder.value_type(init_val)?;
-            ensure!(
-                init_ty == ty,
-                "Type mismatch: expected {:?}, got {:?}",
-                ty,
-                init_ty.clone()
-            );
-            builder
-                .create_store(alloc, init_val)
-                .with_context(|| format!("Failed to store initial value for {}", self.id))?;
+            let elems = flatten_init(init, &dims)?;
+            for (i, exp) in elems.into_iter().enumerate() {
+                let init_val = match exp {
+                    Some(exp) => exp.to_ir(builder)?,
+                    None => builder.create_constant(0),
+                };
+                let ptr = builder.create_elem_ptr(alloc, i, &dims)?;
+                builder.create_store(ptr, init_val)?;
+            }
         }
 
         Ok(())
     }
 }
+
+impl BType {
+    pub fn to_koop(&self) -> Type {
+        match self {
+            BType::Int => Type::get_i32(),
+        }
+    }
+}
@@ -1,51 +1,65 @@
-use anyhow::Context;
-use koopa::ir::{BinaryOp, Value};
+use koopa::ir::{BinaryOp, Type, TypeKind, Value};
 
+use crate::diagnostic::Span;
 use crate::semantic::SymbolKind;
-use crate::traits::semantic::{ConstEval, SymbolTable};
 
 use crate::{ir_builder::IRBuilder, traits::to_ir::ExpToIr};
 
-use super::refactor::{PrimaryExp, UnaryExp};
-use super::ConstInitVal;
+use super::refactor::{FuncCall, PrimaryExp, UnaryExp};
 use super::{op::UnaryOp, refactor::Exp};
-use super::{LVal, Result};
+use super::{resolved, LVal, Result};
 
 impl LVal {
     pub fn new(id: String) -> Self {
-        Self { id }
+        Self {
+            id,
+            indices: Vec::new(),
+            span: Span::default(),
+            symbol: None,
+        }
     }
+
     pub fn get_address(&self, builder: &mut IRBuilder) -> Result<Value> {
-        let sym = builder
-            .lookup(&self.id)
-            .with_context(|| format!("Undefined identifier: {}", self.id))?;
-        match sym {
-            SymbolKind::Variable {
-                value: alloc,
-                scope_level: _,
-            } => Ok(*alloc),
-
-            SymbolKind::Const {
-                value: _,
-                scope_level: _,
-            } => Err(anyhow::anyhow!("Cannot assign to a constant {}", self.id)),
-            _ => Err(anyhow::anyhow!("Not a variable")),
-        }
+        let base = builder.symbol_value(resolved(self.symbol));
+        self.element_ptr(base, builder)
     }
 
     pub fn load_value(&self, builder: &mut IRBuilder) -> Result<Value> {
-        let sym = builder.lookup(&self.id)?;
-        match sym {
-            SymbolKind::Variable {
-                value: alloc,
-                scope_level: _,
-            } => builder.create_load(*alloc),
-            SymbolKind::Const {
-                value: val,
-                scope_level: _,
-            } => Ok(builder.create_constant(*val)),
-            _ => Err(anyhow::anyhow!("Not a variable")),
+        let id = resolved(self.symbol);
+        if let SymbolKind::Const(value) = builder.symbol(id).kind {
+            return Ok(builder.create_constant(value));
+        }
+        let ptr = self.element_ptr(builder.symbol_value(id), builder)?;
+        let ptr_ty = builder.value_type(ptr)?;
+        match ptr_ty.kind() {
+            // 部分下标的数组作为值使用时退化为指向首元素的指针
+            TypeKind::Pointer(base) if matches!(base.kind(), TypeKind::Array(..)) => {
+                let zero = builder.create_constant(0);
+                builder.create_get_elem_ptr(ptr, zero)
+            }
+            _ => builder.create_load(ptr),
+        }
+    }
+
+    /// 对每个下标生成一次 getelemptr, 数组形参的第一个下标先 load 出指针再用 getptr
+    fn element_ptr(&self, base: Value, builder: &mut IRBuilder) -> Result<Value> {
+        let mut indices = self.indices.iter();
+        let mut ptr = base;
+        let is_pointer_param = matches!(
+            builder.value_type(base)?.kind(),
+            TypeKind::Pointer(base) if matches!(base.kind(), TypeKind::Pointer(_))
+        );
+        if is_pointer_param && !self.indices.is_empty() {
+            let index = indices.next().unwrap();
+            let index_val = index.to_ir(builder)?;
+            let param = builder.create_load(base)?;
+            ptr = builder.create_get_ptr(param, index_val)?;
+        }
+        for index in indices {
+            let index_val = index.to_ir(builder)?;
+            ptr = builder.create_get_elem_ptr(ptr, index_val)?;
         }
+        Ok(ptr)
     }
 }
 
@@ -54,28 +68,59 @@ impl ExpToIr for Exp {
         match self {
             Exp::Primary(primary_exp) => primary_exp.to_ir(builder),
             Exp::UnaryExp(unary_exp) => unary_exp.to_ir(builder),
+            Exp::Binary(lhs, op @ (BinaryOp::And | BinaryOp::Or), rhs) => {
+                short_circuit_to_ir(lhs, *op, rhs, builder)
+            }
             Exp::Binary(lhs, op, rhs) => {
                 let lhs_val = lhs.to_ir(builder)?;
                 let rhs_val = rhs.to_ir(builder)?;
-
-                match op {
-                    BinaryOp::And | BinaryOp::Or => {
-                        let logic_lhs = builder.to_logic_val(lhs_val)?;
-                        let logic_rhs = builder.to_logic_val(rhs_val)?;
-                        builder.create_binary(op, logic_lhs, logic_rhs)
-                    }
-                    _ => builder.create_binary(op, lhs_val, rhs_val),
-                }
+                builder.create_binary(op, lhs_val, rhs_val)
             }
         }
     }
 }
 
+/// `&&`/`||` 按 C 语义短路求值: 左侧的逻辑值先写入结果槽,
+/// 只有当它不能决定结果时才跳到右侧求值并覆盖结果槽
+fn short_circuit_to_ir(
+    lhs: &Exp,
+    op: BinaryOp,
+    rhs: &Exp,
+    builder: &mut IRBuilder,
+) -> Result<Value> {
+    let id = builder.next_label_id();
+    let prefix = match op {
+        BinaryOp::And => "and",
+        _ => "or",
+    };
+    let result = builder.create_alloc(Type::get_i32(), format!("%{}_res_{}", prefix, id))?;
+
+    let lhs_val = lhs.to_ir(builder)?;
+    let lhs_logic = builder.create_logic_val(lhs_val)?;
+    builder.create_store(result, lhs_logic)?;
+
+    let rhs_bb = builder.create_bb(&format!("%{}_rhs_{}", prefix, id))?;
+    let end_bb = builder.create_bb(&format!("%{}_end_{}", prefix, id))?;
+    match op {
+        BinaryOp::And => builder.create_branch(lhs_logic, rhs_bb, end_bb)?,
+        _ => builder.create_branch(lhs_logic, end_bb, rhs_bb)?,
+    }
+
+    builder.set_current_bb(rhs_bb);
+    let rhs_val = rhs.to_ir(builder)?;
+    let rhs_logic = builder.create_logic_val(rhs_val)?;
+    builder.create_store(result, rhs_logic)?;
+    builder.create_jump(end_bb)?;
+
+    builder.set_current_bb(end_bb);
+    builder.create_load(result)
+}
+
 impl ExpToIr for PrimaryExp {
     fn to_ir(&self, builder: &mut IRBuilder) -> Result<Value> {
         match self {
-            PrimaryExp::Number(num) => Ok(builder.create_constant(*num)),
-            PrimaryExp::Exp(exp) => exp.to_ir(builder),
+            PrimaryExp::Number(num, _) => Ok(builder.create_constant(*num)),
+            PrimaryExp::Exp(exp, _) => exp.to_ir(builder),
             PrimaryExp::LVal(lval) => lval.load_value(builder),
         }
     }
@@ -85,13 +130,13 @@ impl ExpToIr for UnaryExp {
     fn to_ir(&self, builder: &mut IRBuilder) -> Result<Value> {
         match self {
             UnaryExp::PrimaryExp(primary_exp) => primary_exp.to_ir(builder),
-            UnaryExp::UnaryOp(op, unary_exp) => {
+            UnaryExp::UnaryOp(op, unary_exp, _) => {
                 let val = unary_exp.to_ir(builder)?;
                 match op {
                     UnaryOp::Plus => Ok(val),
                     UnaryOp::Minus => {
                         let zero = builder.create_constant(0);
-                        builder.create_binary(&BinaryOp::Div, zero, val)
+                        builder.create_binary(&BinaryOp::Sub, zero, val)
                     }
                     UnaryOp::Not => {
                         let zero = builder.create_constant(0);
@@ -99,81 +144,27 @@ impl ExpToIr for UnaryExp {
                     }
                 }
             }
+            UnaryExp::Call(call) => call.to_ir(builder),
         }
     }
 }
 
-impl ConstEval for LVal {
-    fn eval_const(&self, builder: &IRBuilder) -> Result<i32> {
-        let sym = builder.lookup(&self.id)?;
-        match sym {
-            SymbolKind::Const {
-                value: num,
-                scope_level: _,
-            } => Ok(*num),
-            _ => Err(anyhow::anyhow!("Not a constant")),
-        }
-    }
-}
-
-impl ConstEval for ConstInitVal {
-    fn eval_const(&self, builder: &IRBuilder) -> Result<i32> {
-        self.exp.eval_const(builder)
-    }
-}
-
-impl ConstEval for Exp {
-    fn eval_const(&self, builder: &IRBuilder) -> Result<i32> {
-        match self {
-            Exp::Primary(primary_exp) => primary_exp.eval_const(builder),
-            Exp::UnaryExp(unary_exp) => unary_exp.eval_const(builder),
-            Exp::Binary(lhs, op, rhs) => {
-                let lhs_val = lhs.eval_const(builder)?;
-                let rhs_val = rhs.eval_const(builder)?;
-
-                match op {
-                    BinaryOp::Add => Ok(lhs_val + rhs_val),
-                    BinaryOp::Sub => Ok(lhs_val - rhs_val),
-                    BinaryOp::Mul => Ok(lhs_val * rhs_val),
-                    BinaryOp::Div => Ok(lhs_val / rhs_val),
-                    BinaryOp::Mod => Ok(lhs_val % rhs_val),
-                    BinaryOp::Eq => Ok((lhs_val == rhs_val) as i32),
-                    BinaryOp::NotEq => Ok((lhs_val != rhs_val) as i32),
-                    BinaryOp::Ge => Ok((lhs_val >= rhs_val) as i32),
-                    BinaryOp::Gt => Ok((lhs_val > rhs_val) as i32),
-                    BinaryOp::Le => Ok((lhs_val <= rhs_val) as i32),
-                    BinaryOp::Lt => Ok((lhs_val < rhs_val) as i32),
-                    BinaryOp::And => Ok(((lhs_val != 0) && (rhs_val != 0)) as i32),
-                    BinaryOp::Or => Ok(((lhs_val != 0) || (rhs_val != 0)) as i32),
-                    _ => Err(anyhow::anyhow!("Unsupported binary operation")),
-                }
-            }
-        }
-    }
-}
-
-impl ConstEval for PrimaryExp {
-    fn eval_const(&self, builder: &IRBuilder) -> Result<i32> {
-        match self {
-            PrimaryExp::Number(num) => Ok(*num),
-            PrimaryExp::Exp(exp) => exp.eval_const(builder),
-            PrimaryExp::LVal(lval) => lval.eval_const(builder),
-        }
+impl ExpToIr for FuncCall {
+    fn to_ir(&self, builder: &mut IRBuilder) -> Result<Value> {
+        let func = builder.symbol_function(resolved(self.symbol));
+        let args = self
+            .args
+            .iter()
+            .map(|arg| arg.to_ir(builder))
+            .collect::<Result<Vec<_>>>()?;
+        builder.create_call(func, args)
     }
 }
 
-impl ConstEval for UnaryExp {
-    fn eval_const(&self, builder: &IRBuilder) -> Result<i32> {
-        match self {
-            UnaryExp::PrimaryExp(primary_exp) => primary_exp.eval_const(builder),
-            UnaryExp::UnaryOp(op, unary_exp) => {
-                let val = unary_exp.eval_const(builder)?;
-                match op {
-                    UnaryOp::Plus => Ok(val),
-                    UnaryOp::Minus => Ok(-val),
-                    UnaryOp::Not => Ok((val == 0) as i32),
-                }
-            }
-        }
-    }
+/// 整数字面量按 32 位无符号数解析, 超过 i32::MAX 的值像 C 一样回绕,
+/// 这样 `-2147483648` 和 `0xFFFFFFFF` 都能表示; 超出 32 位时返回 None
+pub fn parse_int_literal(digits: &str, radix: u32) -> Option<i32> {
+    u32::from_str_radix(digits, radix)
+        .ok()
+        .map(|num| num as i32)
 }
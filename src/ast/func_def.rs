@@ -1,27 +1,52 @@
-use std::vec;
-
-use koopa::ir::Type;
-
 use super::refactor::{FuncDef, FuncType};
-use super::Result;
-use crate::{ast::Block, ir_builder::IRBuilder, traits::ToIr};
+use super::{resolved, Result};
+use crate::semantic::Type;
+use crate::{ir_builder::IRBuilder, traits::ToIr};
 
 impl ToIr for FuncDef {
     fn to_ir(&self, builder: &mut IRBuilder) -> Result<()> {
-        let name = format!("@{}", &self.id);
-        builder.create_function(&name, vec![], self.func_type.to_koop());
+        let id = resolved(self.symbol);
+        let symbol = builder.symbol(id).clone();
+        let Type::Function(param_types, ret) = &symbol.ty else {
+            unreachable!("Function symbol must have a function type");
+        };
+        let params = self
+            .params
+            .iter()
+            .zip(param_types)
+            .map(|(param, ty)| (format!("%{}", param.id), ty.to_koop()))
+            .collect();
+        let func = builder.create_function(&format!("@{}", symbol.name), params, ret.to_koop());
+        builder.bind_function(id, func);
         let entry = builder.create_bb("%entry")?;
         builder.set_current_bb(entry);
 
+        // 形参先存入局部变量, 之后可以像普通变量一样读写
+        let param_values = builder.current_func_params()?;
+        for (param, value) in self.params.iter().zip(param_values) {
+            builder.create_variable(resolved(param.symbol), Some(value))?;
+        }
         self.block.to_ir(builder)?;
+
+        // 函数体能执行到结尾时补上返回, 缺少返回值已由语义分析警告
+        if !builder.is_current_bb_terminated() {
+            match self.func_type {
+                FuncType::Int => {
+                    let zero = builder.create_constant(0);
+                    builder.create_ret(Some(zero))?;
+                }
+                FuncType::Void => builder.create_ret(None)?,
+            }
+        }
         Ok(())
     }
 }
 
 impl FuncType {
-    pub fn to_koop(&self) -> Type {
+    pub fn to_type(&self) -> Type {
         match self {
-            FuncType::Int => Type::get_i32(),
+            FuncType::Int => Type::Int,
+            FuncType::Void => Type::Void,
         }
     }
 }
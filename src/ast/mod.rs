@@ -11,3 +11,10 @@ pub mod stmt;
 pub use anyhow::Result;
 pub use op::{Op, UnaryOp};
 pub use refactor::*;
+
+use crate::semantic::SymbolId;
+
+/// IR 生成只处理通过语义分析的 AST, 此时所有名字都已解析
+pub(crate) fn resolved(symbol: Option<SymbolId>) -> SymbolId {
+    symbol.expect("Symbol must be resolved by semantic analysis")
+}
@@ -1,40 +1,59 @@
 use koopa::ir::BinaryOp;
 
 use super::UnaryOp;
+use crate::diagnostic::Span;
+use crate::semantic::SymbolId;
 
-#[derive(Debug)]
+// `symbol` 字段由语义分析填写, 解析时为 None
+
+#[derive(Debug, Clone)]
 pub struct CompUnit {
     pub items: Vec<CompUnitItem>,
+    pub span: Span,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum CompUnitItem {
     Decl(Decl),
     FuncDef(FuncDef),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct FuncDef {
     pub func_type: FuncType,
     pub id: String,
+    pub params: Vec<FuncFParam>,
     pub block: Block,
+    pub span: Span,
+    pub symbol: Option<SymbolId>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+pub struct FuncFParam {
+    pub ty: BType,
+    pub id: String,
+    // 数组形参除第一维以外的各维长度, 标量形参为 None
+    pub dims: Option<Vec<Exp>>,
+    pub span: Span,
+    pub symbol: Option<SymbolId>,
+}
+
+#[derive(Debug, Clone)]
 pub struct Block {
     pub items: Vec<BlockItem>,
+    pub span: Span,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum BlockItem {
     Decl(Decl),
     Stmt(Stmt),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum Decl {
-    ConstDecl(BType, Vec<ConstDef>),
-    VarDecl(BType, Vec<VarDef>),
+    ConstDecl(BType, Vec<ConstDef>, Span),
+    VarDecl(BType, Vec<VarDef>, Span),
 }
 
 #[derive(Debug, Clone)]
@@ -42,40 +61,55 @@ pub enum BType {
     Int,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ConstDef {
     pub id: String,
+    pub dims: Vec<Exp>,
     pub value: ConstInitVal,
+    pub span: Span,
+    pub symbol: Option<SymbolId>,
 }
 
-#[derive(Debug)]
-pub struct ConstInitVal {
-    pub exp: Box<Exp>,
+#[derive(Debug, Clone)]
+pub enum ConstInitVal {
+    Exp(Box<Exp>),
+    List(Vec<ConstInitVal>, Span),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct VarDef {
     pub id: String,
     pub ty: BType,
+    pub dims: Vec<Exp>,
     pub init_val: Option<InitVal>,
+    pub span: Span,
+    pub symbol: Option<SymbolId>,
 }
 
-#[derive(Debug)]
-pub struct InitVal {
-    pub exp: Box<Exp>,
+#[derive(Debug, Clone)]
+pub enum InitVal {
+    Exp(Box<Exp>),
+    List(Vec<InitVal>, Span),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum Stmt {
-    Return(Option<Exp>),
-    Exp(Option<Exp>),
+    Return(Option<Exp>, Span),
+    Exp(Option<Exp>, Span),
     Block(Block),
-    Assign(LVal, Exp),
+    Assign(LVal, Exp, Span),
+    If(Exp, Box<Stmt>, Option<Box<Stmt>>, Span),
+    While(Exp, Box<Stmt>, Span),
+    Break(Span),
+    Continue(Span),
 }
 
 #[derive(Debug, Clone)]
 pub struct LVal {
     pub id: String,
+    pub indices: Vec<Exp>,
+    pub span: Span,
+    pub symbol: Option<SymbolId>,
 }
 
 #[derive(Debug, Clone)]
@@ -88,17 +122,145 @@ pub enum Exp {
 #[derive(Debug, Clone)]
 pub enum UnaryExp {
     PrimaryExp(PrimaryExp),
-    UnaryOp(UnaryOp, Box<UnaryExp>),
+    UnaryOp(UnaryOp, Box<UnaryExp>, Span),
+    Call(FuncCall),
+}
+
+#[derive(Debug, Clone)]
+pub struct FuncCall {
+    pub id: String,
+    pub args: Vec<Exp>,
+    pub span: Span,
+    pub symbol: Option<SymbolId>,
 }
 
 #[derive(Debug, Clone)]
 pub enum PrimaryExp {
-    Number(i32),
+    Number(i32, Span),
     LVal(LVal),
-    Exp(Box<Exp>),
+    // 括号表达式, 区间包含括号
+    Exp(Box<Exp>, Span),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum FuncType {
     Int,
+    Void,
+}
+
+impl CompUnitItem {
+    pub fn span(&self) -> Span {
+        match self {
+            CompUnitItem::Decl(decl) => decl.span(),
+            CompUnitItem::FuncDef(func_def) => func_def.span,
+        }
+    }
+}
+
+impl BlockItem {
+    pub fn span(&self) -> Span {
+        match self {
+            BlockItem::Decl(decl) => decl.span(),
+            BlockItem::Stmt(stmt) => stmt.span(),
+        }
+    }
+}
+
+impl Decl {
+    pub fn span(&self) -> Span {
+        match self {
+            Decl::ConstDecl(_, _, span) | Decl::VarDecl(_, _, span) => *span,
+        }
+    }
+}
+
+impl ConstInitVal {
+    pub fn span(&self) -> Span {
+        match self {
+            ConstInitVal::Exp(exp) => exp.span(),
+            ConstInitVal::List(_, span) => *span,
+        }
+    }
+}
+
+impl InitVal {
+    pub fn span(&self) -> Span {
+        match self {
+            InitVal::Exp(exp) => exp.span(),
+            InitVal::List(_, span) => *span,
+        }
+    }
+}
+
+impl Stmt {
+    pub fn span(&self) -> Span {
+        match self {
+            Stmt::Block(block) => block.span,
+            Stmt::Return(_, span)
+            | Stmt::Exp(_, span)
+            | Stmt::Assign(_, _, span)
+            | Stmt::If(_, _, _, span)
+            | Stmt::While(_, _, span)
+            | Stmt::Break(span)
+            | Stmt::Continue(span) => *span,
+        }
+    }
+}
+
+impl Exp {
+    pub fn span(&self) -> Span {
+        match self {
+            Exp::Primary(primary) => primary.span(),
+            Exp::UnaryExp(unary) => unary.span(),
+            Exp::Binary(lhs, _, rhs) => lhs.span().to(rhs.span()),
+        }
+    }
+}
+
+impl UnaryExp {
+    pub fn span(&self) -> Span {
+        match self {
+            UnaryExp::PrimaryExp(primary) => primary.span(),
+            UnaryExp::UnaryOp(_, _, span) => *span,
+            UnaryExp::Call(call) => call.span,
+        }
+    }
+}
+
+impl PrimaryExp {
+    pub fn span(&self) -> Span {
+        match self {
+            PrimaryExp::Number(_, span) | PrimaryExp::Exp(_, span) => *span,
+            PrimaryExp::LVal(lval) => lval.span,
+        }
+    }
+}
+
+// 常量折叠后的表达式是一个整数字面量
+impl Exp {
+    pub fn as_number(&self) -> Option<i32> {
+        match self {
+            Exp::Primary(primary) => primary.as_number(),
+            Exp::UnaryExp(unary) => unary.as_number(),
+            Exp::Binary(..) => None,
+        }
+    }
+}
+
+impl UnaryExp {
+    pub fn as_number(&self) -> Option<i32> {
+        match self {
+            UnaryExp::PrimaryExp(primary) => primary.as_number(),
+            _ => None,
+        }
+    }
+}
+
+impl PrimaryExp {
+    pub fn as_number(&self) -> Option<i32> {
+        match self {
+            PrimaryExp::Number(num, _) => Some(*num),
+            _ => None,
+        }
+    }
 }
@@ -8,28 +8,83 @@ use anyhow::Result;
 impl ToIr for Stmt {
     fn to_ir(&self, builder: &mut IRBuilder) -> Result<()> {
         match self {
-            Stmt::Return(exp) => match exp {
-                Some(exp) => {
-                    let val = exp.to_ir(builder)?;
-                    builder.create_ret(Some(val));
-                }
-                None => {
-                    builder.create_ret(None);
-                }
-            },
-            Stmt::Assign(lval, exp) => {
+            Stmt::Return(exp, _) => {
+                let val = match exp {
+                    Some(exp) => Some(exp.to_ir(builder)?),
+                    None => None,
+                };
+                builder.create_ret(val)?;
+            }
+            Stmt::Assign(lval, exp, _) => {
                 let addr = lval.get_address(builder)?;
                 let val = exp.to_ir(builder)?;
 
                 builder.create_store(addr, val)?;
             }
-            Stmt::Exp(exp) => match exp {
-                Some(exp) => {
+            Stmt::Exp(exp, _) => {
+                if let Some(exp) = exp {
                     exp.to_ir(builder)?;
                 }
-                None => {}
-            },
+            }
             Stmt::Block(block) => block.to_ir(builder)?,
+            Stmt::If(cond, then, els, _) => {
+                let cond_val = cond.to_ir(builder)?;
+                let id = builder.next_label_id();
+                let then_bb = builder.create_bb(&format!("%then_{}", id))?;
+                let else_bb = match els {
+                    Some(_) => Some(builder.create_bb(&format!("%else_{}", id))?),
+                    None => None,
+                };
+                let end_bb = builder.create_bb(&format!("%end_{}", id))?;
+
+                builder.create_branch(cond_val, then_bb, else_bb.unwrap_or(end_bb))?;
+
+                builder.set_current_bb(then_bb);
+                then.to_ir(builder)?;
+                builder.create_jump_if_open(end_bb)?;
+
+                if let (Some(els), Some(else_bb)) = (els, else_bb) {
+                    builder.set_current_bb(else_bb);
+                    els.to_ir(builder)?;
+                    builder.create_jump_if_open(end_bb)?;
+                }
+
+                // 两个分支都以 return/break/continue 结束时 %end 块不可达, 不再生成,
+                // 当前块保持已终结的状态, 之后的语句随之被丢弃
+                if builder.is_bb_used(end_bb) {
+                    builder.set_current_bb(end_bb);
+                } else {
+                    builder.remove_bb(end_bb);
+                }
+            }
+            Stmt::While(cond, body, _) => {
+                let id = builder.next_label_id();
+                let cond_bb = builder.create_bb(&format!("%while_cond_{}", id))?;
+                let body_bb = builder.create_bb(&format!("%while_body_{}", id))?;
+                let end_bb = builder.create_bb(&format!("%while_end_{}", id))?;
+
+                builder.create_jump(cond_bb)?;
+                builder.set_current_bb(cond_bb);
+                let cond_val = cond.to_ir(builder)?;
+                builder.create_branch(cond_val, body_bb, end_bb)?;
+
+                builder.set_current_bb(body_bb);
+                builder.enter_loop(cond_bb, end_bb);
+                body.to_ir(builder)?;
+                builder.exit_loop();
+                builder.create_jump_if_open(cond_bb)?;
+
+                builder.set_current_bb(end_bb);
+            }
+            // 语义分析保证 break/continue 位于循环内
+            Stmt::Break(_) => {
+                let ctx = builder.current_loop().expect("break outside of a loop");
+                builder.create_jump(ctx.end_bb)?;
+            }
+            Stmt::Continue(_) => {
+                let ctx = builder.current_loop().expect("continue outside of a loop");
+                builder.create_jump(ctx.cond_bb)?;
+            }
         }
 
         Ok(())
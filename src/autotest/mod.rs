@@ -0,0 +1,243 @@
+mod report;
+
+use std::fs::{read_dir, read_to_string};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Context};
+use koopa::ir::Program;
+
+use crate::asm_generator::AsmGenerator;
+use crate::interpreter::{run_program, TimeLimitExceeded};
+use crate::ir_printer::{parse_program, IRPrinter};
+use crate::riscv_sim::run_asm;
+use crate::Result;
+pub use report::{CaseResult, Report};
+
+// 本地的 autotest: 在测试目录中找到 `.sy`/`.c` 源文件以及同名的 `.in`/`.out`,
+// 编译后分别用 Koopa 解释器和 RISC-V 模拟器执行, 按课程 autotest 的格式比较输出.
+// SysY 的语法分析器生成在可执行文件中, 所以由调用者提供从源文件到 Program 的编译函数
+
+/// 每个用例默认最多执行的指令条数, 死循环的用例按超时失败
+pub const DEFAULT_STEP_LIMIT: u64 = 100_000_000;
+
+/// 源文件编译为 Koopa IR 程序
+pub type Compile<'a> = dyn Fn(&Path) -> Result<Program> + 'a;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    Koopa,
+    Riscv,
+}
+
+impl Target {
+    pub fn name(self) -> &'static str {
+        match self {
+            Target::Koopa => "koopa",
+            Target::Riscv => "riscv",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TestCase {
+    /// 所在目录的名字, 如 `lv1`
+    pub level: String,
+    pub name: String,
+    pub source: PathBuf,
+    pub input: Option<PathBuf>,
+    pub expected: PathBuf,
+}
+
+/// 找出 `root` 下的所有测试. `root` 中直接含有源文件时它本身就是一个 level,
+/// 否则每个子目录是一个 level
+pub fn discover(root: &Path) -> Result<Vec<TestCase>> {
+    let mut cases = cases_in(root)?;
+    if cases.is_empty() {
+        let mut dirs = entries(root)?
+            .into_iter()
+            .filter(|path| path.is_dir())
+            .collect::<Vec<_>>();
+        dirs.sort_by_key(|dir| natural_key(&file_name(dir)));
+        for dir in dirs {
+            cases.extend(cases_in(&dir)?);
+        }
+    }
+    Ok(cases)
+}
+
+fn cases_in(dir: &Path) -> Result<Vec<TestCase>> {
+    let level = file_name(dir);
+    let mut sources = entries(dir)?
+        .into_iter()
+        .filter(|path| {
+            path.is_file() && matches!(path.extension().and_then(|e| e.to_str()), Some("sy" | "c"))
+        })
+        .collect::<Vec<_>>();
+    sources.sort_by_key(|path| natural_key(&file_name(path)));
+    Ok(sources
+        .into_iter()
+        .map(|source| {
+            let input = source.with_extension("in");
+            TestCase {
+                level: level.clone(),
+                name: source
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().into_owned())
+                    .unwrap_or_default(),
+                input: input.is_file().then_some(input),
+                expected: source.with_extension("out"),
+                source,
+            }
+        })
+        .collect())
+}
+
+fn entries(dir: &Path) -> Result<Vec<PathBuf>> {
+    read_dir(dir)
+        .with_context(|| format!("Failed to read directory {}", dir.display()))?
+        .map(|entry| Ok(entry?.path()))
+        .collect()
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// 按开头的数字排序, `lv9` 在 `lv10` 之前, `2_if` 在 `10_while` 之前
+fn natural_key(name: &str) -> (String, u64, String) {
+    let prefix: String = name.chars().take_while(|c| !c.is_ascii_digit()).collect();
+    let digits: String = name[prefix.len()..]
+        .chars()
+        .take_while(|c| c.is_ascii_digit())
+        .collect();
+    (prefix, digits.parse().unwrap_or(0), name.to_string())
+}
+
+/// 对 `root` 下的所有测试, 在每个 target 上编译运行并比较输出.
+/// 每次运行最多执行 `step_limit` 条指令
+pub fn run(root: &Path, targets: &[Target], step_limit: u64, compile: &Compile) -> Result<Report> {
+    let cases = discover(root)?;
+    if cases.is_empty() {
+        bail!("No test cases found in {}", root.display());
+    }
+    let mut results = Vec::new();
+    for case in cases {
+        // 每个用例只编译一次, 两个 target 共用同一个 Program
+        let program = guard(|| compile(&case.source)).context("Compile error");
+        for &target in targets {
+            let outcome = match &program {
+                Ok(program) => guard(|| run_case(&case, target, program, step_limit)),
+                Err(err) => Err(anyhow!("{:#}", err)),
+            };
+            results.push(CaseResult {
+                case: case.clone(),
+                target,
+                outcome: outcome.map_err(|err| format!("{:#}", err)),
+            });
+        }
+    }
+    Ok(Report::new(targets.to_vec(), results))
+}
+
+/// 编译器中的 panic 算作这个用例失败, 不影响其余用例
+pub(crate) fn guard<T>(f: impl FnOnce() -> Result<T>) -> Result<T> {
+    catch_unwind(AssertUnwindSafe(f)).unwrap_or_else(|payload| {
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_default();
+        Err(anyhow!("Compiler panicked: {}", message))
+    })
+}
+
+fn run_case(case: &TestCase, target: Target, program: &Program, step_limit: u64) -> Result<()> {
+    let input = match &case.input {
+        Some(path) => read_to_string(path)?,
+        None => String::new(),
+    };
+    let expected = read_to_string(&case.expected)
+        .with_context(|| format!("Missing expected output {}", case.expected.display()))?;
+    let actual = execute(program, target, &input, step_limit)?;
+    check_output(&expected, &actual)
+}
+
+/// 在 `target` 上执行程序, 返回与 `.out` 文件格式相同的输出.
+/// 超过 `step_limit` 条指令时报告超时
+pub fn execute(program: &Program, target: Target, input: &str, step_limit: u64) -> Result<String> {
+    let mut stdout = Vec::new();
+    let exit_code = match target {
+        // 经过文本形式, 打印器和解析器也一起被测试
+        Target::Koopa => {
+            let text = IRPrinter::new().print_program(program);
+            let program = parse_program(&text)?;
+            run_program(
+                &program,
+                &mut input.as_bytes(),
+                &mut stdout,
+                Some(step_limit),
+            )
+            .map_err(runtime_error)?
+        }
+        Target::Riscv => {
+            let asm = AsmGenerator::new().generate_program(program);
+            run_asm(&asm, &mut input.as_bytes(), &mut stdout, Some(step_limit))
+                .map_err(runtime_error)?
+                .exit_code
+        }
+    };
+    Ok(format_output(&String::from_utf8_lossy(&stdout), exit_code))
+}
+
+/// 超时单独报告, 不带出错位置
+fn runtime_error(err: anyhow::Error) -> anyhow::Error {
+    if err.downcast_ref::<TimeLimitExceeded>().is_some() {
+        anyhow!(TimeLimitExceeded)
+    } else {
+        err.context("Runtime error")
+    }
+}
+
+/// 与课程 autotest 相同: 程序输出之后另起一行写退出码
+pub(crate) fn format_output(stdout: &str, exit_code: i32) -> String {
+    let mut output = stdout.to_string();
+    if !output.is_empty() && !output.ends_with('\n') {
+        output.push('\n');
+    }
+    output.push_str(&(exit_code as u8).to_string());
+    output
+}
+
+/// 忽略行尾空白和末尾的空行, 报告第一处不同
+pub(crate) fn check_output(expected: &str, actual: &str) -> Result<()> {
+    let normalize = |text: &str| -> Vec<String> {
+        let mut lines: Vec<String> = text
+            .lines()
+            .map(|line| line.trim_end().to_string())
+            .collect();
+        while lines.last().is_some_and(|line| line.is_empty()) {
+            lines.pop();
+        }
+        lines
+    };
+    let (expected, actual) = (normalize(expected), normalize(actual));
+    let Some(line) =
+        (0..expected.len().max(actual.len())).find(|&i| expected.get(i) != actual.get(i))
+    else {
+        return Ok(());
+    };
+    let show = |lines: &[String]| {
+        lines
+            .get(line)
+            .map_or("<end of output>".to_string(), |l| format!("{:?}", l))
+    };
+    bail!(
+        "Wrong answer at line {}: expected {}, got {}",
+        line + 1,
+        show(&expected),
+        show(&actual)
+    )
+}
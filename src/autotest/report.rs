@@ -0,0 +1,78 @@
+use std::fmt::Write;
+
+use super::{Target, TestCase};
+
+/// 一个用例在一个 target 上的结果, 失败时带原因
+pub struct CaseResult {
+    pub case: TestCase,
+    pub target: Target,
+    pub outcome: Result<(), String>,
+}
+
+pub struct Report {
+    targets: Vec<Target>,
+    pub results: Vec<CaseResult>,
+}
+
+impl Report {
+    pub fn new(targets: Vec<Target>, results: Vec<CaseResult>) -> Self {
+        Self { targets, results }
+    }
+
+    pub fn all_passed(&self) -> bool {
+        self.results.iter().all(|result| result.outcome.is_ok())
+    }
+
+    /// 先列出失败的用例, 再输出每个 level 在各个 target 上的通过数
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        for result in &self.results {
+            if let Err(reason) = &result.outcome {
+                writeln!(
+                    out,
+                    "FAILED [{}] {}/{}: {}",
+                    result.target.name(),
+                    result.case.level,
+                    result.case.name,
+                    reason
+                )
+                .unwrap();
+            }
+        }
+        if !self.all_passed() {
+            out.push('\n');
+        }
+
+        let mut levels: Vec<&str> = Vec::new();
+        for result in &self.results {
+            if !levels.contains(&result.case.level.as_str()) {
+                levels.push(&result.case.level);
+            }
+        }
+        write!(out, "{:<12}", "level").unwrap();
+        for target in &self.targets {
+            write!(out, "{:>14}", target.name()).unwrap();
+        }
+        out.push('\n');
+        for level in levels.iter().copied().map(Some).chain([None]) {
+            write!(out, "{:<12}", level.unwrap_or("total")).unwrap();
+            for &target in &self.targets {
+                let (passed, total) = self.count(level, target);
+                let mark = if passed == total { "" } else { " !" };
+                write!(out, "{:>14}", format!("{}/{}{}", passed, total, mark)).unwrap();
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    /// `level` 为 None 时统计全部用例
+    fn count(&self, level: Option<&str>, target: Target) -> (usize, usize) {
+        let results = self.results.iter().filter(|result| {
+            result.target == target && level.is_none_or(|level| result.case.level == level)
+        });
+        results.fold((0, 0), |(passed, total), result| {
+            (passed + result.outcome.is_ok() as usize, total + 1)
+        })
+    }
+}
@@ -0,0 +1,221 @@
+use std::collections::BTreeSet;
+use std::fmt;
+
+use lalrpop_util::ParseError;
+
+/// 源码中的字节区间 `[start, end)`, 由 LALRPOP 的 `@L`/`@R` 得到
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    /// 从 `self` 的开头到 `other` 的结尾
+    pub fn to(self, other: Span) -> Span {
+        Span::new(self.start, other.end)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// 带源码位置的编译错误, 作为 anyhow::Error 在各层之间传递
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub span: Span,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn new(span: Span, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Error,
+            span,
+            message: message.into(),
+        }
+    }
+
+    /// 不影响编译结果的警告
+    pub fn warning(span: Span, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            span,
+            message: message.into(),
+        }
+    }
+
+    /// 渲染成 `file:line:col: error: ...` (或 `warning: ...`), 附带源码行和下划线
+    pub fn render(&self, file: &str, source: &str) -> String {
+        // 区间的端点可能落在多字节字符中间, 先退到字符边界
+        let start = char_boundary(source, self.span.start);
+        let (line, col) = line_col(source, start);
+        let line_start = source[..start].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = source[line_start..]
+            .find('\n')
+            .map_or(source.len(), |i| line_start + i);
+        let text = source[line_start..line_end].trim_end_matches('\r');
+        // 跨行的区间只标到第一行末尾
+        let end = char_boundary(source, self.span.end).clamp(start, line_end);
+        let width = source[start..end].chars().count().max(1);
+        let gutter = " ".repeat(line.to_string().len());
+        format!(
+            "{}:{}:{}: {}: {}\n{} |\n{} | {}\n{} | {}{}\n",
+            file,
+            line,
+            col,
+            self.severity,
+            self.message,
+            gutter,
+            line,
+            text,
+            gutter,
+            " ".repeat(col - 1),
+            "^".repeat(width)
+        )
+    }
+}
+
+impl Diagnostic {
+    /// LALRPOP 的语法错误, 需要源码来确定非法字符的长度
+    pub fn from_parse_error<T: fmt::Display>(
+        err: &ParseError<usize, T, Diagnostic>,
+        source: &str,
+    ) -> Self {
+        match err {
+            ParseError::InvalidToken { location } => {
+                let len = source[char_boundary(source, *location)..]
+                    .chars()
+                    .next()
+                    .map_or(0, char::len_utf8);
+                Diagnostic::new(Span::new(*location, *location + len), "Invalid token")
+            }
+            ParseError::UnrecognizedEof { location, expected } => Diagnostic::new(
+                Span::new(*location, *location),
+                format!("Unexpected end of file{}", expected_list(expected)),
+            ),
+            ParseError::UnrecognizedToken {
+                token: (l, token, r),
+                expected,
+            } => Diagnostic::new(
+                Span::new(*l, *r),
+                format!("Unexpected `{}`{}", token, expected_list(expected)),
+            ),
+            ParseError::ExtraToken {
+                token: (l, token, r),
+            } => Diagnostic::new(Span::new(*l, *r), format!("Extra token `{}`", token)),
+            ParseError::User { error } => error.clone(),
+        }
+    }
+}
+
+/// `, expected one of ...`, 没有候选时为空. 几个正则可能对应同一个名字, 排序后去重
+fn expected_list(expected: &[String]) -> String {
+    let names: Vec<String> = expected
+        .iter()
+        .map(|name| token_name(name))
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect();
+    match names.len() {
+        0 => String::new(),
+        1 => format!(", expected {}", names[0]),
+        _ => format!(", expected one of {}", names.join(", ")),
+    }
+}
+
+/// 把 LALRPOP 中的终结符名字 (带引号的字面量或正则) 转换成易读的形式
+fn token_name(name: &str) -> String {
+    if let Some(regex) = name
+        .strip_prefix("r#\"")
+        .and_then(|s| s.strip_suffix("\"#"))
+    {
+        return match regex {
+            "[_a-zA-Z][_a-zA-Z0-9]*" => "identifier".to_string(),
+            "[1-9][0-9]*" | "0[0-7]*" | "0[xX][0-9a-fA-F]+" => "integer literal".to_string(),
+            _ => regex.to_string(),
+        };
+    }
+    let literal = name
+        .strip_prefix('"')
+        .and_then(|s| s.strip_suffix('"'))
+        .unwrap_or(name);
+    format!("`{}`", literal)
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for Diagnostic {}
+
+/// 不超过 `offset` 的最大字符边界
+fn char_boundary(source: &str, offset: usize) -> usize {
+    let mut offset = offset.min(source.len());
+    while !source.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    offset
+}
+
+/// 字节偏移对应的行号和列号, 都从 1 开始, 列号按字符计
+pub fn line_col(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..char_boundary(source, offset)];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    (line, before[line_start..].chars().count() + 1)
+}
+
+/// 在 `span` 处报错并返回, 用法同 `anyhow::bail!`
+#[macro_export]
+macro_rules! bail_at {
+    ($span:expr, $($arg:tt)*) => {
+        return Err($crate::diagnostic::Diagnostic::new($span, format!($($arg)*)).into())
+    };
+}
+
+/// 条件不成立时在 `span` 处报错, 用法同 `anyhow::ensure!`
+#[macro_export]
+macro_rules! ensure_at {
+    ($cond:expr, $span:expr, $($arg:tt)*) => {
+        if !$cond {
+            $crate::bail_at!($span, $($arg)*);
+        }
+    };
+}
+
+/// 给还没有位置信息的错误 (例如 IRBuilder 的类型检查) 补上源码位置
+pub trait WithSpan<T> {
+    fn at(self, span: Span) -> anyhow::Result<T>;
+}
+
+impl<T> WithSpan<T> for anyhow::Result<T> {
+    fn at(self, span: Span) -> anyhow::Result<T> {
+        self.map_err(|err| {
+            if err.chain().any(|e| e.is::<Diagnostic>()) {
+                err
+            } else {
+                Diagnostic::new(span, format!("{:#}", err)).into()
+            }
+        })
+    }
+}
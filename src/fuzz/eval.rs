@@ -0,0 +1,506 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{BufRead, Write};
+
+use anyhow::{anyhow, bail, Context};
+use koopa::ir::BinaryOp;
+
+use crate::ast::{
+    Block, BlockItem, CompUnit, CompUnitItem, Decl, Exp, FuncCall, FuncDef, LVal, PrimaryExp, Stmt,
+    UnaryExp, UnaryOp,
+};
+use crate::interpreter::{read_byte, read_int, TimeLimitExceeded};
+use crate::semantic::flatten_init;
+use crate::Result;
+
+// 直接在 AST 上求值的参考实现, 作为差分测试中的标准答案.
+// 求值的是语义分析之前的 AST: 名字在这里按作用域解析, 常量表达式也在这里求值,
+// 不依赖编译器的名字解析和常量折叠.
+// 与编译出的程序不同, 这里把有符号溢出、除零、越界和读未初始化的变量
+// 都报告为未定义行为, 生成器据此丢弃有问题的程序
+
+/// 程序执行了未定义行为, 结果不能用来比较
+#[derive(Debug)]
+pub struct UndefinedBehavior(pub String);
+
+impl fmt::Display for UndefinedBehavior {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Undefined behavior: {}", self.0)
+    }
+}
+
+impl std::error::Error for UndefinedBehavior {}
+
+macro_rules! ub {
+    ($($arg:tt)*) => {
+        return Err(UndefinedBehavior(format!($($arg)*)).into())
+    };
+}
+
+/// 对语义分析之前的 AST 求值, 返回 main 的返回值. 程序应当已经通过语义分析.
+/// 执行的语句和表达式超过 `step_limit` 个时返回 [`TimeLimitExceeded`]
+pub fn eval_program(
+    ast: &CompUnit,
+    input: &mut dyn BufRead,
+    output: &mut dyn Write,
+    step_limit: Option<u64>,
+) -> Result<i32> {
+    let mut evaluator = Evaluator {
+        steps: 0,
+        step_limit,
+        funcs: HashMap::new(),
+        memory: Vec::new(),
+        globals: HashMap::new(),
+        frames: Vec::new(),
+        input,
+        output,
+    };
+    let code = evaluator.run(ast)?;
+    evaluator.output.flush()?;
+    Ok(code)
+}
+
+/// 变量的存储位置: 对象和其中的起始下标
+#[derive(Debug, Clone, Copy)]
+struct Slot {
+    object: usize,
+    offset: usize,
+}
+
+/// 名字对应的变量. 数组形参是指针, `dims` 不含长度未知的第一维
+#[derive(Debug, Clone)]
+struct Var {
+    slot: Slot,
+    dims: Vec<usize>,
+    pointer: bool,
+}
+
+/// 语句执行后的控制流
+enum Flow {
+    Normal,
+    Break,
+    Continue,
+    Return(Option<i32>),
+}
+
+struct Evaluator<'a> {
+    steps: u64,
+    step_limit: Option<u64>,
+    funcs: HashMap<&'a str, &'a FuncDef>,
+    // 每个对象是一组 i32, None 表示尚未初始化
+    memory: Vec<Vec<Option<i32>>>,
+    globals: HashMap<String, Var>,
+    // 每个函数调用一层, 其中每个块一个作用域
+    frames: Vec<Vec<HashMap<String, Var>>>,
+    input: &'a mut dyn BufRead,
+    output: &'a mut dyn Write,
+}
+
+impl<'a> Evaluator<'a> {
+    fn run(&mut self, ast: &'a CompUnit) -> Result<i32> {
+        for item in &ast.items {
+            match item {
+                CompUnitItem::Decl(decl) => self.decl(decl, true)?,
+                CompUnitItem::FuncDef(func) => {
+                    self.funcs.insert(&func.id, func);
+                }
+            }
+        }
+        let main = self
+            .funcs
+            .get("main")
+            .copied()
+            .ok_or_else(|| anyhow!("Program has no main function"))?;
+        let ret = self.call_func(main, Vec::new())?;
+        Ok(ret.unwrap_or(0))
+    }
+
+    fn alloc(&mut self, cells: Vec<Option<i32>>) -> Slot {
+        self.memory.push(cells);
+        Slot {
+            object: self.memory.len() - 1,
+            offset: 0,
+        }
+    }
+
+    fn bind(&mut self, name: &str, var: Var, global: bool) {
+        if global {
+            self.globals.insert(name.to_string(), var);
+        } else {
+            self.frames
+                .last_mut()
+                .and_then(|frame| frame.last_mut())
+                .expect("Local declaration outside of a function")
+                .insert(name.to_string(), var);
+        }
+    }
+
+    /// 从内层作用域向外查找, 最后是全局变量
+    fn var(&self, name: &str) -> Result<Var> {
+        self.frames
+            .last()
+            .and_then(|frame| frame.iter().rev().find_map(|scope| scope.get(name)))
+            .or_else(|| self.globals.get(name))
+            .cloned()
+            .ok_or_else(|| anyhow!("Undefined identifier {}", name))
+    }
+
+    fn dims(&mut self, dims: &[Exp]) -> Result<Vec<usize>> {
+        dims.iter()
+            .map(|dim| {
+                let len = self.exp(dim)?;
+                usize::try_from(len).map_err(|_| anyhow!("Negative array length {}", len))
+            })
+            .collect()
+    }
+
+    fn load(&self, slot: Slot) -> Result<i32> {
+        match self.memory[slot.object].get(slot.offset) {
+            Some(Some(value)) => Ok(*value),
+            Some(None) => ub!("Read of an uninitialized value"),
+            None => ub!("Out-of-bounds read"),
+        }
+    }
+
+    fn store(&mut self, slot: Slot, value: i32) -> Result<()> {
+        match self.memory[slot.object].get_mut(slot.offset) {
+            Some(cell) => *cell = Some(value),
+            None => ub!("Out-of-bounds write"),
+        }
+        Ok(())
+    }
+
+    fn decl(&mut self, decl: &Decl, global: bool) -> Result<()> {
+        match decl {
+            Decl::ConstDecl(_, defs, _) => {
+                for def in defs {
+                    let dims = self.dims(&def.dims)?;
+                    let cells = flatten_init(&def.value, &dims)?
+                        .into_iter()
+                        .map(|exp| exp.map_or(Ok(0), |exp| self.exp(exp)).map(Some))
+                        .collect::<Result<Vec<_>>>()?;
+                    let slot = self.alloc(cells);
+                    let var = Var {
+                        slot,
+                        dims,
+                        pointer: false,
+                    };
+                    self.bind(&def.id, var, global);
+                }
+            }
+            Decl::VarDecl(_, defs, _) => {
+                for def in defs {
+                    let dims = self.dims(&def.dims)?;
+                    let size = dims.iter().product();
+                    let cells = match &def.init_val {
+                        Some(init) => flatten_init(init, &dims)?
+                            .into_iter()
+                            .map(|exp| exp.map_or(Ok(0), |exp| self.exp(exp)).map(Some))
+                            .collect::<Result<Vec<_>>>()?,
+                        // 全局变量默认为 0, 局部变量未初始化
+                        None if global => vec![Some(0); size],
+                        None => vec![None; size],
+                    };
+                    let slot = self.alloc(cells);
+                    let var = Var {
+                        slot,
+                        dims,
+                        pointer: false,
+                    };
+                    self.bind(&def.id, var, global);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// 每条语句和每个表达式算一步
+    fn tick(&mut self) -> Result<()> {
+        self.steps += 1;
+        if self.step_limit.is_some_and(|limit| self.steps > limit) {
+            return Err(TimeLimitExceeded.into());
+        }
+        Ok(())
+    }
+
+    fn call_func(&mut self, func: &'a FuncDef, args: Vec<Arg>) -> Result<Option<i32>> {
+        let stack_base = self.memory.len();
+        self.frames.push(vec![HashMap::new()]);
+        let flow = self
+            .bind_params(func, args)
+            .and_then(|_| self.block(&func.block))
+            .with_context(|| format!("In function {}", func.id));
+        self.frames.pop();
+        self.memory.truncate(stack_base);
+        match flow? {
+            Flow::Return(value) => Ok(value),
+            _ => Ok(None),
+        }
+    }
+
+    /// 形参在函数最外层的作用域中, 数组形参的各维长度在调用时求值
+    fn bind_params(&mut self, func: &FuncDef, args: Vec<Arg>) -> Result<()> {
+        for (param, arg) in func.params.iter().zip(args) {
+            let var = match (arg, &param.dims) {
+                (Arg::Int(value), _) => Var {
+                    slot: self.alloc(vec![Some(value)]),
+                    dims: Vec::new(),
+                    pointer: false,
+                },
+                (Arg::Ptr(slot), Some(dims)) => Var {
+                    slot,
+                    dims: self.dims(dims)?,
+                    pointer: true,
+                },
+                (Arg::Ptr(_), None) => bail!("Array passed to scalar parameter {}", param.id),
+            };
+            self.bind(&param.id, var, false);
+        }
+        Ok(())
+    }
+
+    fn block(&mut self, block: &Block) -> Result<Flow> {
+        self.scopes().push(HashMap::new());
+        let flow = self.block_items(block);
+        self.scopes().pop();
+        flow
+    }
+
+    fn scopes(&mut self) -> &mut Vec<HashMap<String, Var>> {
+        self.frames.last_mut().expect("Block outside of a function")
+    }
+
+    fn block_items(&mut self, block: &Block) -> Result<Flow> {
+        for item in &block.items {
+            match item {
+                BlockItem::Decl(decl) => self.decl(decl, false)?,
+                BlockItem::Stmt(stmt) => match self.stmt(stmt)? {
+                    Flow::Normal => {}
+                    flow => return Ok(flow),
+                },
+            }
+        }
+        Ok(Flow::Normal)
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) -> Result<Flow> {
+        self.tick()?;
+        match stmt {
+            Stmt::Return(exp, _) => {
+                let value = exp.as_ref().map(|exp| self.exp(exp)).transpose()?;
+                return Ok(Flow::Return(value));
+            }
+            Stmt::Exp(Some(exp), _) => {
+                self.exp(exp)?;
+            }
+            Stmt::Exp(None, _) => {}
+            Stmt::Block(block) => return self.block(block),
+            Stmt::Assign(lval, exp, _) => {
+                let value = self.exp(exp)?;
+                let slot = self.lval_slot(lval)?;
+                self.store(slot, value)?;
+            }
+            Stmt::If(cond, then, otherwise, _) => {
+                if self.exp(cond)? != 0 {
+                    return self.stmt(then);
+                } else if let Some(otherwise) = otherwise {
+                    return self.stmt(otherwise);
+                }
+            }
+            Stmt::While(cond, body, _) => {
+                while self.exp(cond)? != 0 {
+                    match self.stmt(body)? {
+                        Flow::Break => break,
+                        Flow::Return(value) => return Ok(Flow::Return(value)),
+                        Flow::Normal | Flow::Continue => {}
+                    }
+                }
+            }
+            Stmt::Break(_) => return Ok(Flow::Break),
+            Stmt::Continue(_) => return Ok(Flow::Continue),
+        }
+        Ok(Flow::Normal)
+    }
+
+    fn exp(&mut self, exp: &Exp) -> Result<i32> {
+        self.tick()?;
+        match exp {
+            Exp::Primary(primary) => self.primary(primary),
+            Exp::UnaryExp(unary) => self.unary(unary),
+            Exp::Binary(lhs, BinaryOp::And, rhs) => {
+                Ok((self.exp(lhs)? != 0 && self.exp(rhs)? != 0) as i32)
+            }
+            Exp::Binary(lhs, BinaryOp::Or, rhs) => {
+                Ok((self.exp(lhs)? != 0 || self.exp(rhs)? != 0) as i32)
+            }
+            Exp::Binary(lhs, op, rhs) => {
+                let lhs = self.exp(lhs)?;
+                let rhs = self.exp(rhs)?;
+                binary(*op, lhs, rhs)
+            }
+        }
+    }
+
+    fn primary(&mut self, primary: &PrimaryExp) -> Result<i32> {
+        match primary {
+            PrimaryExp::Number(num, _) => Ok(*num),
+            PrimaryExp::Exp(exp, _) => self.exp(exp),
+            PrimaryExp::LVal(lval) => {
+                let slot = self.lval_slot(lval)?;
+                self.load(slot)
+            }
+        }
+    }
+
+    fn unary(&mut self, unary: &UnaryExp) -> Result<i32> {
+        match unary {
+            UnaryExp::PrimaryExp(primary) => self.primary(primary),
+            UnaryExp::UnaryOp(op, operand, _) => {
+                let value = self.unary(operand)?;
+                Ok(match op {
+                    UnaryOp::Plus => value,
+                    UnaryOp::Minus => match value.checked_neg() {
+                        Some(value) => value,
+                        None => ub!("Signed overflow in -{}", value),
+                    },
+                    UnaryOp::Not => (value == 0) as i32,
+                })
+            }
+            UnaryExp::Call(call) => Ok(self.call(call)?.unwrap_or(0)),
+        }
+    }
+
+    fn call(&mut self, call: &FuncCall) -> Result<Option<i32>> {
+        let args = call
+            .args
+            .iter()
+            .map(|arg| self.arg(arg))
+            .collect::<Result<Vec<_>>>()?;
+        match self.funcs.get(call.id.as_str()) {
+            Some(&func) => self.call_func(func, args),
+            // 程序中没有定义的函数来自运行时库
+            None => self.call_runtime(&call.id, args),
+        }
+    }
+
+    /// 实参: 数组名或者下标不完整的数组元素传地址, 其余传值
+    fn arg(&mut self, arg: &Exp) -> Result<Arg> {
+        let lval = match arg {
+            Exp::Primary(PrimaryExp::LVal(lval)) => Some(lval),
+            Exp::UnaryExp(unary) => match unary.as_ref() {
+                UnaryExp::PrimaryExp(PrimaryExp::LVal(lval)) => Some(lval),
+                _ => None,
+            },
+            _ => None,
+        };
+        if let Some(lval) = lval {
+            let var = self.var(&lval.id)?;
+            if lval.indices.len() < var.dims.len() + var.pointer as usize {
+                return Ok(Arg::Ptr(self.lval_slot(lval)?));
+            }
+        }
+        Ok(Arg::Int(self.exp(arg)?))
+    }
+
+    /// 左值的地址. 数组按各维长度检查下标, 数组形参的第一维按所在对象的大小检查
+    fn lval_slot(&mut self, lval: &LVal) -> Result<Slot> {
+        let var = self.var(&lval.id)?;
+        let first_unbounded = var.pointer;
+        let mut dims = var.dims;
+        if first_unbounded {
+            dims.insert(0, usize::MAX);
+        }
+        let mut slot = var.slot;
+        for (k, index) in lval.indices.iter().enumerate() {
+            let index = self.exp(index)?;
+            let stride: usize = dims[k + 1..].iter().product();
+            let in_bounds =
+                index >= 0 && ((k == 0 && first_unbounded) || (index as usize) < dims[k]);
+            if !in_bounds {
+                ub!("Index {} out of bounds in {}", index, lval.id);
+            }
+            slot.offset += index as usize * stride;
+        }
+        if slot.offset >= self.memory[slot.object].len() && !lval.indices.is_empty() {
+            ub!("Index out of bounds in {}", lval.id);
+        }
+        Ok(slot)
+    }
+
+    fn call_runtime(&mut self, name: &str, args: Vec<Arg>) -> Result<Option<i32>> {
+        let int = |arg: &Arg| match arg {
+            Arg::Int(value) => *value,
+            Arg::Ptr(_) => unreachable!("Expected an integer argument"),
+        };
+        let ptr = |arg: &Arg| match arg {
+            Arg::Ptr(slot) => *slot,
+            Arg::Int(_) => unreachable!("Expected an array argument"),
+        };
+        match name {
+            "getint" => Ok(Some(read_int(self.input)?)),
+            "getch" => Ok(Some(read_byte(self.input)?.map_or(-1, i32::from))),
+            "getarray" => {
+                let slot = ptr(&args[0]);
+                let len = read_int(self.input)?;
+                for i in 0..len {
+                    let value = read_int(self.input)?;
+                    let elem = Slot {
+                        offset: slot.offset + i as usize,
+                        ..slot
+                    };
+                    self.store(elem, value)?;
+                }
+                Ok(Some(len))
+            }
+            "putint" => {
+                write!(self.output, "{}", int(&args[0]))?;
+                Ok(None)
+            }
+            "putch" => {
+                self.output.write_all(&[int(&args[0]) as u8])?;
+                Ok(None)
+            }
+            "putarray" => {
+                let (len, slot) = (int(&args[0]), ptr(&args[1]));
+                write!(self.output, "{}:", len)?;
+                for i in 0..len {
+                    let elem = Slot {
+                        offset: slot.offset + i as usize,
+                        ..slot
+                    };
+                    write!(self.output, " {}", self.load(elem)?)?;
+                }
+                writeln!(self.output)?;
+                Ok(None)
+            }
+            "starttime" | "stoptime" => Ok(None),
+            _ => bail!("Unknown runtime function {}", name),
+        }
+    }
+}
+
+enum Arg {
+    Int(i32),
+    Ptr(Slot),
+}
+
+fn binary(op: BinaryOp, lhs: i32, rhs: i32) -> Result<i32> {
+    let checked = match op {
+        BinaryOp::Add => lhs.checked_add(rhs),
+        BinaryOp::Sub => lhs.checked_sub(rhs),
+        BinaryOp::Mul => lhs.checked_mul(rhs),
+        BinaryOp::Div => lhs.checked_div(rhs),
+        BinaryOp::Mod => lhs.checked_rem(rhs),
+        BinaryOp::Lt => Some((lhs < rhs) as i32),
+        BinaryOp::Gt => Some((lhs > rhs) as i32),
+        BinaryOp::Le => Some((lhs <= rhs) as i32),
+        BinaryOp::Ge => Some((lhs >= rhs) as i32),
+        BinaryOp::Eq => Some((lhs == rhs) as i32),
+        BinaryOp::NotEq => Some((lhs != rhs) as i32),
+        _ => unreachable!("{:?} is not a SysY operator", op),
+    };
+    match checked {
+        Some(value) => Ok(value),
+        None => ub!("{} {:?} {} overflows or divides by zero", lhs, op, rhs),
+    }
+}
@@ -0,0 +1,841 @@
+use std::collections::HashSet;
+use std::fmt::Write;
+
+use anyhow::{bail, Context};
+
+use crate::Result;
+
+// 随机生成 SysY 程序, 思路与 Csmith 相同: 生成时就保证程序类型正确、一定终止,
+// 并尽量避开未定义行为, 剩下的情况由参考求值器发现后丢弃.
+// - 所有二元运算都加括号, 不依赖优先级
+// - 除数写成 `((e) % 5 + 6)`, 下标写成 `((e) % N + N) % N`
+// - 乘法的两个操作数先模 1000, 赋值和返回值模 10007, 数值不会溢出
+// - 循环都是有上界的计数循环, 函数只能调用在它之前定义的函数, 没有递归
+// - 表达式中只调用纯函数 (不写全局变量和数组参数, 不输出), 求值顺序不影响结果
+
+/// 可以单独关闭的语言特性
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Feature {
+    Arrays,
+    Functions,
+    Loops,
+    Globals,
+    Consts,
+    Logic,
+    Shadowing,
+}
+
+impl Feature {
+    pub const ALL: [Feature; 7] = [
+        Feature::Arrays,
+        Feature::Functions,
+        Feature::Loops,
+        Feature::Globals,
+        Feature::Consts,
+        Feature::Logic,
+        Feature::Shadowing,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Feature::Arrays => "arrays",
+            Feature::Functions => "functions",
+            Feature::Loops => "loops",
+            Feature::Globals => "globals",
+            Feature::Consts => "consts",
+            Feature::Logic => "logic",
+            Feature::Shadowing => "shadowing",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Feature> {
+        Feature::ALL
+            .into_iter()
+            .find(|feature| feature.name() == name)
+    }
+}
+
+/// 各种语句的相对权重
+#[derive(Debug, Clone)]
+pub struct StmtMix {
+    pub assign: u32,
+    pub if_else: u32,
+    pub while_loop: u32,
+    pub print: u32,
+    pub block: u32,
+    pub call: u32,
+    pub decl: u32,
+}
+
+impl Default for StmtMix {
+    fn default() -> Self {
+        Self {
+            assign: 5,
+            if_else: 2,
+            while_loop: 2,
+            print: 2,
+            block: 1,
+            call: 2,
+            decl: 3,
+        }
+    }
+}
+
+impl StmtMix {
+    fn weight_mut(&mut self, name: &str) -> Option<&mut u32> {
+        Some(match name {
+            "assign" => &mut self.assign,
+            "if" => &mut self.if_else,
+            "while" => &mut self.while_loop,
+            "print" => &mut self.print,
+            "block" => &mut self.block,
+            "call" => &mut self.call,
+            "decl" => &mut self.decl,
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FuzzConfig {
+    pub seed: u64,
+    /// 表达式的最大嵌套深度
+    pub max_expr_depth: usize,
+    /// 语句块的最大嵌套深度
+    pub max_stmt_depth: usize,
+    /// 每个语句块中最多的语句数
+    pub max_stmts: usize,
+    /// main 以外最多的函数个数
+    pub max_funcs: usize,
+    /// 每个循环最多执行的次数
+    pub loop_bound: usize,
+    pub mix: StmtMix,
+    pub features: HashSet<Feature>,
+}
+
+impl Default for FuzzConfig {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            max_expr_depth: 3,
+            max_stmt_depth: 3,
+            max_stmts: 5,
+            max_funcs: 3,
+            loop_bound: 4,
+            mix: StmtMix::default(),
+            features: Feature::ALL.into_iter().collect(),
+        }
+    }
+}
+
+impl FuzzConfig {
+    pub fn has(&self, feature: Feature) -> bool {
+        self.features.contains(&feature)
+    }
+
+    /// 处理 `seed=N`、`depth=N`、`stmt-depth=N`、`stmts=N`、`funcs=N`、`loops=N`、
+    /// `mix=assign:4,if:2,...`、`no-<特性>` 和 `<特性>`
+    pub fn apply_option(&mut self, option: &str) -> Result<()> {
+        if let Some((key, value)) = option.split_once('=') {
+            let number = || -> Result<usize> {
+                value
+                    .parse()
+                    .with_context(|| format!("Invalid value for {}: {}", key, value))
+            };
+            match key {
+                "seed" => self.seed = number()? as u64,
+                "depth" => self.max_expr_depth = number()?,
+                "stmt-depth" => self.max_stmt_depth = number()?,
+                "stmts" => self.max_stmts = number()?.max(1),
+                "funcs" => self.max_funcs = number()?,
+                "loops" => self.loop_bound = number()?,
+                "mix" => {
+                    for item in value.split(',') {
+                        let Some((name, weight)) = item.split_once(':') else {
+                            bail!("Invalid statement weight: {}", item);
+                        };
+                        let Some(slot) = self.mix.weight_mut(name) else {
+                            bail!("Unknown statement kind: {}", name);
+                        };
+                        *slot = weight
+                            .parse()
+                            .with_context(|| format!("Invalid weight for {}: {}", name, weight))?;
+                    }
+                }
+                _ => bail!("Unknown fuzzer option: {}", key),
+            }
+            return Ok(());
+        }
+        let (name, enable) = match option.strip_prefix("no-") {
+            Some(name) => (name, false),
+            None => (option, true),
+        };
+        let Some(feature) = Feature::from_name(name) else {
+            bail!("Unknown fuzzer option: {}", option);
+        };
+        if enable {
+            self.features.insert(feature);
+        } else {
+            self.features.remove(&feature);
+        }
+        Ok(())
+    }
+}
+
+/// SplitMix64, 同一个种子在任何平台上生成同一个程序
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// [0, n) 中的整数
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n.max(1) as u64) as usize
+    }
+
+    fn chance(&mut self, percent: usize) -> bool {
+        self.below(100) < percent
+    }
+
+    fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.below(items.len())]
+    }
+}
+
+/// 生成的数组只有两种形状: `int a[4]` 和 `int a[3][4]`, 数组形参分别写作
+/// `int p[]` 和 `int p[][4]`, 这样任何数组都可以传给同形状的形参
+const ROW_LEN: usize = 4;
+const ROWS: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Shape {
+    Scalar,
+    Row,
+    Matrix,
+}
+
+#[derive(Debug, Clone)]
+struct Var {
+    name: String,
+    shape: Shape,
+    is_const: bool,
+    writable: bool,
+    global: bool,
+    // 正在生成自己初始值的变量还不可见, 同时遮住外层的同名变量
+    hidden: bool,
+}
+
+#[derive(Debug, Clone)]
+struct Func {
+    name: String,
+    params: Vec<Shape>,
+    returns_int: bool,
+    pure: bool,
+}
+
+/// 按配置生成一个 SysY 程序
+pub fn generate(config: &FuzzConfig) -> String {
+    let mut generator = Generator {
+        config,
+        rng: Rng(config.seed),
+        out: String::new(),
+        indent: 0,
+        scopes: vec![Vec::new()],
+        funcs: Vec::new(),
+        counter: 0,
+        loop_depth: 0,
+        pure: false,
+    };
+    generator.program();
+    generator.out
+}
+
+struct Generator<'a> {
+    config: &'a FuzzConfig,
+    rng: Rng,
+    out: String,
+    indent: usize,
+    // 第 0 层是全局作用域
+    scopes: Vec<Vec<Var>>,
+    funcs: Vec<Func>,
+    counter: usize,
+    loop_depth: usize,
+    // 正在生成纯函数的函数体
+    pure: bool,
+}
+
+impl Generator<'_> {
+    fn program(&mut self) {
+        if self.config.has(Feature::Globals) {
+            for _ in 0..self.rng.below(4) {
+                self.global_decl();
+            }
+        }
+        if self.config.has(Feature::Functions) {
+            for _ in 0..self.rng.below(self.config.max_funcs + 1) {
+                self.func_def();
+            }
+        }
+        self.main_def();
+    }
+
+    fn line(&mut self, text: &str) {
+        for _ in 0..self.indent {
+            self.out.push_str("  ");
+        }
+        self.out.push_str(text);
+        self.out.push('\n');
+    }
+
+    fn fresh(&mut self, prefix: &str) -> String {
+        self.counter += 1;
+        format!("{}{}", prefix, self.counter)
+    }
+
+    fn declare(&mut self, var: Var) {
+        self.scopes.last_mut().unwrap().push(var);
+    }
+
+    /// 每个名字只取最内层的声明
+    fn visible(&self) -> Vec<&Var> {
+        let mut seen = HashSet::new();
+        let mut vars = Vec::new();
+        for scope in self.scopes.iter().rev() {
+            for var in scope.iter().rev() {
+                if seen.insert(var.name.as_str()) && !var.hidden {
+                    vars.push(var);
+                }
+            }
+        }
+        vars
+    }
+
+    fn vars_with(&self, filter: impl Fn(&Var) -> bool) -> Vec<Var> {
+        self.visible()
+            .into_iter()
+            .filter(|var| filter(var))
+            .cloned()
+            .collect()
+    }
+
+    /// 纯函数中只能写局部变量
+    fn assignable(&self, shape: Shape) -> Vec<Var> {
+        self.vars_with(|var| var.shape == shape && var.writable && !(self.pure && var.global))
+    }
+
+    // ---- 声明 ----
+
+    fn global_decl(&mut self) {
+        let is_const = self.config.has(Feature::Consts) && self.rng.chance(30);
+        let shape = self.random_shape();
+        let name = self.fresh(if is_const { "c" } else { "g" });
+        let init = match shape {
+            Shape::Scalar => bounded(&self.const_exp(2)),
+            _ => self.init_list(shape, |g| bounded(&g.const_exp(1))),
+        };
+        // 全局变量可以不写初始值, 默认为 0
+        let text = if is_const {
+            format!("const int {}{} = {};", name, dims(shape), init)
+        } else if self.rng.chance(20) {
+            format!("int {}{};", name, dims(shape))
+        } else {
+            format!("int {}{} = {};", name, dims(shape), init)
+        };
+        self.line(&text);
+        self.declare(Var {
+            name,
+            shape,
+            is_const,
+            writable: !is_const,
+            global: true,
+            hidden: false,
+        });
+    }
+
+    fn local_decl(&mut self, depth: usize) {
+        let is_const = self.config.has(Feature::Consts) && self.rng.chance(20);
+        let shape = self.random_shape();
+        let name = self.shadow_name().unwrap_or_else(|| self.fresh("v"));
+        // 初始值中同名的外层变量已经被遮住
+        self.declare(Var {
+            name: name.clone(),
+            shape,
+            is_const,
+            writable: false,
+            global: false,
+            hidden: true,
+        });
+        let init = match (shape, is_const) {
+            (Shape::Scalar, true) => bounded(&self.const_exp(2)),
+            (Shape::Scalar, false) => bounded(&self.exp(depth)),
+            (_, true) => self.init_list(shape, |g| bounded(&g.const_exp(1))),
+            (_, false) => self.init_list(shape, |g| bounded(&g.exp(depth.min(2)))),
+        };
+        let keyword = if is_const { "const int" } else { "int" };
+        self.line(&format!("{} {}{} = {};", keyword, name, dims(shape), init));
+        let var = self.scopes.last_mut().unwrap().last_mut().unwrap();
+        var.hidden = false;
+        var.writable = !is_const;
+    }
+
+    /// 遮蔽外层作用域中的变量
+    fn shadow_name(&mut self) -> Option<String> {
+        if !self.config.has(Feature::Shadowing) || !self.rng.chance(30) {
+            return None;
+        }
+        let current: HashSet<&str> = self
+            .scopes
+            .last()
+            .unwrap()
+            .iter()
+            .map(|v| v.name.as_str())
+            .collect();
+        let outer: Vec<String> = self
+            .visible()
+            .into_iter()
+            .filter(|var| !current.contains(var.name.as_str()) && !var.name.starts_with('i'))
+            .map(|var| var.name.clone())
+            .collect();
+        (!outer.is_empty()).then(|| self.rng.pick(&outer).clone())
+    }
+
+    fn random_shape(&mut self) -> Shape {
+        if !self.config.has(Feature::Arrays) {
+            return Shape::Scalar;
+        }
+        match self.rng.below(10) {
+            0..=5 => Shape::Scalar,
+            6..=8 => Shape::Row,
+            _ => Shape::Matrix,
+        }
+    }
+
+    /// 数组的初始化列表, 有时省略末尾的元素或写成不带内层括号的形式
+    fn init_list(&mut self, shape: Shape, mut elem: impl FnMut(&mut Self) -> String) -> String {
+        let mut row = |g: &mut Self| {
+            let len = if g.rng.chance(20) {
+                g.rng.below(ROW_LEN + 1)
+            } else {
+                ROW_LEN
+            };
+            (0..len).map(|_| elem(g)).collect::<Vec<_>>().join(", ")
+        };
+        match shape {
+            Shape::Scalar => unreachable!("Scalars have no initializer list"),
+            Shape::Row => format!("{{{}}}", row(self)),
+            Shape::Matrix if self.rng.chance(20) => {
+                let elems: Vec<String> = (0..ROWS)
+                    .map(|_| row(self))
+                    .filter(|row| !row.is_empty())
+                    .collect();
+                format!("{{{}}}", elems.join(", "))
+            }
+            Shape::Matrix => {
+                let rows: Vec<String> = (0..ROWS).map(|_| format!("{{{}}}", row(self))).collect();
+                format!("{{{}}}", rows.join(", "))
+            }
+        }
+    }
+
+    // ---- 函数 ----
+
+    fn func_def(&mut self) {
+        let name = self.fresh("f");
+        let returns_int = self.rng.chance(75);
+        let pure = returns_int && self.rng.chance(50);
+        let params: Vec<Shape> = (0..self.rng.below(4))
+            .map(|_| self.random_shape())
+            .collect();
+        let mut param_vars = Vec::new();
+        let mut param_texts = Vec::new();
+        for &shape in &params {
+            let name = self.fresh("p");
+            param_texts.push(match shape {
+                Shape::Scalar => format!("int {}", name),
+                Shape::Row => format!("int {}[]", name),
+                Shape::Matrix => format!("int {}[][{}]", name, ROW_LEN),
+            });
+            // 纯函数不能修改通过数组参数传进来的内存
+            param_vars.push(Var {
+                name,
+                shape,
+                is_const: false,
+                writable: shape == Shape::Scalar || !pure,
+                global: false,
+                hidden: false,
+            });
+        }
+        let ret = if returns_int { "int" } else { "void" };
+        self.line(&format!("{} {}({}) {{", ret, name, param_texts.join(", ")));
+        self.pure = pure;
+        self.scopes.push(param_vars);
+        self.indent += 1;
+        self.stmts(0, returns_int);
+        if returns_int {
+            let value = self.exp(self.config.max_expr_depth);
+            self.line(&format!("return {};", bounded(&value)));
+        }
+        self.indent -= 1;
+        self.scopes.pop();
+        self.pure = false;
+        self.line("}");
+        self.funcs.push(Func {
+            name,
+            params,
+            returns_int,
+            pure,
+        });
+    }
+
+    fn main_def(&mut self) {
+        self.line("int main() {");
+        self.scopes.push(Vec::new());
+        self.indent += 1;
+        self.stmts(0, true);
+        // 最后输出所有可见的变量, 让每次赋值都能影响输出
+        let vars = self.vars_with(|var| !var.is_const);
+        for var in vars {
+            match var.shape {
+                Shape::Scalar => {
+                    self.line(&format!("putint({});", var.name));
+                    self.line("putch(10);");
+                }
+                Shape::Row => self.line(&format!("putarray({}, {});", ROW_LEN, var.name)),
+                Shape::Matrix => {
+                    for row in 0..ROWS {
+                        self.line(&format!("putarray({}, {}[{}]);", ROW_LEN, var.name, row));
+                    }
+                }
+            }
+        }
+        let value = self.exp(self.config.max_expr_depth);
+        self.line(&format!("return {};", value));
+        self.indent -= 1;
+        self.scopes.pop();
+        self.line("}");
+    }
+
+    // ---- 语句 ----
+
+    fn stmts(&mut self, depth: usize, returns_int: bool) {
+        for _ in 0..1 + self.rng.below(self.config.max_stmts) {
+            self.stmt(depth, returns_int);
+        }
+    }
+
+    fn block(&mut self, depth: usize, returns_int: bool) {
+        self.scopes.push(Vec::new());
+        self.indent += 1;
+        self.stmts(depth + 1, returns_int);
+        self.indent -= 1;
+        self.scopes.pop();
+    }
+
+    fn stmt(&mut self, depth: usize, returns_int: bool) {
+        let mix = &self.config.mix;
+        let nested = depth < self.config.max_stmt_depth;
+        let loops = nested && self.config.has(Feature::Loops) && self.loop_depth < 2;
+        let calls = !self.callable(false).is_empty();
+        let weights = [
+            mix.assign,
+            if nested { mix.if_else } else { 0 },
+            if loops { mix.while_loop } else { 0 },
+            if self.pure { 0 } else { mix.print },
+            if nested { mix.block } else { 0 },
+            if calls { mix.call } else { 0 },
+            mix.decl,
+        ];
+        let total: u32 = weights.iter().sum();
+        if total == 0 {
+            return self.line(";");
+        }
+        let mut choice = self.rng.below(total as usize) as u32;
+        let kind = weights
+            .iter()
+            .position(|&w| {
+                if choice < w {
+                    true
+                } else {
+                    choice -= w;
+                    false
+                }
+            })
+            .unwrap();
+        let exp_depth = self.config.max_expr_depth;
+        match kind {
+            0 => self.assign(exp_depth),
+            1 => self.if_else(depth, returns_int),
+            2 => self.while_loop(depth, returns_int),
+            3 => {
+                let value = self.exp(exp_depth);
+                self.line(&format!("putint({});", value));
+                let sep = if self.rng.chance(50) { 32 } else { 10 };
+                self.line(&format!("putch({});", sep));
+            }
+            4 => {
+                self.line("{");
+                self.block(depth, returns_int);
+                self.line("}");
+            }
+            5 => self.call_stmt(exp_depth),
+            _ => self.local_decl(exp_depth),
+        }
+    }
+
+    fn assign(&mut self, depth: usize) {
+        let mut shapes = vec![Shape::Scalar];
+        if self.config.has(Feature::Arrays) {
+            shapes.extend([Shape::Row, Shape::Matrix]);
+        }
+        let shape = *self.rng.pick(&shapes);
+        let targets = self.assignable(shape);
+        if targets.is_empty() {
+            return self.local_decl(depth);
+        }
+        let target = self.rng.pick(&targets).clone();
+        let lval = self.element(&target, depth.saturating_sub(1));
+        let value = self.exp(depth);
+        self.line(&format!("{} = {};", lval, bounded(&value)));
+    }
+
+    fn if_else(&mut self, depth: usize, returns_int: bool) {
+        let cond = self.exp(self.config.max_expr_depth);
+        // 循环中的 break/continue 以及提前返回
+        if self.rng.chance(25) {
+            let jump = if self.loop_depth > 0 && self.rng.chance(60) {
+                if self.rng.chance(50) {
+                    "break;"
+                } else {
+                    "continue;"
+                }
+                .to_string()
+            } else if returns_int {
+                format!("return {};", bounded(&self.exp(2)))
+            } else {
+                "return;".to_string()
+            };
+            return self.line(&format!("if ({}) {}", cond, jump));
+        }
+        self.line(&format!("if ({}) {{", cond));
+        self.block(depth, returns_int);
+        if self.rng.chance(50) {
+            self.line("} else {");
+            self.block(depth, returns_int);
+        }
+        self.line("}");
+    }
+
+    /// `{ int i = 0; while (i < K) { i = i + 1; ... } }`, 计数器不会被其他语句修改
+    fn while_loop(&mut self, depth: usize, returns_int: bool) {
+        let counter = self.fresh("i");
+        let bound = 1 + self.rng.below(self.config.loop_bound);
+        self.line("{");
+        self.indent += 1;
+        self.line(&format!("int {} = 0;", counter));
+        // 附加的条件只能让循环提前结束
+        let cond = if self.config.has(Feature::Logic) && self.rng.chance(30) {
+            let extra = self.exp(2);
+            if self.rng.chance(50) {
+                format!("({} < {}) && ({})", counter, bound, extra)
+            } else {
+                format!("!(({} >= {}) || !({}))", counter, bound, extra)
+            }
+        } else {
+            format!("{} < {}", counter, bound)
+        };
+        self.line(&format!("while ({}) {{", cond));
+        self.scopes.push(vec![Var {
+            name: counter.clone(),
+            shape: Shape::Scalar,
+            is_const: false,
+            writable: false,
+            global: false,
+            hidden: false,
+        }]);
+        self.indent += 1;
+        self.line(&format!("{0} = {0} + 1;", counter));
+        self.indent -= 1;
+        self.loop_depth += 1;
+        self.block(depth, returns_int);
+        self.loop_depth -= 1;
+        self.scopes.pop();
+        self.line("}");
+        self.indent -= 1;
+        self.line("}");
+    }
+
+    fn call_stmt(&mut self, depth: usize) {
+        let funcs = self.callable(false);
+        let func = self.rng.pick(&funcs).clone();
+        let call = self.call(&func, depth);
+        let targets = self.assignable(Shape::Scalar);
+        if func.returns_int && !targets.is_empty() && self.rng.chance(70) {
+            let target = self.rng.pick(&targets).name.clone();
+            self.line(&format!("{} = {};", target, bounded(&call)));
+        } else {
+            self.line(&format!("{};", call));
+        }
+    }
+
+    // ---- 表达式 ----
+
+    /// 当前能调用的函数: 实参都能找到, 纯函数中只能调用纯函数
+    fn callable(&self, in_exp: bool) -> Vec<Func> {
+        let has = |shape| {
+            !self
+                .vars_with(|v| v.shape == shape && !v.is_const)
+                .is_empty()
+        };
+        let (rows, matrices) = (has(Shape::Row) || has(Shape::Matrix), has(Shape::Matrix));
+        self.funcs
+            .iter()
+            .filter(|func| {
+                (func.pure || (!in_exp && !self.pure))
+                    && (func.returns_int || !in_exp)
+                    && func.params.iter().all(|shape| match shape {
+                        Shape::Scalar => true,
+                        Shape::Row => rows,
+                        Shape::Matrix => matrices,
+                    })
+            })
+            .cloned()
+            .collect()
+    }
+
+    fn call(&mut self, func: &Func, depth: usize) -> String {
+        let args: Vec<String> = func
+            .params
+            .iter()
+            .map(|&shape| match shape {
+                Shape::Scalar => self.exp(depth.saturating_sub(1)),
+                _ => self.array_arg(shape),
+            })
+            .collect();
+        format!("{}({})", func.name, args.join(", "))
+    }
+
+    /// 一维数组形参可以接收一维数组, 也可以接收二维数组的一行
+    fn array_arg(&mut self, shape: Shape) -> String {
+        let arrays = self.vars_with(|v| {
+            !v.is_const && (v.shape == shape || (shape == Shape::Row && v.shape == Shape::Matrix))
+        });
+        let array = self.rng.pick(&arrays).clone();
+        if array.shape == shape {
+            array.name
+        } else {
+            format!("{}[{}]", array.name, self.index(ROWS, 0))
+        }
+    }
+
+    fn index(&mut self, len: usize, depth: usize) -> String {
+        let exp = self.exp(depth);
+        format!("((({}) % {len} + {len}) % {len})", exp, len = len)
+    }
+
+    /// 标量本身或数组中的一个元素
+    fn element(&mut self, var: &Var, depth: usize) -> String {
+        match var.shape {
+            Shape::Scalar => var.name.clone(),
+            Shape::Row => format!("{}[{}]", var.name, self.index(ROW_LEN, depth)),
+            Shape::Matrix => {
+                let row = self.index(ROWS, depth);
+                let col = self.index(ROW_LEN, depth);
+                format!("{}[{}][{}]", var.name, row, col)
+            }
+        }
+    }
+
+    /// 深度用完时只取字面量和标量, 数组下标也是表达式
+    fn leaf(&mut self, depth: usize) -> String {
+        let vars = self.vars_with(|var| depth > 0 || var.shape == Shape::Scalar);
+        match self.rng.below(10) {
+            0..=2 => self.rng.below(100).to_string(),
+            3..=8 if !vars.is_empty() => {
+                let var = self.rng.pick(&vars).clone();
+                self.element(&var, depth.saturating_sub(1))
+            }
+            _ => {
+                let funcs = self.callable(true);
+                if depth == 0 || funcs.is_empty() {
+                    return self.rng.below(100).to_string();
+                }
+                let func = self.rng.pick(&funcs).clone();
+                self.call(&func, depth)
+            }
+        }
+    }
+
+    fn exp(&mut self, depth: usize) -> String {
+        if depth == 0 || self.rng.chance(25) {
+            return self.leaf(depth);
+        }
+        let logic = self.config.has(Feature::Logic);
+        let d = depth - 1;
+        match self.rng.below(12) {
+            0 | 1 => format!("({}) + ({})", self.exp(d), self.exp(d)),
+            2 | 3 => format!("({}) - ({})", self.exp(d), self.exp(d)),
+            4 => format!("(({}) % 1000) * (({}) % 1000)", self.exp(d), self.exp(d)),
+            5 => format!("({}) / ((({}) % 5) + 6)", self.exp(d), self.exp(d)),
+            6 => format!("({}) % ((({}) % 5) + 6)", self.exp(d), self.exp(d)),
+            7 => {
+                let op = *self.rng.pick(&["<", ">", "<=", ">=", "==", "!="]);
+                format!("({}) {} ({})", self.exp(d), op, self.exp(d))
+            }
+            8 if logic => {
+                let op = if self.rng.chance(50) { "&&" } else { "||" };
+                format!("({}) {} ({})", self.exp(d), op, self.exp(d))
+            }
+            9 if logic => format!("!({})", self.exp(d)),
+            10 => format!("+({})", self.exp(d)),
+            _ => format!("-({})", self.exp(d)),
+        }
+    }
+
+    /// 常量表达式: 只用字面量和标量常量
+    fn const_exp(&mut self, depth: usize) -> String {
+        let consts = self.vars_with(|v| v.is_const && v.shape == Shape::Scalar);
+        if depth == 0 || self.rng.chance(30) {
+            if !consts.is_empty() && self.rng.chance(40) {
+                return self.rng.pick(&consts).name.clone();
+            }
+            return self.rng.below(100).to_string();
+        }
+        let d = depth - 1;
+        match self.rng.below(5) {
+            0 => format!("({}) + ({})", self.const_exp(d), self.const_exp(d)),
+            1 => format!("({}) - ({})", self.const_exp(d), self.const_exp(d)),
+            2 => format!(
+                "(({}) % 100) * (({}) % 100)",
+                self.const_exp(d),
+                self.const_exp(d)
+            ),
+            3 => format!(
+                "({}) / ((({}) % 5) + 6)",
+                self.const_exp(d),
+                self.const_exp(d)
+            ),
+            _ => format!("-({})", self.const_exp(d)),
+        }
+    }
+}
+
+/// 赋值、初始化和返回的值都模 10007, 之后的运算不会溢出
+fn bounded(exp: &str) -> String {
+    format!("({}) % 10007", exp)
+}
+
+fn dims(shape: Shape) -> String {
+    let mut text = String::new();
+    match shape {
+        Shape::Scalar => {}
+        Shape::Row => write!(text, "[{}]", ROW_LEN).unwrap(),
+        Shape::Matrix => write!(text, "[{}][{}]", ROWS, ROW_LEN).unwrap(),
+    }
+    text
+}
@@ -0,0 +1,127 @@
+mod eval;
+mod generator;
+
+use anyhow::bail;
+use koopa::ir::Program;
+
+use crate::ast::CompUnit;
+use crate::autotest::{check_output, execute, format_output, guard, Target, DEFAULT_STEP_LIMIT};
+use crate::interpreter::TimeLimitExceeded;
+use crate::Result;
+pub use eval::{eval_program, UndefinedBehavior};
+pub use generator::{generate, Feature, FuzzConfig, StmtMix};
+
+// 差分测试: 随机生成的程序先用参考求值器在 AST 上求值, 再编译后分别用
+// Koopa 解释器和 RISC-V 模拟器执行, 三者的输出和退出码必须一致.
+// 与 autotest 一样, 语法分析器在可执行文件中, 由调用者提供编译函数
+
+/// 编译结果. `ast` 是语义分析之前的语法树, 供参考求值器使用, 不受常量折叠的影响;
+/// `program` 是正常编译生成的 IR
+pub struct Compiled {
+    pub ast: CompUnit,
+    pub program: Program,
+}
+
+/// SysY 源码编译为 [`Compiled`]
+pub type Compile<'a> = dyn Fn(&str) -> Result<Compiled> + 'a;
+
+/// 一个程序的测试结果
+#[derive(Debug)]
+pub enum Verdict {
+    Agree,
+    /// 参考求值器发现了未定义行为或者超时, 程序被丢弃
+    Skipped(String),
+    Mismatch(String),
+}
+
+/// 参考求值器最多执行的语句和表达式个数. 每一步对应若干条指令,
+/// 在这之内结束的程序在两个 target 上都不会超过 [`DEFAULT_STEP_LIMIT`]
+const REFERENCE_STEP_LIMIT: u64 = 1_000_000;
+
+/// 编译并执行一个程序, 比较参考求值器和两个 target 的结果
+pub fn check(source: &str, compile: &Compile) -> Verdict {
+    let compiled = match guard(|| compile(source)) {
+        Ok(compiled) => compiled,
+        Err(err) => return Verdict::Mismatch(format!("Compile error: {:#}", err)),
+    };
+    let mut stdout = Vec::new();
+    let expected = match eval_program(
+        &compiled.ast,
+        &mut &b""[..],
+        &mut stdout,
+        Some(REFERENCE_STEP_LIMIT),
+    ) {
+        Ok(code) => format_output(&String::from_utf8_lossy(&stdout), code),
+        // 不结束的程序没有可以比较的结果
+        Err(err) if err.downcast_ref::<TimeLimitExceeded>().is_some() => {
+            return Verdict::Skipped(err.to_string())
+        }
+        Err(err) => {
+            return match err.downcast_ref::<UndefinedBehavior>() {
+                Some(ub) => Verdict::Skipped(ub.to_string()),
+                None => Verdict::Mismatch(format!("Reference evaluator failed: {:#}", err)),
+            }
+        }
+    };
+    for target in [Target::Koopa, Target::Riscv] {
+        let outcome = guard(|| {
+            let actual = execute(&compiled.program, target, "", DEFAULT_STEP_LIMIT)?;
+            check_output(&expected, &actual)
+        });
+        if let Err(err) = outcome {
+            return Verdict::Mismatch(format!("[{}] {:#}", target.name(), err));
+        }
+    }
+    Verdict::Agree
+}
+
+/// 不一致的程序, 用种子可以重新生成
+pub struct Mismatch {
+    pub seed: u64,
+    pub source: String,
+    pub reason: String,
+}
+
+#[derive(Default)]
+pub struct Report {
+    pub agreed: usize,
+    pub skipped: usize,
+    pub mismatches: Vec<Mismatch>,
+}
+
+impl Report {
+    pub fn render(&self) -> String {
+        format!(
+            "{} programs: {} agreed, {} skipped (undefined behavior or timeout), {} mismatched\n",
+            self.agreed + self.skipped + self.mismatches.len(),
+            self.agreed,
+            self.skipped,
+            self.mismatches.len()
+        )
+    }
+}
+
+/// 从 `config.seed` 开始, 用连续的种子生成并测试 `count` 个程序
+pub fn run(config: &FuzzConfig, count: usize, compile: &Compile) -> Result<Report> {
+    if count == 0 {
+        bail!("Nothing to fuzz");
+    }
+    let mut report = Report::default();
+    for i in 0..count as u64 {
+        let config = FuzzConfig {
+            seed: config.seed.wrapping_add(i),
+            ..config.clone()
+        };
+        let source = generate(&config);
+        match check(&source, compile) {
+            Verdict::Agree => report.agreed += 1,
+            Verdict::Skipped(_) => report.skipped += 1,
+            Verdict::Mismatch(reason) => report.mismatches.push(Mismatch {
+                seed: config.seed,
+                source,
+                reason,
+            }),
+        }
+    }
+    Ok(report)
+}
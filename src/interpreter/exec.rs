@@ -0,0 +1,212 @@
+use anyhow::{anyhow, bail, ensure, Context};
+use koopa::ir::{BasicBlock, BinaryOp, Type, TypeKind, Value, ValueKind};
+
+use super::memory::{cells, Val};
+use super::Interpreter;
+use crate::Result;
+
+// 逐条执行指令. 算术按 RISC-V 的语义回绕, 移位量只取低 5 位
+
+impl Interpreter<'_> {
+    /// 执行一条指令, main 返回时得到它的返回值
+    pub(super) fn step(&mut self) -> Result<Option<Option<Val>>> {
+        let program = self.program;
+        let frame = self.frame_mut();
+        let inst = *frame
+            .insts
+            .get(frame.pc)
+            .ok_or_else(|| anyhow!("Basic block ends without a terminator"))?;
+        frame.pc += 1;
+        let data = program.func(frame.func).dfg().value(inst);
+        match data.kind() {
+            ValueKind::Alloc(_) => {
+                // 循环中再次执行同一条 alloc 时沿用本帧已有的对象, 与栈上的固定位置一致
+                if !frame.values.contains_key(&inst) {
+                    let ptr = self
+                        .memory
+                        .alloc(vec![Val::Int(0); pointee_cells(data.ty())]);
+                    self.define(inst, Val::Ptr(ptr));
+                }
+            }
+            ValueKind::Load(load) => {
+                ensure!(
+                    cells(data.ty()) == 1,
+                    "Can not load a value of type {}",
+                    data.ty()
+                );
+                let ptr = self.eval(load.src())?.as_ptr()?;
+                let val = self.memory.load(ptr)?;
+                self.define(inst, val);
+            }
+            ValueKind::Store(store) => {
+                let ptr = self.eval(store.dest())?.as_ptr()?;
+                let mut vals = Vec::new();
+                self.flatten(store.value(), &mut vals)?;
+                for (i, val) in vals.into_iter().enumerate() {
+                    self.memory.store(ptr.add(i as isize), val)?;
+                }
+            }
+            ValueKind::GetPtr(get_ptr) => {
+                let ptr = self.eval(get_ptr.src())?.as_ptr()?;
+                let index = self.eval(get_ptr.index())?.as_int()?;
+                let stride = pointee_cells(data.ty()) as isize;
+                self.define(inst, Val::Ptr(ptr.add(index as isize * stride)));
+            }
+            ValueKind::GetElemPtr(gep) => {
+                let ptr = self.eval(gep.src())?.as_ptr()?;
+                let index = self.eval(gep.index())?.as_int()?;
+                let stride = pointee_cells(data.ty()) as isize;
+                self.define(inst, Val::Ptr(ptr.add(index as isize * stride)));
+            }
+            ValueKind::Binary(binary) => {
+                let lhs = self.eval(binary.lhs())?.as_int()?;
+                let rhs = self.eval(binary.rhs())?.as_int()?;
+                self.define(inst, Val::Int(eval_binary(binary.op(), lhs, rhs)?));
+            }
+            ValueKind::Branch(branch) => {
+                if self.eval(branch.cond())?.as_int()? != 0 {
+                    self.jump(branch.true_bb(), branch.true_args())?;
+                } else {
+                    self.jump(branch.false_bb(), branch.false_args())?;
+                }
+            }
+            ValueKind::Jump(jump) => self.jump(jump.target(), jump.args())?,
+            ValueKind::Call(call) => {
+                let args = call
+                    .args()
+                    .iter()
+                    .map(|&arg| self.eval(arg))
+                    .collect::<Result<Vec<_>>>()?;
+                let callee = program.func(call.callee());
+                if callee.layout().entry_bb().is_some() {
+                    self.enter(call.callee(), args)?;
+                } else if let Some(ret) = self
+                    .call_runtime(callee.name(), &args)
+                    .with_context(|| format!("In call to {}", callee.name()))?
+                {
+                    self.define(inst, ret);
+                }
+            }
+            ValueKind::Return(ret) => {
+                let val = ret.value().map(|val| self.eval(val)).transpose()?;
+                let frame = self.frames.pop().expect("Return without a frame");
+                self.memory.truncate(frame.stack_base);
+                let Some(caller) = self.frames.last_mut() else {
+                    return Ok(Some(val));
+                };
+                // 调用者的 pc 已经越过 call 指令
+                let call = caller.insts[caller.pc - 1];
+                if let Some(val) = val {
+                    caller.values.insert(call, val);
+                }
+            }
+            kind => bail!("{:?} is not an instruction", kind),
+        }
+        Ok(None)
+    }
+
+    fn define(&mut self, inst: Value, val: Val) {
+        self.frame_mut().values.insert(inst, val);
+    }
+
+    /// 跳转到 `target`, 把参数绑定到基本块参数上
+    fn jump(&mut self, target: BasicBlock, args: &[Value]) -> Result<()> {
+        let args = args
+            .iter()
+            .map(|&arg| self.eval(arg))
+            .collect::<Result<Vec<_>>>()?;
+        let func = self.frame().func;
+        let params = self.program.func(func).dfg().bb(target).params().to_vec();
+        let insts = self.bb_insts(func, target);
+        let frame = self.frame_mut();
+        frame.values.extend(params.into_iter().zip(args));
+        frame.bb = target;
+        frame.insts = insts;
+        frame.pc = 0;
+        Ok(())
+    }
+
+    fn eval(&self, value: Value) -> Result<Val> {
+        if let Some(&val) = self.globals.get(&value) {
+            return Ok(val);
+        }
+        let frame = self.frame();
+        let data = self.program.func(frame.func).dfg().value(value);
+        match data.kind() {
+            ValueKind::Integer(int) => Ok(Val::Int(int.value())),
+            ValueKind::Undef(_) => Ok(Val::Int(0)),
+            _ => frame.values.get(&value).copied().ok_or_else(|| {
+                anyhow!(
+                    "{} is used before it is defined",
+                    data.name().as_deref().unwrap_or("Value")
+                )
+            }),
+        }
+    }
+
+    /// 把 store 的值展开为标量单元, 聚合常量按行优先顺序
+    fn flatten(&self, value: Value, out: &mut Vec<Val>) -> Result<()> {
+        let data = self.program.func(self.frame().func).dfg().value(value);
+        match data.kind() {
+            ValueKind::ZeroInit(_) => out.extend(vec![Val::Int(0); cells(data.ty())]),
+            ValueKind::Aggregate(agg) => {
+                for &elem in agg.elems() {
+                    self.flatten(elem, out)?;
+                }
+            }
+            _ => out.push(self.eval(value)?),
+        }
+        Ok(())
+    }
+
+    pub(super) fn flatten_global(&self, init: Value, out: &mut Vec<Val>) {
+        let data = self.program.borrow_value(init);
+        match data.kind() {
+            ValueKind::Integer(int) => out.push(Val::Int(int.value())),
+            ValueKind::ZeroInit(_) | ValueKind::Undef(_) => {
+                out.extend(vec![Val::Int(0); cells(data.ty())])
+            }
+            ValueKind::Aggregate(agg) => {
+                for &elem in agg.elems() {
+                    self.flatten_global(elem, out);
+                }
+            }
+            kind => unreachable!("Unsupported global initializer {:?}", kind),
+        }
+    }
+}
+
+fn pointee_cells(ty: &Type) -> usize {
+    match ty.kind() {
+        TypeKind::Pointer(base) => cells(base),
+        _ => unreachable!("Expected a pointer type, got {}", ty),
+    }
+}
+
+fn eval_binary(op: BinaryOp, lhs: i32, rhs: i32) -> Result<i32> {
+    Ok(match op {
+        BinaryOp::NotEq => (lhs != rhs) as i32,
+        BinaryOp::Eq => (lhs == rhs) as i32,
+        BinaryOp::Gt => (lhs > rhs) as i32,
+        BinaryOp::Lt => (lhs < rhs) as i32,
+        BinaryOp::Ge => (lhs >= rhs) as i32,
+        BinaryOp::Le => (lhs <= rhs) as i32,
+        BinaryOp::Add => lhs.wrapping_add(rhs),
+        BinaryOp::Sub => lhs.wrapping_sub(rhs),
+        BinaryOp::Mul => lhs.wrapping_mul(rhs),
+        BinaryOp::Div => {
+            ensure!(rhs != 0, "Division by zero");
+            lhs.wrapping_div(rhs)
+        }
+        BinaryOp::Mod => {
+            ensure!(rhs != 0, "Division by zero");
+            lhs.wrapping_rem(rhs)
+        }
+        BinaryOp::And => lhs & rhs,
+        BinaryOp::Or => lhs | rhs,
+        BinaryOp::Xor => lhs ^ rhs,
+        BinaryOp::Shl => lhs.wrapping_shl(rhs as u32),
+        BinaryOp::Shr => (lhs as u32).wrapping_shr(rhs as u32) as i32,
+        BinaryOp::Sar => lhs.wrapping_shr(rhs as u32),
+    })
+}
@@ -0,0 +1,97 @@
+use anyhow::{anyhow, bail};
+use koopa::ir::{Type, TypeKind};
+
+use crate::Result;
+
+// 内存按对象划分, 每个对象是一组标量单元, 指针是 (对象, 单元下标).
+// 越界访问会报错而不是读到别的对象, 方便定位 IRBuilder 生成的错误地址计算
+
+/// 解释器中的值, Koopa 程序中只有 i32 和指针两种标量
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Val {
+    Int(i32),
+    Ptr(Ptr),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Ptr {
+    pub object: usize,
+    pub offset: isize,
+}
+
+impl Val {
+    pub fn as_int(self) -> Result<i32> {
+        match self {
+            Val::Int(int) => Ok(int),
+            Val::Ptr(_) => bail!("Expected an integer, got a pointer"),
+        }
+    }
+
+    pub fn as_ptr(self) -> Result<Ptr> {
+        match self {
+            Val::Ptr(ptr) => Ok(ptr),
+            Val::Int(_) => bail!("Expected a pointer, got an integer"),
+        }
+    }
+}
+
+impl Ptr {
+    pub fn add(self, cells: isize) -> Self {
+        Self {
+            object: self.object,
+            offset: self.offset + cells,
+        }
+    }
+}
+
+/// 全局变量在最底部, 之后是各个栈帧的 alloc, 函数返回时整段释放
+#[derive(Default)]
+pub struct Memory {
+    objects: Vec<Vec<Val>>,
+}
+
+impl Memory {
+    pub fn alloc(&mut self, cells: Vec<Val>) -> Ptr {
+        self.objects.push(cells);
+        Ptr {
+            object: self.objects.len() - 1,
+            offset: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.objects.len()
+    }
+
+    /// 释放 `len` 之后分配的对象
+    pub fn truncate(&mut self, len: usize) {
+        self.objects.truncate(len);
+    }
+
+    pub fn load(&self, ptr: Ptr) -> Result<Val> {
+        self.objects
+            .get(ptr.object)
+            .and_then(|cells| cells.get(usize::try_from(ptr.offset).ok()?))
+            .copied()
+            .ok_or_else(|| anyhow!("Out-of-bounds load at {:?}", ptr))
+    }
+
+    pub fn store(&mut self, ptr: Ptr, val: Val) -> Result<()> {
+        let cell = self
+            .objects
+            .get_mut(ptr.object)
+            .and_then(|cells| cells.get_mut(usize::try_from(ptr.offset).ok()?))
+            .ok_or_else(|| anyhow!("Out-of-bounds store at {:?}", ptr))?;
+        *cell = val;
+        Ok(())
+    }
+}
+
+/// 类型占用的标量单元数
+pub fn cells(ty: &Type) -> usize {
+    match ty.kind() {
+        TypeKind::Int32 | TypeKind::Pointer(_) => 1,
+        TypeKind::Array(elem, len) => cells(elem) * len,
+        _ => unreachable!("Type {} can not be stored in memory", ty),
+    }
+}
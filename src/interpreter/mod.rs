@@ -0,0 +1,188 @@
+mod exec;
+mod memory;
+mod runtime_lib;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{BufRead, Write};
+use std::rc::Rc;
+
+use anyhow::{anyhow, ensure, Context};
+use koopa::ir::{BasicBlock, Function, Program, Value, ValueKind};
+
+use crate::Result;
+use memory::{Memory, Val};
+pub(crate) use runtime_lib::{read_byte, read_int};
+
+// Koopa IR 解释器: 不经过 RISC-V 后端直接执行 Program, 运行时库函数读写给定的输入输出.
+// 调用栈保存在 Vec 中而不是递归执行, 深递归的 SysY 程序不会耗尽解释器自身的栈
+
+/// 调用深度上限, 超过时按栈溢出报错
+const MAX_CALL_DEPTH: usize = 1 << 16;
+
+/// 执行 `program` 中的 `@main`, 返回 main 的返回值 (即进程退出码).
+/// `step_limit` 限制执行的指令条数, 超过时返回 [`TimeLimitExceeded`]
+pub fn run_program(
+    program: &Program,
+    input: &mut dyn BufRead,
+    output: &mut dyn Write,
+    step_limit: Option<u64>,
+) -> Result<i32> {
+    let mut interpreter = Interpreter::new(program, input, output);
+    interpreter.step_limit = step_limit;
+    interpreter.run()
+}
+
+/// 执行的指令条数超过了上限, 解释器和模拟器共用
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeLimitExceeded;
+
+impl fmt::Display for TimeLimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Time limit exceeded")
+    }
+}
+
+impl std::error::Error for TimeLimitExceeded {}
+
+pub struct Interpreter<'a> {
+    program: &'a Program,
+    memory: Memory,
+    globals: HashMap<Value, Val>,
+    frames: Vec<Frame>,
+    // 每个基本块的指令序列, 第一次进入时从布局中收集
+    insts: HashMap<BasicBlock, Rc<[Value]>>,
+    steps: u64,
+    step_limit: Option<u64>,
+    input: &'a mut dyn BufRead,
+    output: &'a mut dyn Write,
+}
+
+/// 一次函数调用的状态
+struct Frame {
+    func: Function,
+    bb: BasicBlock,
+    insts: Rc<[Value]>,
+    // 下一条要执行的指令在 insts 中的下标
+    pc: usize,
+    values: HashMap<Value, Val>,
+    // 进入函数时的内存对象数, 返回时释放之后的 alloc
+    stack_base: usize,
+}
+
+impl<'a> Interpreter<'a> {
+    pub fn new(
+        program: &'a Program,
+        input: &'a mut dyn BufRead,
+        output: &'a mut dyn Write,
+    ) -> Self {
+        Self {
+            program,
+            memory: Memory::default(),
+            globals: HashMap::new(),
+            frames: Vec::new(),
+            insts: HashMap::new(),
+            steps: 0,
+            step_limit: None,
+            input,
+            output,
+        }
+    }
+
+    /// 最多执行 `limit` 条指令
+    pub fn with_step_limit(mut self, limit: u64) -> Self {
+        self.step_limit = Some(limit);
+        self
+    }
+
+    pub fn run(&mut self) -> Result<i32> {
+        let main = self
+            .program
+            .func_layout()
+            .iter()
+            .copied()
+            .find(|&func| self.program.func(func).name() == "@main")
+            .ok_or_else(|| anyhow!("Program has no main function"))?;
+        self.init_globals();
+        self.enter(main, Vec::new())?;
+        let code = loop {
+            self.steps += 1;
+            if self.step_limit.is_some_and(|limit| self.steps > limit) {
+                return Err(TimeLimitExceeded.into());
+            }
+            if let Some(ret) = self.step().with_context(|| self.location())? {
+                break ret;
+            }
+        };
+        self.output.flush()?;
+        code.map_or(Ok(0), Val::as_int)
+    }
+
+    fn init_globals(&mut self) {
+        for &value in self.program.inst_layout() {
+            let data = self.program.borrow_value(value);
+            let ValueKind::GlobalAlloc(alloc) = data.kind() else {
+                unreachable!("Global value must be an alloc");
+            };
+            let mut cells = Vec::new();
+            self.flatten_global(alloc.init(), &mut cells);
+            let ptr = self.memory.alloc(cells);
+            self.globals.insert(value, Val::Ptr(ptr));
+        }
+    }
+
+    /// 压入 `func` 的栈帧, 从入口基本块开始执行
+    fn enter(&mut self, func: Function, args: Vec<Val>) -> Result<()> {
+        ensure!(self.frames.len() < MAX_CALL_DEPTH, "Stack overflow");
+        let data = self.program.func(func);
+        let entry = data
+            .layout()
+            .entry_bb()
+            .ok_or_else(|| anyhow!("Function {} has no body", data.name()))?;
+        let values = data.params().iter().copied().zip(args).collect();
+        let insts = self.bb_insts(func, entry);
+        self.frames.push(Frame {
+            func,
+            bb: entry,
+            insts,
+            pc: 0,
+            values,
+            stack_base: self.memory.len(),
+        });
+        Ok(())
+    }
+
+    fn bb_insts(&mut self, func: Function, bb: BasicBlock) -> Rc<[Value]> {
+        let program = self.program;
+        self.insts
+            .entry(bb)
+            .or_insert_with(|| {
+                let node = program.func(func).layout().bbs().node(&bb);
+                node.map_or_else(Vec::new, |node| node.insts().keys().copied().collect())
+                    .into()
+            })
+            .clone()
+    }
+
+    /// 出错时所在的函数和基本块
+    fn location(&self) -> String {
+        let frame = self.frame();
+        let func = self.program.func(frame.func);
+        match func.dfg().bb(frame.bb).name() {
+            Some(bb) => format!(
+                "Runtime error in function {}, basic block {}",
+                func.name(),
+                bb
+            ),
+            None => format!("Runtime error in function {}", func.name()),
+        }
+    }
+
+    fn frame(&self) -> &Frame {
+        self.frames.last().expect("No active frame")
+    }
+
+    fn frame_mut(&mut self) -> &mut Frame {
+        self.frames.last_mut().expect("No active frame")
+    }
+}
@@ -0,0 +1,95 @@
+use std::io::BufRead;
+
+use anyhow::{bail, ensure};
+
+use super::memory::Val;
+use super::Interpreter;
+use crate::Result;
+
+// SysY 运行时库, 行为与 libsysy 一致: getint/getarray 按 scanf("%d") 读入,
+// putarray 输出 `n: a0 a1 ...` 并换行. 计时函数不做任何事
+
+impl Interpreter<'_> {
+    pub(super) fn call_runtime(&mut self, name: &str, args: &[Val]) -> Result<Option<Val>> {
+        match (name, args) {
+            ("@getint", []) => Ok(Some(Val::Int(read_int(self.input)?))),
+            ("@getch", []) => {
+                let ch = read_byte(self.input)?.map_or(-1, i32::from);
+                Ok(Some(Val::Int(ch)))
+            }
+            ("@getarray", &[ptr]) => {
+                let ptr = ptr.as_ptr()?;
+                let len = read_int(self.input)?;
+                for i in 0..len {
+                    let val = read_int(self.input)?;
+                    self.memory.store(ptr.add(i as isize), Val::Int(val))?;
+                }
+                Ok(Some(Val::Int(len)))
+            }
+            ("@putint", &[val]) => {
+                write!(self.output, "{}", val.as_int()?)?;
+                Ok(None)
+            }
+            ("@putch", &[val]) => {
+                self.output.write_all(&[val.as_int()? as u8])?;
+                Ok(None)
+            }
+            ("@putarray", &[len, ptr]) => {
+                let (len, ptr) = (len.as_int()?, ptr.as_ptr()?);
+                write!(self.output, "{}:", len)?;
+                for i in 0..len {
+                    let val = self.memory.load(ptr.add(i as isize))?.as_int()?;
+                    write!(self.output, " {}", val)?;
+                }
+                writeln!(self.output)?;
+                Ok(None)
+            }
+            ("@starttime" | "@stoptime", []) => Ok(None),
+            _ => bail!(
+                "Unknown runtime function {} with {} arguments",
+                name,
+                args.len()
+            ),
+        }
+    }
+}
+
+fn peek_byte(input: &mut dyn BufRead) -> Result<Option<u8>> {
+    Ok(input.fill_buf()?.first().copied())
+}
+
+/// 读入一个字节, 对应 getch
+pub(crate) fn read_byte(input: &mut dyn BufRead) -> Result<Option<u8>> {
+    let byte = peek_byte(input)?;
+    if byte.is_some() {
+        input.consume(1);
+    }
+    Ok(byte)
+}
+
+/// 跳过空白后读入一个带符号的十进制整数, 溢出时回绕, 对应 getint
+pub(crate) fn read_int(input: &mut dyn BufRead) -> Result<i32> {
+    while peek_byte(input)?.is_some_and(|b| b.is_ascii_whitespace()) {
+        read_byte(input)?;
+    }
+    let negative = match peek_byte(input)? {
+        Some(sign @ (b'-' | b'+')) => {
+            read_byte(input)?;
+            sign == b'-'
+        }
+        _ => false,
+    };
+    let mut value = 0i32;
+    let mut digits = 0;
+    while let Some(digit) = peek_byte(input)?.filter(u8::is_ascii_digit) {
+        read_byte(input)?;
+        value = value.wrapping_mul(10).wrapping_add(i32::from(digit - b'0'));
+        digits += 1;
+    }
+    ensure!(digits > 0, "Expected an integer in the input");
+    Ok(if negative {
+        value.wrapping_neg()
+    } else {
+        value
+    })
+}
@@ -1,65 +1,59 @@
-use koopa::ir::Value;
+use koopa::ir::{Function, Value};
 
-use crate::semantic::SymbolKind;
-use crate::traits::semantic::SymbolTable;
+use crate::semantic::{Symbol, SymbolId};
 
 use super::IRBuilder;
 use super::Result;
 impl IRBuilder {
-    fn get_scoped_name(&self, name: &str) -> String {
-        format!("@{}_{}", name, self.current_scope_level)
+    pub fn symbol(&self, id: SymbolId) -> &Symbol {
+        &self.symbols[id.0]
     }
 
-    pub fn create_variable(&mut self, name: &str, init_val: Option<Value>) -> Result<Value> {
-        let scoped_name = self.get_scoped_name(name);
-        let value = self.create_alloc(self.create_int_type(), scoped_name)?;
-
-        let symbol = SymbolKind::Variable {
-            value,
-            scope_level: self.current_scope_level,
-        };
-        self.add_symbol(name, symbol)?;
-
-        if let Some(init_val) = init_val {
-            self.create_store(value, init_val)?;
-        }
-        Ok(value)
+    pub fn bind_value(&mut self, id: SymbolId, value: Value) {
+        self.values.insert(id, value);
     }
 
-    pub fn create_const(&mut self, name: &str, value: i32) -> Result<()> {
-        let scoped_name = self.get_scoped_name(name);
-        let symbol = SymbolKind::Const {
-            value: value,
-            scope_level: self.current_scope_level,
-        };
-
-        self.add_symbol(&scoped_name, symbol)
+    /// 变量或常量数组对应的 alloc, 定义总是先于使用被生成
+    pub fn symbol_value(&self, id: SymbolId) -> Value {
+        self.values[&id]
     }
 
-    pub fn get_var_value(&self, name: &str) -> Result<Value> {
-        let symbol = self.lookup(name)?;
-        match symbol {
-            SymbolKind::Variable { value, .. } => Ok(*value),
-            _ => Err(anyhow::anyhow!("Not a variable: {}", name)),
-        }
+    pub fn bind_function(&mut self, id: SymbolId, func: Function) {
+        self.funcs.insert(id, func);
     }
 
-    pub fn load_var(&mut self, name: &str) -> Result<Value> {
-        let var_value = self.get_var_value(name)?;
-        self.create_load(var_value)
+    pub fn symbol_function(&self, id: SymbolId) -> Function {
+        self.funcs[&id]
     }
 
-    pub fn store_var(&mut self, name: &str, value: Value) -> Result<()> {
-        let var_value = self.get_var_value(name)?;
-        self.create_store(var_value, value)
+    /// 为局部变量生成 `@名字_作用域层数` 的 alloc, 有初始值时再 store
+    pub fn create_variable(&mut self, id: SymbolId, init_val: Option<Value>) -> Result<Value> {
+        let symbol = self.symbol(id);
+        let scoped_name = format!("@{}_{}", symbol.name, symbol.scope_level);
+        let ty = symbol.ty.to_koop();
+        let value = self.create_alloc(ty, scoped_name)?;
+        self.bind_value(id, value);
+
+        if let Some(init_val) = init_val {
+            self.create_store(value, init_val)?;
+        }
+        Ok(value)
     }
 
-    pub fn contains_var_in_current_scope(&self, name: &str) -> bool {
-        match self.lookup(name) {
-            Ok(SymbolKind::Variable { scope_level, .. }) => {
-                *scope_level == self.current_scope_level
-            }
-            _ => false,
+    /// 按行优先下标 `flat_index` 逐维生成 getelemptr, 得到数组元素的地址
+    pub fn create_elem_ptr(
+        &mut self,
+        base: Value,
+        flat_index: usize,
+        dims: &[usize],
+    ) -> Result<Value> {
+        let mut ptr = base;
+        let mut stride: usize = dims.iter().product();
+        for dim in dims {
+            stride /= dim;
+            let index = self.create_constant((flat_index / stride % dim) as i32);
+            ptr = self.create_get_elem_ptr(ptr, index)?;
         }
+        Ok(ptr)
     }
 }
@@ -1,6 +1,6 @@
 use anyhow::ensure;
 use koopa::ir::{builder::LocalInstBuilder, BinaryOp, Value};
-use koopa::ir::{BasicBlock, Function, Type, ValueKind};
+use koopa::ir::{BasicBlock, Function, Type, TypeKind};
 
 use super::IRBuilder;
 use super::Result;
@@ -14,7 +14,7 @@ impl IRBuilder {
             .ok_or_else(|| anyhow::anyhow!("No active basic block"))?;
         Ok((func, bb))
     }
-    fn create_instruction<F>(&mut self, create_value: F) -> Result<Value>
+    pub(super) fn create_instruction<F>(&mut self, create_value: F) -> Result<Value>
     where
         F: FnOnce(&mut koopa::ir::dfg::DataFlowGraph) -> Value,
     {
@@ -24,7 +24,7 @@ impl IRBuilder {
         let value = create_value(dfg);
 
         if let Some(val_data) = dfg.values().get(&value) {
-            if val_data.name().is_none() && needs_id(val_data.kind()) {
+            if val_data.name().is_none() && !val_data.ty().is_unit() {
                 let id = self.next_value_id();
                 self.program
                     .func_mut(func)
@@ -44,28 +44,27 @@ impl IRBuilder {
     }
 
     pub fn create_binary(&mut self, op: &BinaryOp, lhs: Value, rhs: Value) -> Result<Value> {
-        let (func, _) = self.get_current_context()?;
-
-        let lhs_ty = self.program.func(func).dfg().value(lhs).ty();
-        let rhs_ty = self.program.func(func).dfg().value(rhs).ty();
+        let lhs_ty = self.value_type(lhs)?;
+        let rhs_ty = self.value_type(rhs)?;
         ensure!(
-            lhs_ty == rhs_ty,
-            "Type mismatch in binary op: {:?} vs {:?}",
+            lhs_ty.is_i32() && lhs_ty == rhs_ty,
+            "Type mismatch in binary op: {} vs {}",
             lhs_ty,
             rhs_ty
         );
 
         let real_op = *op;
-        Ok(self.create_instruction(|dfg| dfg.new_value().binary(real_op, lhs, rhs))?)
+        self.create_instruction(|dfg| dfg.new_value().binary(real_op, lhs, rhs))
     }
 
-    pub(crate) fn to_logic_val(&mut self, val: Value) -> Result<Value> {
+    pub(crate) fn create_logic_val(&mut self, val: Value) -> Result<Value> {
         let zero = self.create_constant(0);
-        let inner = self.create_binary(&BinaryOp::Eq, zero, val)?;
-        self.create_binary(&BinaryOp::Eq, zero, inner)
+        self.create_binary(&BinaryOp::NotEq, val, zero)
     }
 
+    /// 同一函数中重名的 alloc 会加上后缀, 见 `unique_local_name`
     pub fn create_alloc(&mut self, ty: Type, name: String) -> Result<Value> {
+        let name = self.unique_local_name(&name);
         self.create_instruction(|dfg| {
             let alloc = dfg.new_value().alloc(ty);
             dfg.set_value_name(alloc, Some(name));
@@ -74,14 +73,50 @@ impl IRBuilder {
     }
 
     pub fn create_store(&mut self, ptr: Value, value: Value) -> Result<()> {
+        let ptr_ty = self.value_type(ptr)?;
+        let value_ty = self.value_type(value)?;
+        ensure!(
+            ptr_ty == Type::get_pointer(value_ty.clone()),
+            "Type mismatch in store: cannot store {} to {}",
+            value_ty,
+            ptr_ty
+        );
         self.create_instruction(|dfg| dfg.new_value().store(value, ptr))?;
         Ok(())
     }
+    pub fn create_get_elem_ptr(&mut self, src: Value, index: Value) -> Result<Value> {
+        let src_ty = self.value_type(src)?;
+        let is_array_ptr = matches!(
+            src_ty.kind(),
+            TypeKind::Pointer(base) if matches!(base.kind(), TypeKind::Array(..))
+        );
+        ensure!(is_array_ptr, "Cannot index into a value of type {}", src_ty);
+        let index_ty = self.value_type(index)?;
+        ensure!(
+            index_ty.is_i32(),
+            "Array index must be an int, got {}",
+            index_ty
+        );
+        self.create_instruction(|dfg| dfg.new_value().get_elem_ptr(src, index))
+    }
+
+    pub fn create_get_ptr(&mut self, src: Value, index: Value) -> Result<Value> {
+        let src_ty = self.value_type(src)?;
+        ensure!(
+            matches!(src_ty.kind(), TypeKind::Pointer(_)),
+            "Cannot index into a value of type {}",
+            src_ty
+        );
+        let index_ty = self.value_type(index)?;
+        ensure!(
+            index_ty.is_i32(),
+            "Array index must be an int, got {}",
+            index_ty
+        );
+        self.create_instruction(|dfg| dfg.new_value().get_ptr(src, index))
+    }
+
     pub fn create_load(&mut self, ptr: Value) -> Result<Value> {
         self.create_instruction(|dfg| dfg.new_value().load(ptr))
     }
 }
-
-fn needs_id(kind: &ValueKind) -> bool {
-    matches!(kind, ValueKind::Load(_) | ValueKind::Binary(_))
-}
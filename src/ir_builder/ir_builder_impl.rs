@@ -1,12 +1,9 @@
-use std::collections::HashMap;
-
+use anyhow::ensure;
 use koopa::ir::{
-    builder::{BasicBlockBuilder, LocalInstBuilder, ValueBuilder},
-    BasicBlock, Function, FunctionData, Type, Value,
+    builder::{BasicBlockBuilder, GlobalInstBuilder, LocalInstBuilder, ValueBuilder},
+    BasicBlock, Function, FunctionData, Type, TypeKind, Value, ValueKind,
 };
 
-use crate::{semantic::SymbolKind, traits::semantic::SymbolTable};
-
 use super::IRBuilder;
 use super::Result;
 impl IRBuilder {
@@ -16,49 +13,108 @@ impl IRBuilder {
         params: Vec<(String, Type)>,
         return_type: Type,
     ) -> Function {
+        self.local_names.clear();
+        let params = params
+            .into_iter()
+            .map(|(n, t)| (Some(self.unique_local_name(&n)), t))
+            .collect();
         let func = self.program.new_func(FunctionData::with_param_names(
             name.into(),
-            params.into_iter().map(|(n, t)| (Some(n), t)).collect(),
+            params,
             return_type,
         ));
         self.set_current_func(func);
+        self.value_counter = 0;
+        self.label_counter = 0;
         func
     }
+
+    pub fn current_func_params(&self) -> Result<Vec<Value>> {
+        let func = self
+            .current_func
+            .ok_or_else(|| anyhow::anyhow!("No active function"))?;
+        Ok(self.program.func(func).params().to_vec())
+    }
     pub fn create_bb(&mut self, name: &str) -> Result<BasicBlock> {
-        let func = self.current_func.expect("No active function");
+        let func = self
+            .current_func
+            .ok_or_else(|| anyhow::anyhow!("No active function"))?;
         let bb = self
             .program
             .func_mut(func)
             .dfg_mut()
             .new_bb()
             .basic_block(Some(name.into()));
-        self.program
-            .func_mut(func)
-            .layout_mut()
-            .bbs_mut()
-            .push_key_back(bb)
-            .map_err(|_| anyhow::anyhow!("Failed to create basic block"))?;
-
-        self.set_current_bb(bb);
         Ok(bb)
     }
 
-    pub fn create_ret(&mut self, value: Option<Value>) {
+    /// 是否有跳转指令以 `bb` 为目标
+    pub fn is_bb_used(&self, bb: BasicBlock) -> bool {
         let func = self.current_func.expect("No active function");
-        let bb = self.current_block.expect("No active basic block");
-        let ret = self
-            .program
-            .func_mut(func)
-            .dfg_mut()
-            .new_value()
-            .ret(Some(value).expect("No return value"));
-        self.program
-            .func_mut(func)
-            .layout_mut()
-            .bb_mut(bb)
-            .insts_mut()
-            .push_key_back(ret)
-            .unwrap();
+        !self.program.func(func).dfg().bb(bb).used_by().is_empty()
+    }
+
+    /// 删除尚未加入布局的基本块
+    pub fn remove_bb(&mut self, bb: BasicBlock) {
+        let func = self.current_func.expect("No active function");
+        self.program.func_mut(func).dfg_mut().remove_bb(bb);
+    }
+
+    pub fn create_ret(&mut self, value: Option<Value>) -> Result<()> {
+        self.create_instruction(|dfg| dfg.new_value().ret(value))?;
+        Ok(())
+    }
+
+    /// 实参的个数和类型已由语义分析检查
+    pub fn create_call(&mut self, callee: Function, args: Vec<Value>) -> Result<Value> {
+        self.create_instruction(|dfg| dfg.new_value().call(callee, args))
+    }
+
+    pub fn create_branch(
+        &mut self,
+        cond: Value,
+        true_bb: BasicBlock,
+        false_bb: BasicBlock,
+    ) -> Result<()> {
+        let cond_ty = self.value_type(cond)?;
+        ensure!(
+            cond_ty.is_i32(),
+            "Condition must be an int, got {}",
+            cond_ty
+        );
+        self.create_instruction(|dfg| dfg.new_value().branch(cond, true_bb, false_bb))?;
+        Ok(())
+    }
+
+    pub fn create_jump(&mut self, target: BasicBlock) -> Result<()> {
+        self.create_instruction(|dfg| dfg.new_value().jump(target))?;
+        Ok(())
+    }
+
+    /// 当前基本块尚未以 br/jump/ret 结尾时才跳转到 `target`
+    pub fn create_jump_if_open(&mut self, target: BasicBlock) -> Result<()> {
+        if !self.is_current_bb_terminated() {
+            self.create_jump(target)?;
+        }
+        Ok(())
+    }
+
+    pub fn is_current_bb_terminated(&self) -> bool {
+        let (Some(func), Some(bb)) = (self.current_func, self.current_block) else {
+            return false;
+        };
+        let func_data = self.program.func(func);
+        func_data
+            .layout()
+            .bbs()
+            .node(&bb)
+            .and_then(|node| node.insts().back_key())
+            .is_some_and(|inst| {
+                matches!(
+                    func_data.dfg().value(*inst).kind(),
+                    ValueKind::Branch(_) | ValueKind::Jump(_) | ValueKind::Return(_)
+                )
+            })
     }
 
     pub fn create_constant(&mut self, value: i32) -> Value {
@@ -71,52 +127,41 @@ impl IRBuilder {
     }
 
     pub fn value_type(&self, val: Value) -> Result<Type> {
-        let func = self.current_func.expect("No active function");
+        if val.is_global() {
+            return Ok(self.program.borrow_value(val).ty().clone());
+        }
+        let func = self
+            .current_func
+            .ok_or_else(|| anyhow::anyhow!("No active function"))?;
         let ty = self.program.func(func).dfg().value(val).ty().clone();
         Ok(ty)
     }
-}
 
-impl SymbolTable for IRBuilder {
-    fn enter_scope(&mut self) -> Result<()> {
-        self.symbol_spaces.push(HashMap::new());
-        self.current_scope_level += 1;
-        Ok(())
-    }
-
-    fn exit_scope(&mut self) -> Result<()> {
-        self.symbol_spaces
-            .pop()
-            .ok_or_else(|| anyhow::anyhow!("No scope to exit"))?;
-        self.current_scope_level -= 1;
-
-        Ok(())
+    /// 生成 `global @name = alloc ty, init`, `init` 是行优先展开后的初始值,
+    /// 没有初始值或全为 0 的子数组使用 zeroinit
+    pub fn create_global_alloc(&mut self, name: &str, ty: Type, init: Option<&[i32]>) -> Value {
+        let init = match init {
+            Some(values) => self.create_global_init(&ty, values),
+            None => self.program.new_value().zero_init(ty),
+        };
+        let alloc = self.program.new_value().global_alloc(init);
+        self.program.set_value_name(alloc, Some(name.to_string()));
+        alloc
     }
 
-    fn lookup(&self, name: &str) -> Result<&SymbolKind> {
-        for scope in self.symbol_spaces.iter().rev() {
-            if let Some(sym) = scope.get(name) {
-                return Ok(sym);
+    fn create_global_init(&mut self, ty: &Type, values: &[i32]) -> Value {
+        match ty.kind() {
+            TypeKind::Array(..) if values.iter().all(|&v| v == 0) => {
+                self.program.new_value().zero_init(ty.clone())
             }
+            TypeKind::Array(base, _) => {
+                let elems = values
+                    .chunks(base.size() / 4)
+                    .map(|chunk| self.create_global_init(base, chunk))
+                    .collect();
+                self.program.new_value().aggregate(elems)
+            }
+            _ => self.program.new_value().integer(values[0]),
         }
-        Err(anyhow::anyhow!("Symbol {} not found", name))
-    }
-
-    fn add_symbol(&mut self, name: &str, kind: SymbolKind) -> Result<()> {
-        let scope = self.symbol_spaces.last_mut().ok_or_else(|| {
-            anyhow::anyhow!(
-                "
-No active scope"
-            )
-        })?;
-        if scope.contains_key(name) {
-            return Err(anyhow::anyhow!("Symbol {} already exists", name));
-        }
-        scope.insert(name.to_string(), kind);
-        Ok(())
-    }
-
-    fn current_scope_level(&self) -> usize {
-        self.current_scope_level
     }
 }
@@ -1,40 +1,91 @@
 mod def_decl;
 mod ir_builder_exp_op;
 mod ir_builder_impl;
+mod names;
+mod runtime_lib;
 use koopa::ir::*;
-use std::collections::HashMap;
+pub use names::check_unique_names;
+use std::collections::{HashMap, HashSet};
 
 use super::Result;
-use crate::{asm_generator::AsmGenerator, ir_printer::IRPrinter, semantic::SymbolKind};
+use crate::semantic::{Symbol, SymbolId};
+use crate::{asm_generator::AsmGenerator, ir_printer::IRPrinter};
+/// 一层 while 循环的跳转目标: continue 跳回条件块, break 跳到出口块
+#[derive(Debug, Clone, Copy)]
+pub struct LoopContext {
+    pub cond_bb: BasicBlock,
+    pub end_bb: BasicBlock,
+}
+
 pub struct IRBuilder {
     program: Program,
     current_func: Option<Function>,
     current_block: Option<BasicBlock>,
     value_counter: usize,
-    symbol_spaces: Vec<HashMap<String, SymbolKind>>,
-    current_scope_level: usize,
+    label_counter: usize,
+    loop_stack: Vec<LoopContext>,
+    // 语义分析得到的符号表, 以及符号对应的 alloc 和函数
+    symbols: Vec<Symbol>,
+    values: HashMap<SymbolId, Value>,
+    funcs: HashMap<SymbolId, Function>,
+    // 已经使用的名字, 见 names.rs
+    global_names: HashSet<String>,
+    local_names: HashSet<String>,
 }
+
 // IRBuilder getter and new methods
 impl IRBuilder {
-    pub fn new() -> Self {
-        Self {
+    pub fn new(symbols: Vec<Symbol>) -> Self {
+        let global_names = symbols
+            .iter()
+            .filter(|symbol| symbol.is_global())
+            .map(|symbol| format!("@{}", symbol.name))
+            .collect();
+        let mut builder = Self {
             program: Program::new(),
             current_func: None,
             current_block: None,
             value_counter: 0,
-            symbol_spaces: vec![HashMap::new()],
-            current_scope_level: 0,
-        }
+            label_counter: 0,
+            loop_stack: Vec::new(),
+            symbols,
+            values: HashMap::new(),
+            funcs: HashMap::new(),
+            global_names,
+            local_names: HashSet::new(),
+        };
+        builder.declare_runtime_lib();
+        builder
     }
     fn next_value_id(&mut self) -> usize {
         let id = self.value_counter;
         self.value_counter += 1;
         id
     }
+    /// 分支/循环生成的基本块共用同一个编号, 例如 `%then_0`/`%else_0`/`%end_0`
+    pub fn next_label_id(&mut self) -> usize {
+        let id = self.label_counter;
+        self.label_counter += 1;
+        id
+    }
+    pub fn enter_loop(&mut self, cond_bb: BasicBlock, end_bb: BasicBlock) {
+        self.loop_stack.push(LoopContext { cond_bb, end_bb });
+    }
+
+    pub fn exit_loop(&mut self) {
+        self.loop_stack.pop().expect("No loop to exit");
+    }
+
+    pub fn current_loop(&self) -> Option<LoopContext> {
+        self.loop_stack.last().copied()
+    }
     pub fn program(&self) -> &Program {
         &self.program
     }
 
+    pub fn into_program(self) -> Program {
+        self.program
+    }
     pub fn program_mut(&mut self) -> &mut Program {
         &mut self.program
     }
@@ -42,7 +93,17 @@ impl IRBuilder {
         self.current_func = Some(func);
     }
 
+    /// 切换到 `bb`, 基本块在第一次被切换到时才加入函数的布局,
+    /// 这样嵌套语句生成的块会按源码顺序排列
     pub fn set_current_bb(&mut self, bb: BasicBlock) {
+        let func = self.current_func.expect("No active function");
+        let layout = self.program.func_mut(func).layout_mut();
+        if !layout.bbs().contains_key(&bb) {
+            layout
+                .bbs_mut()
+                .push_key_back(bb)
+                .expect("Failed to insert basic block");
+        }
         self.current_block = Some(bb);
     }
 
@@ -52,10 +113,8 @@ impl IRBuilder {
     pub fn to_asm(&self, asm_gen: &mut AsmGenerator) -> String {
         asm_gen.generate_program(&self.program)
     }
-    pub fn to_ir(&self, printer: &mut IRPrinter) -> String {
-        printer.print_program(&self.program)
-    }
-    pub fn contains_var(&self, name: &str) -> bool {
-        self.symbol_spaces.last().unwrap().contains_key(name)
+    pub fn to_ir(&self, printer: &mut IRPrinter) -> Result<String> {
+        check_unique_names(&self.program)?;
+        Ok(printer.print_program(&self.program))
     }
 }
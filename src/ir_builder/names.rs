@@ -0,0 +1,80 @@
+use std::collections::HashSet;
+
+use anyhow::ensure;
+use koopa::ir::Program;
+
+use super::IRBuilder;
+use super::Result;
+
+// Koopa 中全局值、函数和各函数内的值共用一个命名空间, 基本块的名字在函数内单独命名
+
+impl IRBuilder {
+    /// 返回当前函数内未被使用的名字, 重名时依次尝试 `@a_2_1`, `@a_2_2`, ...
+    /// 全局变量和函数的名字在创建 IRBuilder 时已经预留
+    pub fn unique_local_name(&mut self, name: &str) -> String {
+        let mut unique = name.to_string();
+        let mut suffix = 0;
+        while self.global_names.contains(&unique) || self.local_names.contains(&unique) {
+            suffix += 1;
+            unique = format!("{}_{}", name, suffix);
+        }
+        self.local_names.insert(unique.clone());
+        unique
+    }
+}
+
+/// 检查程序中没有重名的值和基本块, 否则文本形式的 Koopa IR 无法被解析
+pub fn check_unique_names(program: &Program) -> Result<()> {
+    let mut globals = HashSet::new();
+    for &value in program.inst_layout() {
+        let data = program.borrow_value(value);
+        let name = data.name().as_ref().expect("Global value without name");
+        ensure!(
+            globals.insert(name.clone()),
+            "Duplicate global name {}",
+            name
+        );
+    }
+    for &func in program.func_layout() {
+        let name = program.func(func).name();
+        ensure!(
+            globals.insert(name.to_string()),
+            "Duplicate global name {}",
+            name
+        );
+    }
+
+    for &func in program.func_layout() {
+        let data = program.func(func);
+        let mut values = HashSet::new();
+        let mut bbs = HashSet::new();
+        let insts = data
+            .layout()
+            .bbs()
+            .iter()
+            .flat_map(|(_, node)| node.insts().keys());
+        for &value in data.params().iter().chain(insts) {
+            let Some(name) = data.dfg().value(value).name() else {
+                continue;
+            };
+            ensure!(
+                !globals.contains(name) && values.insert(name),
+                "Duplicate name {} in function {}",
+                name,
+                data.name()
+            );
+        }
+        for bb in data.layout().bbs().keys() {
+            let Some(name) = data.dfg().bb(*bb).name() else {
+                continue;
+            };
+            ensure!(
+                bbs.insert(name),
+                "Duplicate basic block {} in function {}",
+                name,
+                data.name()
+            );
+        }
+    }
+    Ok(())
+}
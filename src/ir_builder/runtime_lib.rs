@@ -0,0 +1,26 @@
+use koopa::ir::FunctionData;
+
+use crate::semantic::{SymbolId, SymbolKind, Type};
+
+use super::IRBuilder;
+
+impl IRBuilder {
+    /// 为符号表中的 SysY 运行时库函数生成 Koopa 中的 `decl`
+    pub(super) fn declare_runtime_lib(&mut self) {
+        for i in 0..self.symbols.len() {
+            let symbol = &self.symbols[i];
+            if !matches!(symbol.kind, SymbolKind::RuntimeFunction) {
+                continue;
+            }
+            let Type::Function(params, ret) = &symbol.ty else {
+                unreachable!("Function symbol must have a function type");
+            };
+            let func = self.program.new_func(FunctionData::new_decl(
+                format!("@{}", symbol.name),
+                params.iter().map(Type::to_koop).collect(),
+                ret.to_koop(),
+            ));
+            self.bind_function(SymbolId(i), func);
+        }
+    }
+}
@@ -1,51 +1,233 @@
 use super::IRPrinter;
-use koopa::ir::{BasicBlock, FunctionData, Program, Value, ValueKind};
+use koopa::ir::{BasicBlock, FunctionData, Program, TypeKind, Value, ValueKind};
+use std::collections::{HashMap, HashSet};
 use std::fmt::Write;
 
+impl Default for IRPrinter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl IRPrinter {
     pub fn new() -> Self {
         Self {
             output: String::new(),
             indent_level: 0,
+            global_names: HashMap::new(),
+            local_names: HashMap::new(),
+            bb_names: HashMap::new(),
         }
     }
 
     pub fn print_program(&mut self, program: &Program) -> String {
         self.output.clear();
+        self.global_names.clear();
+        self.assign_global_names(program);
+
+        // Print global allocs
+        for &value in program.inst_layout() {
+            self.print_global(program, value);
+        }
+        if !program.inst_layout().is_empty() {
+            self.output.push('\n');
+        }
+
+        // Print runtime library declarations
+        let (decls, funcs): (Vec<_>, Vec<_>) = program
+            .func_layout()
+            .iter()
+            .map(|&func| program.func(func))
+            .partition(|func| func.layout().entry_bb().is_none());
+        for func in &decls {
+            self.print_decl(func);
+        }
+        if !decls.is_empty() {
+            self.output.push('\n');
+        }
 
         // Print each function
-        for &func in program.func_layout() {
-            self.print_function(program.func(func));
+        for func in funcs {
+            self.print_function(program, func);
             self.output.push('\n');
         }
 
         self.output.clone()
     }
 
-    fn print_function(&mut self, func: &FunctionData) {
+    /// 没有名字的全局变量按 `@0`, `@1`, ... 命名, 跳过已被使用的名字
+    fn assign_global_names(&mut self, program: &Program) {
+        let mut used: HashSet<String> = program
+            .inst_layout()
+            .iter()
+            .filter_map(|&value| program.borrow_value(value).name().clone())
+            .chain(
+                program
+                    .func_layout()
+                    .iter()
+                    .map(|&func| program.func(func).name().to_string()),
+            )
+            .collect();
+        let mut counter = 0;
+        for &value in program.inst_layout() {
+            let name = match program.borrow_value(value).name() {
+                Some(name) => name.clone(),
+                None => fresh_name("@", &mut counter, &mut used),
+            };
+            self.global_names.insert(value, name);
+        }
+    }
+
+    /// 没有名字的形参、基本块参数和指令结果按 `%0`, `%1`, ... 命名
+    fn assign_local_names(&mut self, func: &FunctionData) {
+        self.local_names.clear();
+        let bbs = func.layout().bbs();
+        let bb_params = bbs
+            .keys()
+            .flat_map(|&bb| func.dfg().bb(bb).params().iter().copied());
+        let insts = bbs
+            .iter()
+            .flat_map(|(_, node)| node.insts().keys().copied());
+        let values: Vec<Value> = func
+            .params()
+            .iter()
+            .copied()
+            .chain(bb_params)
+            .chain(insts)
+            .collect();
+
+        let mut used: HashSet<String> = self.global_names.values().cloned().collect();
+        used.extend(
+            values
+                .iter()
+                .filter_map(|&v| func.dfg().value(v).name().clone()),
+        );
+        let mut counter = 0;
+        for value in values {
+            let data = func.dfg().value(value);
+            let name = match data.name() {
+                Some(name) => name.clone(),
+                None if data.ty().is_unit() => continue,
+                None => fresh_name("%", &mut counter, &mut used),
+            };
+            self.local_names.insert(value, name);
+        }
+        self.bb_names = bb_names(func);
+    }
+
+    fn print_global(&mut self, program: &Program, value: Value) {
+        let data = program.borrow_value(value);
+        let ValueKind::GlobalAlloc(alloc) = data.kind() else {
+            unreachable!("Global value must be an alloc");
+        };
+        let init_ty = program.borrow_value(alloc.init()).ty().clone();
+        let init_str = Self::global_init_to_string(program, alloc.init());
+        writeln!(
+            &mut self.output,
+            "global {} = alloc {}, {}",
+            self.global_names[&value], init_ty, init_str
+        )
+        .unwrap();
+    }
+
+    fn global_init_to_string(program: &Program, init: Value) -> String {
+        let init = program.borrow_value(init);
+        match init.kind() {
+            ValueKind::Integer(int) => int.value().to_string(),
+            ValueKind::ZeroInit(_) => "zeroinit".to_string(),
+            ValueKind::Undef(_) => "undef".to_string(),
+            ValueKind::Aggregate(agg) => {
+                let elems = agg
+                    .elems()
+                    .iter()
+                    .map(|&elem| Self::global_init_to_string(program, elem))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                format!("{{{}}}", elems)
+            }
+            _ => unreachable!("Unsupported global initializer {:?}", init.kind()),
+        }
+    }
+
+    fn print_decl(&mut self, func: &FunctionData) {
+        let TypeKind::Function(params, ret) = func.ty().kind() else {
+            unreachable!("Function must have a function type");
+        };
+        let params = params
+            .iter()
+            .map(|ty| ty.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let ret_ty = if ret.is_unit() {
+            String::new()
+        } else {
+            format!(": {}", ret)
+        };
+        writeln!(
+            &mut self.output,
+            "decl {}({}){}",
+            func.name(),
+            params,
+            ret_ty
+        )
+        .unwrap();
+    }
+
+    fn print_function(&mut self, program: &Program, func: &FunctionData) {
+        self.assign_local_names(func);
+
         // Print function header
-        writeln!(&mut self.output, "fun {} {} {{", func.name(), func.ty()).unwrap();
+        let params = self.params_to_string(func, func.params());
+        let ret_ty = match func.ty().kind() {
+            TypeKind::Function(_, ret) if !ret.is_unit() => format!(": {}", ret),
+            _ => String::new(),
+        };
+        writeln!(
+            &mut self.output,
+            "fun {}({}){} {{",
+            func.name(),
+            params,
+            ret_ty
+        )
+        .unwrap();
 
         self.indent_level += 1;
 
         // Print each basic block
         for (bb, _) in func.layout().bbs() {
-            self.print_basic_block(func, *bb);
+            self.print_basic_block(program, func, *bb);
         }
 
         self.indent_level -= 1;
         self.output.push_str("}\n");
     }
 
-    fn print_basic_block(&mut self, func: &FunctionData, bb: BasicBlock) {
+    /// 形参和基本块参数: `%x: i32, %y: *i32`
+    fn params_to_string(&self, func: &FunctionData, params: &[Value]) -> String {
+        params
+            .iter()
+            .map(|&p| {
+                let data = func.dfg().value(p);
+                format!("{}: {}", self.value_to_string(func, p), data.ty())
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    fn print_basic_block(&mut self, program: &Program, func: &FunctionData, bb: BasicBlock) {
         // Print block label
-        let bb_data = func.dfg().bb(bb);
-        let name = bb_data
-            .name()
-            .as_ref()
-            .map_or("".to_string(), |n| n.clone());
+        let params = func.dfg().bb(bb).params();
+        let label = if params.is_empty() {
+            self.bb_names[&bb].clone()
+        } else {
+            format!(
+                "{}({})",
+                self.bb_names[&bb],
+                self.params_to_string(func, params)
+            )
+        };
         self.indent();
-        writeln!(&mut self.output, "{}:", name).unwrap();
+        writeln!(&mut self.output, "{}:", label).unwrap();
 
         self.indent_level += 1;
 
@@ -56,97 +238,117 @@ impl IRPrinter {
             .node(&bb)
             .expect("Basic block not found");
         for (inst, _) in bb_node.insts() {
-            self.print_instruction(func, *inst);
+            self.print_instruction(program, func, *inst);
         }
         self.indent_level -= 1;
     }
 
-    fn print_instruction(&mut self, func: &FunctionData, value: Value) {
+    fn print_instruction(&mut self, program: &Program, func: &FunctionData, value: Value) {
         let data = func.dfg().value(value);
 
-        self.indent();
-        match data.kind() {
-            ValueKind::Return(ret) => {
-                if let Some(val) = ret.value() {
-                    let val = self.value_to_string(func, val);
-                    writeln!(&mut self.output, "ret {}", val).unwrap();
-                } else {
-                    writeln!(&mut self.output, "ret").unwrap();
-                }
-            }
-            ValueKind::Integer(int) => {
-                writeln!(&mut self.output, "{}", int.value()).unwrap();
-            }
+        let inst = match data.kind() {
+            ValueKind::Return(ret) => match ret.value() {
+                Some(val) => format!("ret {}", self.value_to_string(func, val)),
+                None => "ret".to_string(),
+            },
             ValueKind::Binary(bin) => {
                 let lhs = self.value_to_string(func, bin.lhs());
                 let rhs = self.value_to_string(func, bin.rhs());
-                let op: &str = match bin.op() {
-                    koopa::ir::BinaryOp::Sub => "sub",
-                    koopa::ir::BinaryOp::Eq => "eq",
-                    koopa::ir::BinaryOp::Add => "add",
-                    koopa::ir::BinaryOp::Mul => "mul",
-                    koopa::ir::BinaryOp::Div => "div",
-                    koopa::ir::BinaryOp::Mod => "mod",
-                    koopa::ir::BinaryOp::And => "and",
-                    koopa::ir::BinaryOp::Or => "or",
-                    koopa::ir::BinaryOp::Lt => "lt",
-                    koopa::ir::BinaryOp::Gt => "gt",
-                    koopa::ir::BinaryOp::Le => "le",
-                    koopa::ir::BinaryOp::Ge => "ge",
-                    koopa::ir::BinaryOp::NotEq => "ne",
-
-                    _ => unreachable!(),
-                };
-                let value = self.value_to_string(func, value);
-                writeln!(&mut self.output, "{} = {} {}, {}", value, op, lhs, rhs).unwrap();
+                format!("{} {}, {}", bin.op(), lhs, rhs)
             }
             ValueKind::Alloc(_) => {
-                // Handle alloc instruction
-                let value = self.value_to_string(func, value);
-                writeln!(&mut self.output, "{} = alloc i32", value).unwrap();
+                let TypeKind::Pointer(base) = data.ty().kind() else {
+                    unreachable!("Alloc must have a pointer type");
+                };
+                format!("alloc {}", base)
             }
-            ValueKind::Load(load) => {
-                // Handle load instruction
-                let value = self.value_to_string(func, value);
-                let src = self.value_to_string(func, load.src());
-                writeln!(&mut self.output, "{} = load {}", value, src).unwrap();
+            ValueKind::GetElemPtr(gep) => {
+                let src = self.value_to_string(func, gep.src());
+                let index = self.value_to_string(func, gep.index());
+                format!("getelemptr {}, {}", src, index)
             }
+            ValueKind::GetPtr(gp) => {
+                let src = self.value_to_string(func, gp.src());
+                let index = self.value_to_string(func, gp.index());
+                format!("getptr {}, {}", src, index)
+            }
+            ValueKind::Load(load) => format!("load {}", self.value_to_string(func, load.src())),
             ValueKind::Store(store) => {
-                // Handle store instruction
                 let value = self.value_to_string(func, store.value());
                 let dest = self.value_to_string(func, store.dest());
-                writeln!(&mut self.output, "store {}, {}", value, dest).unwrap();
+                format!("store {}, {}", value, dest)
+            }
+            ValueKind::Branch(br) => {
+                let cond = self.value_to_string(func, br.cond());
+                let true_bb = self.bb_target(func, br.true_bb(), br.true_args());
+                let false_bb = self.bb_target(func, br.false_bb(), br.false_args());
+                format!("br {}, {}, {}", cond, true_bb, false_bb)
+            }
+            ValueKind::Jump(jump) => {
+                format!("jump {}", self.bb_target(func, jump.target(), jump.args()))
+            }
+            ValueKind::Call(call) => {
+                let callee = program.func(call.callee()).name();
+                format!(
+                    "call {}({})",
+                    callee,
+                    self.args_to_string(func, call.args())
+                )
             }
-            _ => writeln!(&mut self.output, "{:?}", data.kind()).unwrap(),
+            kind => unreachable!("{:?} is not an instruction", kind),
+        };
+
+        self.indent();
+        // 有返回值的指令需要给结果命名
+        if data.ty().is_unit() {
+            writeln!(&mut self.output, "{}", inst).unwrap();
+        } else {
+            let value = self.value_to_string(func, value);
+            writeln!(&mut self.output, "{} = {}", value, inst).unwrap();
+        }
+    }
+
+    /// 跳转目标, 带基本块参数时为 `%bb(%0, 1)`
+    fn bb_target(&self, func: &FunctionData, bb: BasicBlock, args: &[Value]) -> String {
+        let name = &self.bb_names[&bb];
+        if args.is_empty() {
+            name.clone()
+        } else {
+            format!("{}({})", name, self.args_to_string(func, args))
         }
     }
 
+    fn args_to_string(&self, func: &FunctionData, args: &[Value]) -> String {
+        args.iter()
+            .map(|&arg| self.value_to_string(func, arg))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// 常量直接写出, 其余的值使用名字
     fn value_to_string(&self, func: &FunctionData, value: Value) -> String {
+        if let Some(name) = self.global_names.get(&value) {
+            return name.clone();
+        }
         let data = func.dfg().value(value);
         match data.kind() {
             ValueKind::Integer(int) => int.value().to_string(),
-            ValueKind::Alloc(_) => {
-                if let Some(name) = data.name() {
-                    if name.starts_with('@') {
-                        name.to_string()
-                    } else {
-                        format!("@{}", name)
-                    }
-                } else {
-                    unreachable!("Alloc value without name")
-                }
-            }
-            ValueKind::Binary(_) | ValueKind::Load(_) | ValueKind::Store(_) => {
-                if let Some(name) = data.name() {
-                    name.to_string()
-                } else {
-                    unreachable!("Value without name")
-                }
-            }
-            _ => {
-                let name = data.name().as_ref().unwrap();
-                format!("{}", name)
+            ValueKind::ZeroInit(_) => "zeroinit".to_string(),
+            ValueKind::Undef(_) => "undef".to_string(),
+            ValueKind::Aggregate(agg) => {
+                let elems = agg
+                    .elems()
+                    .iter()
+                    .map(|&elem| self.value_to_string(func, elem))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                format!("{{{}}}", elems)
             }
+            _ => self
+                .local_names
+                .get(&value)
+                .cloned()
+                .unwrap_or_else(|| panic!("Value {:?} is not in the function layout", data)),
         }
     }
 
@@ -156,3 +358,32 @@ impl IRPrinter {
         }
     }
 }
+
+/// 基本块打印时使用的名字, 没有名字的基本块按 `%bb0`, `%bb1`, ... 命名
+pub(super) fn bb_names(func: &FunctionData) -> HashMap<BasicBlock, String> {
+    let bbs = func.layout().bbs();
+    let mut used: HashSet<String> = bbs
+        .keys()
+        .filter_map(|&bb| func.dfg().bb(bb).name().clone())
+        .collect();
+    let mut counter = 0;
+    bbs.keys()
+        .map(|&bb| {
+            let name = match func.dfg().bb(bb).name() {
+                Some(name) => name.clone(),
+                None => fresh_name("%bb", &mut counter, &mut used),
+            };
+            (bb, name)
+        })
+        .collect()
+}
+
+fn fresh_name(prefix: &str, counter: &mut usize, used: &mut HashSet<String>) -> String {
+    loop {
+        let name = format!("{}{}", prefix, counter);
+        *counter += 1;
+        if used.insert(name.clone()) {
+            return name;
+        }
+    }
+}
@@ -1,8 +1,20 @@
 mod ir_print;
+mod parse;
+mod round_trip;
 
+use std::collections::HashMap;
 
+use koopa::ir::{BasicBlock, Value};
+
+pub use parse::{parse_file, parse_program};
+pub use round_trip::check_round_trip;
 
 pub struct IRPrinter {
     output: String,
     indent_level: usize,
+    // 全局变量不在函数的 dfg 中, 打印前先收集名字
+    global_names: HashMap<Value, String>,
+    // 当前函数中各个值和基本块的名字, 没有名字的在打印前临时命名
+    local_names: HashMap<Value, String>,
+    bb_names: HashMap<BasicBlock, String>,
 }
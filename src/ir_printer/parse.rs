@@ -0,0 +1,26 @@
+use std::path::Path;
+
+use anyhow::{anyhow, Context};
+use koopa::front::Driver;
+use koopa::ir::Program;
+
+use crate::Result;
+
+// IRPrinter 的逆过程: 用 koopa::front::Driver 把文本形式的 Koopa IR 解析为 Program.
+// Driver 在解析出错时会自己把带位置的错误信息输出到 stderr, 返回的错误本身没有内容
+
+/// 解析一段 Koopa IR 文本
+pub fn parse_program(text: &str) -> Result<Program> {
+    Driver::from(text)
+        .generate_program()
+        .map_err(|_| anyhow!("Failed to parse Koopa IR"))
+}
+
+/// 解析 Koopa IR 文件, 解析器报告错误时会带上文件名
+pub fn parse_file(path: impl AsRef<Path>) -> Result<Program> {
+    let path = path.as_ref();
+    Driver::from_path(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?
+        .generate_program()
+        .map_err(|_| anyhow!("Failed to parse Koopa IR in {}", path.display()))
+}
@@ -0,0 +1,354 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::{anyhow, bail, ensure, Context};
+use koopa::ir::{BasicBlock, Function, FunctionData, Program, Value, ValueKind};
+
+use super::ir_print::bb_names;
+use super::parse::parse_program;
+use super::IRPrinter;
+use crate::Result;
+
+// 用 koopa::front::Driver 解析打印出的文本, 再与内存中的程序逐条比较,
+// 检查 IRPrinter 输出的文本与程序完全对应
+
+/// 打印 `program`, 解析回来并比较两者的结构
+pub fn check_round_trip(program: &Program) -> Result<()> {
+    let text = IRPrinter::new().print_program(program);
+    let parsed = parse_program(&text).context("Printed IR can not be parsed back")?;
+    RoundTrip::new(program, &parsed)
+        .compare()
+        .context("Printed IR does not match the program")
+}
+
+/// 原程序中的全局变量、函数、基本块和值到解析结果中对应项的映射
+struct RoundTrip<'a> {
+    expected: &'a Program,
+    parsed: &'a Program,
+    funcs: HashMap<Function, Function>,
+    values: HashMap<Value, Value>,
+    bbs: HashMap<BasicBlock, BasicBlock>,
+}
+
+impl<'a> RoundTrip<'a> {
+    fn new(expected: &'a Program, parsed: &'a Program) -> Self {
+        Self {
+            expected,
+            parsed,
+            funcs: HashMap::new(),
+            values: HashMap::new(),
+            bbs: HashMap::new(),
+        }
+    }
+
+    fn compare(&mut self) -> Result<()> {
+        let (expected, parsed) = (self.expected.inst_layout(), self.parsed.inst_layout());
+        ensure!(
+            expected.len() == parsed.len(),
+            "Expected {} global values, got {}",
+            expected.len(),
+            parsed.len()
+        );
+        for (&lhs, &rhs) in expected.iter().zip(parsed) {
+            let (lhs_data, rhs_data) = (
+                self.expected.borrow_value(lhs),
+                self.parsed.borrow_value(rhs),
+            );
+            let name = lhs_data.name().clone().unwrap_or_default();
+            ensure!(
+                same_name(lhs_data.name(), rhs_data.name()),
+                "Global {} is printed as {:?}",
+                name,
+                rhs_data.name()
+            );
+            let (ValueKind::GlobalAlloc(lhs_alloc), ValueKind::GlobalAlloc(rhs_alloc)) =
+                (lhs_data.kind(), rhs_data.kind())
+            else {
+                bail!("Global {} is not an alloc", name);
+            };
+            ensure!(
+                self.same_global_const(lhs_alloc.init(), rhs_alloc.init()),
+                "Initializer of global {} differs",
+                name
+            );
+            self.values.insert(lhs, rhs);
+        }
+
+        // 打印时 decl 在前, 函数定义在后
+        let (decls, defs): (Vec<Function>, Vec<Function>) = self
+            .expected
+            .func_layout()
+            .iter()
+            .partition(|&&func| self.expected.func(func).layout().entry_bb().is_none());
+        let expected: Vec<Function> = decls.into_iter().chain(defs).collect();
+        let parsed = self.parsed.func_layout();
+        ensure!(
+            expected.len() == parsed.len(),
+            "Expected {} functions, got {}",
+            expected.len(),
+            parsed.len()
+        );
+        for (&lhs, &rhs) in expected.iter().zip(parsed) {
+            self.funcs.insert(lhs, rhs);
+        }
+        for (&lhs, &rhs) in expected.iter().zip(parsed) {
+            let name = self.expected.func(lhs).name().to_string();
+            self.compare_function(self.expected.func(lhs), self.parsed.func(rhs))
+                .with_context(|| format!("In function {}", name))?;
+        }
+        Ok(())
+    }
+
+    fn compare_function(&mut self, lhs: &FunctionData, rhs: &FunctionData) -> Result<()> {
+        ensure!(
+            lhs.name() == rhs.name(),
+            "Function is printed as {}",
+            rhs.name()
+        );
+        ensure!(lhs.ty() == rhs.ty(), "Type is printed as {}", rhs.ty());
+        self.map_values(lhs, rhs, lhs.params(), rhs.params())?;
+
+        // koopa 的解析器会丢弃不可达的基本块并调整基本块的顺序,
+        // 所以只比较可达的基本块, 并按打印出的名字对应
+        let names = bb_names(lhs);
+        let rhs_by_name: HashMap<String, BasicBlock> = rhs
+            .layout()
+            .bbs()
+            .keys()
+            .map(|&bb| (rhs.dfg().bb(bb).name().clone().unwrap_or_default(), bb))
+            .collect();
+        let lhs_bbs = reachable_bbs(lhs);
+        ensure!(
+            lhs_bbs.len() == rhs_by_name.len(),
+            "Expected {} reachable basic blocks, got {}",
+            lhs_bbs.len(),
+            rhs_by_name.len()
+        );
+        let rhs_bbs = lhs_bbs
+            .iter()
+            .map(|bb| {
+                rhs_by_name
+                    .get(&names[bb])
+                    .copied()
+                    .ok_or_else(|| anyhow!("Basic block {} is missing after parsing", names[bb]))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        // 先建立基本块和指令的对应关系, 跳转和使用可能指向后面的块
+        for (&lhs_bb, &rhs_bb) in lhs_bbs.iter().zip(&rhs_bbs) {
+            let (lhs_data, rhs_data) = (lhs.dfg().bb(lhs_bb), rhs.dfg().bb(rhs_bb));
+            let name = lhs_data.name().clone().unwrap_or_default();
+            ensure!(
+                same_name(lhs_data.name(), rhs_data.name()),
+                "Basic block {} is printed as {:?}",
+                name,
+                rhs_data.name()
+            );
+            self.bbs.insert(lhs_bb, rhs_bb);
+            self.map_values(lhs, rhs, lhs_data.params(), rhs_data.params())
+                .with_context(|| format!("In basic block {}", name))?;
+            let lhs_insts = insts(lhs, lhs_bb);
+            let rhs_insts = insts(rhs, rhs_bb);
+            self.map_values(lhs, rhs, &lhs_insts, &rhs_insts)
+                .with_context(|| format!("In basic block {}", name))?;
+        }
+
+        for (&lhs_bb, &rhs_bb) in lhs_bbs.iter().zip(&rhs_bbs) {
+            for (lhs_inst, rhs_inst) in insts(lhs, lhs_bb).into_iter().zip(insts(rhs, rhs_bb)) {
+                ensure!(
+                    self.same_inst(lhs, rhs, lhs_inst, rhs_inst),
+                    "In basic block {}: {:?} is printed as {:?}",
+                    rhs.dfg().bb(rhs_bb).name().clone().unwrap_or_default(),
+                    lhs.dfg().value(lhs_inst).kind(),
+                    rhs.dfg().value(rhs_inst).kind()
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// 按位置对应两组值, 原来有名字的值必须保持名字和类型
+    fn map_values(
+        &mut self,
+        lhs_func: &FunctionData,
+        rhs_func: &FunctionData,
+        lhs: &[Value],
+        rhs: &[Value],
+    ) -> Result<()> {
+        ensure!(
+            lhs.len() == rhs.len(),
+            "Expected {} values, got {}",
+            lhs.len(),
+            rhs.len()
+        );
+        for (&lhs, &rhs) in lhs.iter().zip(rhs) {
+            let (lhs_data, rhs_data) = (lhs_func.dfg().value(lhs), rhs_func.dfg().value(rhs));
+            ensure!(
+                same_name(lhs_data.name(), rhs_data.name()),
+                "Value {:?} is printed as {:?}",
+                lhs_data.name(),
+                rhs_data.name()
+            );
+            ensure!(
+                lhs_data.ty() == rhs_data.ty(),
+                "Value {:?} has type {}, printed as {}",
+                lhs_data.name(),
+                lhs_data.ty(),
+                rhs_data.ty()
+            );
+            self.values.insert(lhs, rhs);
+        }
+        Ok(())
+    }
+
+    fn same_inst(
+        &self,
+        lhs_func: &FunctionData,
+        rhs_func: &FunctionData,
+        lhs: Value,
+        rhs: Value,
+    ) -> bool {
+        let same = |l: Value, r: Value| self.same_value(lhs_func, rhs_func, l, r);
+        let same_all = |l: &[Value], r: &[Value]| {
+            l.len() == r.len() && l.iter().zip(r).all(|(&l, &r)| same(l, r))
+        };
+        let same_bb = |l: BasicBlock, r: BasicBlock| self.bbs.get(&l) == Some(&r);
+        let (lhs_data, rhs_data) = (lhs_func.dfg().value(lhs), rhs_func.dfg().value(rhs));
+        if lhs_data.ty() != rhs_data.ty() {
+            return false;
+        }
+        match (lhs_data.kind(), rhs_data.kind()) {
+            (ValueKind::Alloc(_), ValueKind::Alloc(_)) => true,
+            (ValueKind::Load(l), ValueKind::Load(r)) => same(l.src(), r.src()),
+            (ValueKind::Store(l), ValueKind::Store(r)) => {
+                same(l.value(), r.value()) && same(l.dest(), r.dest())
+            }
+            (ValueKind::GetPtr(l), ValueKind::GetPtr(r)) => {
+                same(l.src(), r.src()) && same(l.index(), r.index())
+            }
+            (ValueKind::GetElemPtr(l), ValueKind::GetElemPtr(r)) => {
+                same(l.src(), r.src()) && same(l.index(), r.index())
+            }
+            (ValueKind::Binary(l), ValueKind::Binary(r)) => {
+                l.op() == r.op() && same(l.lhs(), r.lhs()) && same(l.rhs(), r.rhs())
+            }
+            (ValueKind::Branch(l), ValueKind::Branch(r)) => {
+                same(l.cond(), r.cond())
+                    && same_bb(l.true_bb(), r.true_bb())
+                    && same_bb(l.false_bb(), r.false_bb())
+                    && same_all(l.true_args(), r.true_args())
+                    && same_all(l.false_args(), r.false_args())
+            }
+            (ValueKind::Jump(l), ValueKind::Jump(r)) => {
+                same_bb(l.target(), r.target()) && same_all(l.args(), r.args())
+            }
+            (ValueKind::Call(l), ValueKind::Call(r)) => {
+                self.funcs.get(&l.callee()) == Some(&r.callee()) && same_all(l.args(), r.args())
+            }
+            (ValueKind::Return(l), ValueKind::Return(r)) => match (l.value(), r.value()) {
+                (Some(l), Some(r)) => same(l, r),
+                (None, None) => true,
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    /// 常量按内容比较, 其余的值按对应关系比较
+    fn same_value(
+        &self,
+        lhs_func: &FunctionData,
+        rhs_func: &FunctionData,
+        lhs: Value,
+        rhs: Value,
+    ) -> bool {
+        if lhs.is_global() || rhs.is_global() {
+            return self.values.get(&lhs) == Some(&rhs);
+        }
+        let (lhs_data, rhs_data) = (lhs_func.dfg().value(lhs), rhs_func.dfg().value(rhs));
+        if !lhs_data.kind().is_const() {
+            return self.values.get(&lhs) == Some(&rhs);
+        }
+        if lhs_data.ty() != rhs_data.ty() {
+            return false;
+        }
+        match (lhs_data.kind(), rhs_data.kind()) {
+            (ValueKind::Integer(l), ValueKind::Integer(r)) => l.value() == r.value(),
+            (ValueKind::ZeroInit(_), ValueKind::ZeroInit(_)) => true,
+            (ValueKind::Undef(_), ValueKind::Undef(_)) => true,
+            (ValueKind::Aggregate(l), ValueKind::Aggregate(r)) => {
+                l.elems().len() == r.elems().len()
+                    && l.elems()
+                        .iter()
+                        .zip(r.elems())
+                        .all(|(&l, &r)| self.same_value(lhs_func, rhs_func, l, r))
+            }
+            _ => false,
+        }
+    }
+
+    fn same_global_const(&self, lhs: Value, rhs: Value) -> bool {
+        let (lhs_data, rhs_data) = (
+            self.expected.borrow_value(lhs),
+            self.parsed.borrow_value(rhs),
+        );
+        if lhs_data.ty() != rhs_data.ty() {
+            return false;
+        }
+        match (lhs_data.kind(), rhs_data.kind()) {
+            (ValueKind::Integer(l), ValueKind::Integer(r)) => l.value() == r.value(),
+            (ValueKind::ZeroInit(_), ValueKind::ZeroInit(_)) => true,
+            (ValueKind::Undef(_), ValueKind::Undef(_)) => true,
+            (ValueKind::Aggregate(l), ValueKind::Aggregate(r)) => {
+                l.elems().len() == r.elems().len()
+                    && l.elems()
+                        .iter()
+                        .zip(r.elems())
+                        .all(|(&l, &r)| self.same_global_const(l, r))
+            }
+            _ => false,
+        }
+    }
+}
+
+/// 解析器把 `%0` 这样的数字名字当作临时值, 不保留名字
+fn same_name(expected: &Option<String>, parsed: &Option<String>) -> bool {
+    match expected {
+        Some(name) if !name[1..].chars().all(|c| c.is_ascii_digit()) => expected == parsed,
+        _ => true,
+    }
+}
+
+fn insts(func: &FunctionData, bb: BasicBlock) -> Vec<Value> {
+    func.layout()
+        .bbs()
+        .node(&bb)
+        .expect("Basic block not in layout")
+        .insts()
+        .keys()
+        .copied()
+        .collect()
+}
+
+/// 按布局顺序列出从入口可达的基本块
+fn reachable_bbs(func: &FunctionData) -> Vec<BasicBlock> {
+    let layout = func.layout();
+    let mut reachable = HashSet::new();
+    let mut stack: Vec<BasicBlock> = layout.entry_bb().into_iter().collect();
+    while let Some(bb) = stack.pop() {
+        if !reachable.insert(bb) {
+            continue;
+        }
+        if let Some(&last) = layout
+            .bbs()
+            .node(&bb)
+            .and_then(|node| node.insts().back_key())
+        {
+            stack.extend(func.dfg().value(last).kind().bb_uses());
+        }
+    }
+    layout
+        .bbs()
+        .keys()
+        .copied()
+        .filter(|bb| reachable.contains(bb))
+        .collect()
+}
@@ -0,0 +1,159 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::{bail, ensure, Context};
+use koopa::ir::{BasicBlock, Value, ValueKind};
+
+use super::FunctionVerifier;
+use crate::Result;
+
+// 每个被使用的值都必须定义在支配使用处的位置: 同一基本块中位于使用之前,
+// 或者所在的基本块支配使用所在的基本块. 不可达的基本块不做检查
+
+impl FunctionVerifier<'_> {
+    pub(super) fn verify_dominance(&self) -> Result<()> {
+        let dominators = self.dominators();
+        for (&bb, node) in self.func.layout().bbs() {
+            let Some(doms) = dominators.get(&bb) else {
+                continue;
+            };
+            node.insts()
+                .keys()
+                .enumerate()
+                .try_for_each(|(i, &inst)| self.verify_uses(bb, doms, i, inst))
+                .with_context(|| format!("In basic block {}", self.bb_name(bb)))?;
+        }
+        Ok(())
+    }
+
+    /// `inst` 是 `bb` 中的第 `i` 条指令, `doms` 是 `bb` 的支配者
+    fn verify_uses(
+        &self,
+        bb: BasicBlock,
+        doms: &HashSet<BasicBlock>,
+        i: usize,
+        inst: Value,
+    ) -> Result<()> {
+        for value in self.func.dfg().value(inst).kind().value_uses() {
+            if value.is_global() {
+                continue;
+            }
+            let data = self.func.dfg().value(value);
+            // 常量和函数参数不在布局中, 总是可用
+            if data.kind().is_const() || matches!(data.kind(), ValueKind::FuncArgRef(_)) {
+                continue;
+            }
+            // 基本块参数在块的开头定义
+            let dominated = if matches!(data.kind(), ValueKind::BlockArgRef(_)) {
+                let Some(&def_bb) = self.block_params.get(&value) else {
+                    bail!(
+                        "{} uses {}, which is not a parameter of any basic block",
+                        self.inst_name(inst),
+                        self.inst_name(value)
+                    );
+                };
+                def_bb == bb || doms.contains(&def_bb)
+            } else {
+                let Some(&(def_bb, def_index)) = self.positions.get(&value) else {
+                    bail!(
+                        "{} uses {}, which is not in the layout",
+                        self.inst_name(inst),
+                        self.inst_name(value)
+                    );
+                };
+                if def_bb == bb {
+                    def_index < i
+                } else {
+                    doms.contains(&def_bb)
+                }
+            };
+            ensure!(
+                dominated,
+                "{} uses {} before it is defined",
+                self.inst_name(inst),
+                self.inst_name(value)
+            );
+        }
+        Ok(())
+    }
+
+    /// 迭代求每个可达基本块的支配者集合, 函数只有几十个块, 不需要更快的算法
+    fn dominators(&self) -> HashMap<BasicBlock, HashSet<BasicBlock>> {
+        let layout = self.func.layout();
+        let entry = layout.entry_bb().expect("Function has no entry block");
+        let succs: HashMap<BasicBlock, Vec<BasicBlock>> = layout
+            .bbs()
+            .iter()
+            .map(|(&bb, node)| {
+                let targets = node
+                    .insts()
+                    .back_key()
+                    .map(|&inst| self.func.dfg().value(inst).kind().bb_uses().collect())
+                    .unwrap_or_default();
+                (bb, targets)
+            })
+            .collect();
+
+        // 从入口出发的逆后序
+        let mut order = Vec::new();
+        let mut visited = HashSet::new();
+        post_order(entry, &succs, &mut visited, &mut order);
+        order.reverse();
+
+        let mut preds: HashMap<BasicBlock, Vec<BasicBlock>> = HashMap::new();
+        for &bb in &order {
+            for &succ in &succs[&bb] {
+                preds.entry(succ).or_default().push(bb);
+            }
+        }
+
+        let all: HashSet<BasicBlock> = order.iter().copied().collect();
+        let mut doms: HashMap<BasicBlock, HashSet<BasicBlock>> = order
+            .iter()
+            .map(|&bb| {
+                (
+                    bb,
+                    if bb == entry {
+                        HashSet::from([bb])
+                    } else {
+                        all.clone()
+                    },
+                )
+            })
+            .collect();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for &bb in order.iter().filter(|&&bb| bb != entry) {
+                let mut new = preds[&bb]
+                    .iter()
+                    .map(|pred| doms[pred].clone())
+                    .reduce(|acc, set| acc.intersection(&set).copied().collect())
+                    .unwrap_or_default();
+                new.insert(bb);
+                if new != doms[&bb] {
+                    doms.insert(bb, new);
+                    changed = true;
+                }
+            }
+        }
+        doms
+    }
+}
+
+fn post_order(
+    bb: BasicBlock,
+    succs: &HashMap<BasicBlock, Vec<BasicBlock>>,
+    visited: &mut HashSet<BasicBlock>,
+    order: &mut Vec<BasicBlock>,
+) {
+    if !visited.insert(bb) {
+        return;
+    }
+    // 跳转目标不在函数中的错误已在前面报告
+    for &succ in succs.get(&bb).into_iter().flatten() {
+        if succs.contains_key(&succ) {
+            post_order(succ, succs, visited, order);
+        }
+    }
+    order.push(bb);
+}
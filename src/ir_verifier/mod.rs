@@ -0,0 +1,148 @@
+mod dominance;
+mod types;
+
+use std::collections::HashMap;
+
+use anyhow::{bail, ensure, Context};
+use koopa::ir::{BasicBlock, FunctionData, Program, Type, Value, ValueKind};
+
+use crate::ir_builder::check_unique_names;
+use crate::Result;
+
+// Koopa IR 校验: debug 构建中在生成 IR 之后, 以及每个优化 pass 之后运行,
+// 尽早发现 IRBuilder 的错误, 而不是等到评测工具拒绝我们的输出
+
+/// 校验整个程序, 出错时指明所在的函数和基本块
+pub fn verify_program(program: &Program) -> Result<()> {
+    check_unique_names(program)?;
+    for &func in program.func_layout() {
+        let data = program.func(func);
+        // 没有基本块的是 decl
+        if data.layout().bbs().is_empty() {
+            continue;
+        }
+        FunctionVerifier::new(program, data)
+            .verify()
+            .with_context(|| format!("Invalid IR in function {}", data.name()))?;
+    }
+    Ok(())
+}
+
+struct FunctionVerifier<'a> {
+    program: &'a Program,
+    func: &'a FunctionData,
+    // 每条指令所在的基本块和在块内的位置
+    positions: HashMap<Value, (BasicBlock, usize)>,
+    // 每个基本块参数所属的基本块
+    block_params: HashMap<Value, BasicBlock>,
+}
+
+impl<'a> FunctionVerifier<'a> {
+    fn new(program: &'a Program, func: &'a FunctionData) -> Self {
+        let mut positions = HashMap::new();
+        let mut block_params = HashMap::new();
+        for (&bb, node) in func.layout().bbs() {
+            for (i, &inst) in node.insts().keys().enumerate() {
+                positions.insert(inst, (bb, i));
+            }
+            for &param in func.dfg().bb(bb).params() {
+                block_params.insert(param, bb);
+            }
+        }
+        Self {
+            program,
+            func,
+            positions,
+            block_params,
+        }
+    }
+
+    fn verify(&self) -> Result<()> {
+        for (&bb, node) in self.func.layout().bbs() {
+            let insts: Vec<Value> = node.insts().keys().copied().collect();
+            self.verify_terminators(&insts)
+                .and_then(|_| insts.iter().try_for_each(|&inst| self.verify_inst(inst)))
+                .with_context(|| format!("In basic block {}", self.bb_name(bb)))?;
+        }
+        self.verify_dominance()
+    }
+
+    /// 基本块非空, 以且仅以最后一条指令作为终结指令
+    fn verify_terminators(&self, insts: &[Value]) -> Result<()> {
+        let Some((&last, rest)) = insts.split_last() else {
+            bail!("Basic block is empty");
+        };
+        ensure!(
+            self.is_terminator(last),
+            "Basic block does not end with a terminator"
+        );
+        if let Some(&inst) = rest.iter().find(|&&inst| self.is_terminator(inst)) {
+            bail!(
+                "Terminator {} is followed by other instructions",
+                self.inst_name(inst)
+            );
+        }
+        Ok(())
+    }
+
+    fn verify_inst(&self, inst: Value) -> Result<()> {
+        let kind = self.func.dfg().value(inst).kind();
+        for value in kind.value_uses() {
+            ensure!(
+                value.is_global() || self.func.dfg().values().contains_key(&value),
+                "{} uses a value that does not belong to this function",
+                self.inst_name(inst)
+            );
+        }
+        for target in kind.bb_uses() {
+            ensure!(
+                self.func.layout().bbs().contains_key(&target),
+                "{} jumps to a basic block that is not in this function",
+                self.inst_name(inst)
+            );
+        }
+        self.verify_types(inst)
+            .with_context(|| format!("In {}", self.inst_name(inst)))
+    }
+
+    fn is_terminator(&self, inst: Value) -> bool {
+        matches!(
+            self.func.dfg().value(inst).kind(),
+            ValueKind::Branch(_) | ValueKind::Jump(_) | ValueKind::Return(_)
+        )
+    }
+
+    fn value_type(&self, value: Value) -> Type {
+        if value.is_global() {
+            self.program.borrow_value(value).ty().clone()
+        } else {
+            self.func.dfg().value(value).ty().clone()
+        }
+    }
+
+    fn bb_name(&self, bb: BasicBlock) -> String {
+        self.func
+            .dfg()
+            .bb(bb)
+            .name()
+            .clone()
+            .unwrap_or_else(|| "(unnamed)".to_string())
+    }
+
+    /// 报错时用名字指明指令, 没有名字的指令 (store/br/ret 等) 用其种类
+    fn inst_name(&self, inst: Value) -> String {
+        let data = self.func.dfg().value(inst);
+        if let Some(name) = data.name() {
+            return name.clone();
+        }
+        let kind = match data.kind() {
+            ValueKind::Store(_) => "store",
+            ValueKind::Branch(_) => "br",
+            ValueKind::Jump(_) => "jump",
+            ValueKind::Call(_) => "call",
+            ValueKind::Return(_) => "ret",
+            _ => "instruction",
+        };
+        format!("unnamed {}", kind)
+    }
+}
@@ -0,0 +1,154 @@
+use anyhow::{bail, ensure};
+use koopa::ir::{BasicBlock, Type, TypeKind, Value, ValueKind};
+
+use super::FunctionVerifier;
+use crate::Result;
+
+// 各条指令的操作数类型检查, 与 koopa 文本格式的类型规则一致
+
+impl FunctionVerifier<'_> {
+    pub(super) fn verify_types(&self, inst: Value) -> Result<()> {
+        let data = self.func.dfg().value(inst);
+        let ty = data.ty();
+        match data.kind() {
+            ValueKind::Alloc(_) => ensure!(
+                matches!(ty.kind(), TypeKind::Pointer(_)),
+                "alloc must produce a pointer, got {}",
+                ty
+            ),
+            ValueKind::Load(load) => {
+                let base = self.pointee(load.src())?;
+                ensure!(
+                    *ty == base,
+                    "load from {} produces {}",
+                    self.value_type(load.src()),
+                    ty
+                );
+            }
+            ValueKind::Store(store) => {
+                let base = self.pointee(store.dest())?;
+                let value_ty = self.value_type(store.value());
+                ensure!(
+                    value_ty == base,
+                    "Cannot store {} into {}",
+                    value_ty,
+                    self.value_type(store.dest())
+                );
+            }
+            ValueKind::GetPtr(get_ptr) => {
+                self.pointee(get_ptr.src())?;
+                self.expect_i32(get_ptr.index(), "getptr index")?;
+                ensure!(
+                    *ty == self.value_type(get_ptr.src()),
+                    "getptr on {} produces {}",
+                    self.value_type(get_ptr.src()),
+                    ty
+                );
+            }
+            ValueKind::GetElemPtr(gep) => {
+                let TypeKind::Array(elem, _) = self.pointee(gep.src())?.kind().clone() else {
+                    bail!(
+                        "getelemptr needs a pointer to an array, got {}",
+                        self.value_type(gep.src())
+                    );
+                };
+                self.expect_i32(gep.index(), "getelemptr index")?;
+                ensure!(
+                    *ty == Type::get_pointer(elem.clone()),
+                    "getelemptr on {} produces {}",
+                    self.value_type(gep.src()),
+                    ty
+                );
+            }
+            ValueKind::Binary(binary) => {
+                self.expect_i32(binary.lhs(), "Left operand")?;
+                self.expect_i32(binary.rhs(), "Right operand")?;
+                ensure!(ty.is_i32(), "Binary operation produces {}", ty);
+            }
+            ValueKind::Branch(branch) => {
+                self.expect_i32(branch.cond(), "Branch condition")?;
+                self.verify_block_args(branch.true_bb(), branch.true_args())?;
+                self.verify_block_args(branch.false_bb(), branch.false_args())?;
+            }
+            ValueKind::Jump(jump) => self.verify_block_args(jump.target(), jump.args())?,
+            ValueKind::Call(call) => {
+                let callee = self.program.func(call.callee());
+                let TypeKind::Function(params, ret) = callee.ty().kind() else {
+                    bail!("Callee {} is not a function", callee.name());
+                };
+                self.verify_args(callee.name(), params, call.args())?;
+                ensure!(
+                    ty == ret,
+                    "call {} produces {}, expected {}",
+                    callee.name(),
+                    ty,
+                    ret
+                );
+            }
+            ValueKind::Return(ret) => {
+                let TypeKind::Function(_, expected) = self.func.ty().kind() else {
+                    unreachable!("Function without a function type");
+                };
+                let actual = ret
+                    .value()
+                    .map_or_else(Type::get_unit, |value| self.value_type(value));
+                ensure!(
+                    actual == *expected,
+                    "Return type mismatch: expected {}, got {}",
+                    expected,
+                    actual
+                );
+            }
+            _ => bail!("{} is not an instruction", self.inst_name(inst)),
+        }
+        Ok(())
+    }
+
+    fn pointee(&self, value: Value) -> Result<Type> {
+        let ty = self.value_type(value);
+        match ty.kind() {
+            TypeKind::Pointer(base) => Ok(base.clone()),
+            _ => bail!("Expected a pointer, got {}", ty),
+        }
+    }
+
+    fn expect_i32(&self, value: Value, what: &str) -> Result<()> {
+        let ty = self.value_type(value);
+        ensure!(ty.is_i32(), "{} must be i32, got {}", what, ty);
+        Ok(())
+    }
+
+    fn verify_block_args(&self, target: BasicBlock, args: &[Value]) -> Result<()> {
+        let params: Vec<Type> = self
+            .func
+            .dfg()
+            .bb(target)
+            .params()
+            .iter()
+            .map(|&param| self.value_type(param))
+            .collect();
+        self.verify_args(&self.bb_name(target), &params, args)
+    }
+
+    fn verify_args(&self, callee: &str, params: &[Type], args: &[Value]) -> Result<()> {
+        ensure!(
+            params.len() == args.len(),
+            "{} expects {} arguments, got {}",
+            callee,
+            params.len(),
+            args.len()
+        );
+        for (i, (param, &arg)) in params.iter().zip(args).enumerate() {
+            let arg_ty = self.value_type(arg);
+            ensure!(
+                *param == arg_ty,
+                "Argument {} of {}: expected {}, got {}",
+                i + 1,
+                callee,
+                param,
+                arg_ty
+            );
+        }
+        Ok(())
+    }
+}
@@ -1,7 +1,14 @@
+#![allow(non_snake_case)]
 pub mod asm_generator;
 pub mod ast;
+pub mod autotest;
+pub mod diagnostic;
+pub mod fuzz;
+pub mod interpreter;
 pub mod ir_builder;
 pub mod ir_printer;
+pub mod ir_verifier;
+pub mod riscv_sim;
 pub mod semantic;
 pub mod traits;
 pub use anyhow::Result;
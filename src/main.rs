@@ -1,40 +1,103 @@
-use anyhow::Context;
-use anyhow::Result;
+#![allow(non_snake_case)]
+use anyhow::{Context, Result};
+use koopa::ir::Program;
 use lalrpop_util::lalrpop_mod;
 use std::env::args;
 use std::fs::read_to_string;
 use std::fs::write;
+use std::io::{stdin, stdout, BufWriter};
+use std::path::Path;
 use sysY::asm_generator;
+use sysY::ast::CompUnit;
+use sysY::diagnostic::{Diagnostic, Severity};
+use sysY::ir_builder::IRBuilder;
+use sysY::semantic::{Analyzer, LintConfig};
+use sysY::traits::semantic::Analyze;
 use sysY::traits::ToIr;
-use sysY::{ir_builder, ir_printer};
+use sysY::{autotest, fuzz, interpreter, ir_printer, ir_verifier, riscv_sim};
 // 引用 lalrpop 生成的解析器
 // 因为我们刚刚创建了 sysy.lalrpop, 所以模块名是 sysy
 
-lalrpop_mod!(sysy);
+lalrpop_mod!(
+    #[allow(clippy::ptr_arg)]
+    sysy
+);
 fn main() -> Result<()> {
     // 解析命令行参数
-    let mut args = args();
-    args.next();
+    // `-W` 开头的参数是警告开关, `-from-koopa` 强制把输入当作 Koopa IR, 其余按位置解析
+    let mut lints = LintConfig::default();
+    let mut from_koopa = false;
+    let mut positional = Vec::new();
+    for arg in args().skip(1) {
+        if arg.starts_with("-W") {
+            lints.apply_flag(&arg)?;
+        } else if arg == "-from-koopa" {
+            from_koopa = true;
+        } else {
+            positional.push(arg);
+        }
+    }
+    let mut args = positional.into_iter();
     let mode = args.next().unwrap();
-    println!("{}", mode);
-    let input = args.next().unwrap();
+    // 解释执行时 stdout 只留给被执行的程序, autotest 和 fuzz 只输出结果
+    if !mode.starts_with("-run-") && mode != "-autotest" && mode != "-fuzz" {
+        println!("{}", mode);
+    }
+    let input_path = args.next().unwrap();
+    // `-autotest <目录> [-koopa|-riscv] [-max-steps=<n>]`, 不指定 target 时两种都测试
+    if mode == "-autotest" {
+        let mut targets = vec![autotest::Target::Koopa, autotest::Target::Riscv];
+        let mut step_limit = autotest::DEFAULT_STEP_LIMIT;
+        for arg in args {
+            match arg.as_str() {
+                "-koopa" => targets = vec![autotest::Target::Koopa],
+                "-riscv" => targets = vec![autotest::Target::Riscv],
+                _ => {
+                    let limit = arg
+                        .strip_prefix("-max-steps=")
+                        .ok_or_else(|| anyhow::anyhow!("Unknown autotest option: {}", arg))?;
+                    step_limit = limit
+                        .parse()
+                        .with_context(|| format!("Invalid step limit: {}", limit))?;
+                }
+            }
+        }
+        return run_autotest(&input_path, &targets, step_limit);
+    }
+    // `-fuzz <个数> [选项...]`, 选项见 FuzzConfig::apply_option
+    if mode == "-fuzz" {
+        let mut config = fuzz::FuzzConfig::default();
+        for option in args {
+            config.apply_option(&option)?;
+        }
+        let count = input_path
+            .parse()
+            .with_context(|| format!("Invalid program count: {}", input_path))?;
+        return run_fuzz(&config, count);
+    }
     args.next();
-    let output = args.next().unwrap();
+    // `-run-koopa` 和 `-run-riscv` 不需要输出文件
+    let output = args.next().unwrap_or_default();
 
-    // 读取输入文件
-    let input = read_to_string(input)?;
-    let static_input = Box::leak(input.into_boxed_str());
-    // 调用 lalrpop 生成的 parser 解析输入文件
-    let ast = sysy::CompUnitParser::new()
-        .parse(static_input)
-        .with_context(|| format!("Failed to parse input file"))?;
-    let mut builder = ir_builder::IRBuilder::new();
+    // 汇编文件直接交给模拟器执行
+    if mode == "-run-riscv" && (input_path.ends_with(".S") || input_path.ends_with(".s")) {
+        return run_riscv(&read_to_string(&input_path)?);
+    }
+    // `.koopa` 文件直接交给后端, 跳过前端和语义分析
+    if from_koopa || input_path.ends_with(".koopa") {
+        return compile_koopa(&mode, &input_path, &output);
+    }
 
-    ast.to_ir(&mut builder).context("Failed to build IR")?;
+    let Some((ast, builder)) = compile_sysy(&input_path, lints)? else {
+        std::process::exit(1);
+    };
     match mode.as_str() {
         "-koopa" => {
             let mut printer = ir_printer::IRPrinter::new();
-            let ir = builder.to_ir(&mut printer);
+            let ir = builder.to_ir(&mut printer)?;
+            // debug 构建中把打印出的文本解析回来, 检查与内存中的程序一致
+            #[cfg(debug_assertions)]
+            ir_printer::check_round_trip(builder.program())?;
             // println!("{:#?}", ir);
             write(&output, ir)?;
         }
@@ -44,6 +107,8 @@ fn main() -> Result<()> {
             let res = builder.to_asm(&mut code_generator);
             write(&output, res)?;
         }
+        "-run-koopa" => run_koopa(builder.program())?,
+        "-run-riscv" => run_riscv(&builder.to_asm(&mut asm_generator::AsmGenerator::new()))?,
         _ => {
             unreachable!("Invalid mode");
         }
@@ -53,3 +118,175 @@ fn main() -> Result<()> {
 
     Ok(())
 }
+
+/// SysY 前端: 语法分析、语义分析并生成 IR. 诊断信息已经输出到 stderr 时返回 None
+fn compile_sysy(input_path: &str, lints: LintConfig) -> Result<Option<(CompUnit, IRBuilder)>> {
+    // 读取输入文件
+    let input = read_to_string(input_path)?;
+    compile_source(input_path, input, lints)
+}
+
+/// 编译 `input`, 诊断信息中的文件名为 `input_path`
+fn compile_source(
+    input_path: &str,
+    input: String,
+    lints: LintConfig,
+) -> Result<Option<(CompUnit, IRBuilder)>> {
+    let static_input = Box::leak(input.into_boxed_str());
+    match parse_source(input_path, static_input) {
+        Some(ast) => lower_ast(input_path, static_input, ast, lints),
+        None => Ok(None),
+    }
+}
+
+/// 语法分析, 语法错误全部输出到 stderr 时返回 None
+fn parse_source(input_path: &str, input: &str) -> Option<CompUnit> {
+    // 调用 lalrpop 生成的 parser 解析输入文件
+    let mut errors = Vec::new();
+    let result = sysy::CompUnitParser::new().parse(&mut errors, input);
+    // 报告所有恢复过的语法错误, 以及最终导致解析失败的错误
+    let mut diags: Vec<Diagnostic> = errors
+        .iter()
+        .map(|recovery| Diagnostic::from_parse_error(&recovery.error, input))
+        .collect();
+    if let Err(err) = &result {
+        diags.push(Diagnostic::from_parse_error(err, input));
+    }
+    // 恢复的错误在规则归约时才记录, 按位置排序后输出
+    diags.sort_by_key(|diag| diag.span.start);
+    if !diags.is_empty() {
+        for diag in &diags {
+            eprint!("{}", diag.render(input_path, input));
+        }
+        eprintln!("{} error(s) found while parsing", diags.len());
+        return None;
+    }
+    result.ok()
+}
+
+/// 语义分析并生成 IR, 诊断信息已经输出到 stderr 时返回 None
+fn lower_ast(
+    input_path: &str,
+    static_input: &str,
+    mut ast: CompUnit,
+    lints: LintConfig,
+) -> Result<Option<(CompUnit, IRBuilder)>> {
+    // 语义分析: 名字解析、类型检查和常量折叠, 出错时不会生成任何 IR
+    let mut analyzer = Analyzer::with_lints(lints);
+    let result = ast.analyze(&mut analyzer);
+    let warnings = analyzer.take_warnings();
+    for warning in &warnings {
+        eprint!("{}", warning.render(input_path, static_input));
+    }
+    if let Err(err) = result {
+        // 带位置的错误按 `file:line:col` 加源码摘录输出
+        match err.chain().find_map(|e| e.downcast_ref::<Diagnostic>()) {
+            Some(diag) => {
+                eprint!("{}", diag.render(input_path, static_input));
+                return Ok(None);
+            }
+            None => return Err(err.context("Semantic analysis failed")),
+        }
+    }
+    // -Werror: 警告已按错误输出
+    if warnings.iter().any(|w| w.severity == Severity::Error) {
+        return Ok(None);
+    }
+
+    let mut builder = IRBuilder::new(analyzer.into_symbols());
+    ast.to_ir(&mut builder).context("Failed to build IR")?;
+    // debug 构建中校验生成的 IR; 以后加入优化 pass 时, 每个 pass 之后也要校验
+    #[cfg(debug_assertions)]
+    ir_verifier::verify_program(builder.program()).context("IR verification failed")?;
+    Ok(Some((ast, builder)))
+}
+
+/// 输入是文本形式的 Koopa IR: 解析、校验后按 `mode` 输出
+fn compile_koopa(mode: &str, input_path: &str, output: &str) -> Result<()> {
+    let program = ir_printer::parse_file(input_path)?;
+    // 外部输入的 IR 总是校验, 以免后端在不合法的 IR 上给出难以理解的结果
+    ir_verifier::verify_program(&program).context("IR verification failed")?;
+    if mode == "-run-koopa" {
+        return run_koopa(&program);
+    }
+    let res = match mode {
+        "-koopa" => ir_printer::IRPrinter::new().print_program(&program),
+        "-riscv" | "-run-riscv" => {
+            asm_generator::check_supported(&program)
+                .context("The RISC-V backend can not compile this program")?;
+            asm_generator::AsmGenerator::new().generate_program(&program)
+        }
+        _ => unreachable!("Invalid mode"),
+    };
+    if mode == "-run-riscv" {
+        return run_riscv(&res);
+    }
+    write(output, res)?;
+    Ok(())
+}
+
+/// 解释执行程序, 从 stdin 读入、向 stdout 输出, 以 main 的返回值作为退出码
+fn run_koopa(program: &Program) -> Result<()> {
+    let mut output = BufWriter::new(stdout().lock());
+    let code = interpreter::run_program(program, &mut stdin().lock(), &mut output, None)?;
+    drop(output);
+    std::process::exit(code);
+}
+
+/// 在模拟器中执行汇编, 执行的指令条数输出到 stderr
+fn run_riscv(asm: &str) -> Result<()> {
+    let mut output = BufWriter::new(stdout().lock());
+    let result = riscv_sim::run_asm(asm, &mut stdin().lock(), &mut output, None)?;
+    drop(output);
+    eprintln!("Executed {} instructions", result.instructions);
+    std::process::exit(result.exit_code);
+}
+
+/// 运行测试目录中的所有用例, 有失败时以 1 退出
+fn run_autotest(root: &str, targets: &[autotest::Target], step_limit: u64) -> Result<()> {
+    let compile = |path: &Path| -> Result<Program> {
+        let path = path.to_string_lossy();
+        match compile_sysy(&path, LintConfig::silenced())? {
+            Some((_, builder)) => Ok(builder.into_program()),
+            None => anyhow::bail!("Compilation failed"),
+        }
+    };
+    let report = autotest::run(Path::new(root), targets, step_limit, &compile)?;
+    print!("{}", report.render());
+    if !report.all_passed() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// 生成随机程序做差分测试, 不一致的程序保存为 `fuzz-<种子>.c`, 有不一致时以 1 退出
+fn run_fuzz(config: &fuzz::FuzzConfig, count: usize) -> Result<()> {
+    let compile = |source: &str| -> Result<fuzz::Compiled> {
+        let Some(ast) = parse_source("<fuzz>", source) else {
+            anyhow::bail!("Compilation failed");
+        };
+        // 参考求值器使用语义分析之前的 AST, 不受编译器常量折叠的影响
+        let reference = ast.clone();
+        match lower_ast("<fuzz>", source, ast, LintConfig::silenced())? {
+            Some((_, builder)) => Ok(fuzz::Compiled {
+                ast: reference,
+                program: builder.into_program(),
+            }),
+            None => anyhow::bail!("Compilation failed"),
+        }
+    };
+    let report = fuzz::run(config, count, &compile)?;
+    for mismatch in &report.mismatches {
+        let path = format!("fuzz-{}.c", mismatch.seed);
+        write(&path, &mismatch.source)?;
+        println!(
+            "MISMATCH seed {} ({}): {}",
+            mismatch.seed, path, mismatch.reason
+        );
+    }
+    print!("{}", report.render());
+    if !report.mismatches.is_empty() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
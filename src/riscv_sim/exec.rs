@@ -0,0 +1,177 @@
+use anyhow::{anyhow, ensure};
+
+use super::{
+    AluOp, Cond, Inst, Simulator, Width, DATA_BASE, EXIT_ADDR, STACK_SIZE, STACK_TOP, TEXT_BASE,
+};
+use crate::Result;
+
+// 指令的执行语义与 RV32IM 规范一致, 包括除零和溢出时的结果, 不会产生异常
+
+impl Simulator<'_> {
+    /// 执行一条指令, 程序结束时返回 true
+    pub(super) fn step(&mut self) -> Result<bool> {
+        let inst = *self
+            .program
+            .insts
+            .get(self.pc)
+            .ok_or_else(|| anyhow!("Execution ran past the end of .text"))?;
+        self.instructions += 1;
+        let mut next = self.pc + 1;
+        match inst {
+            Inst::Op { op, rd, rs1, rs2 } => {
+                let value = alu(op, self.reg(rs1), self.reg(rs2));
+                self.set_reg(rd, value);
+            }
+            Inst::OpImm { op, rd, rs1, imm } => {
+                let value = alu(op, self.reg(rs1), imm as u32);
+                self.set_reg(rd, value);
+            }
+            Inst::Li { rd, imm } => self.set_reg(rd, imm as u32),
+            Inst::Load {
+                width,
+                rd,
+                base,
+                offset,
+            } => {
+                let addr = self.reg(base).wrapping_add(offset as u32);
+                let value = self.load(addr, width)?;
+                self.set_reg(rd, value);
+            }
+            Inst::Store {
+                width,
+                rs,
+                base,
+                offset,
+            } => {
+                let addr = self.reg(base).wrapping_add(offset as u32);
+                self.store(addr, width, self.reg(rs))?;
+            }
+            Inst::Branch {
+                cond,
+                rs1,
+                rs2,
+                target,
+            } => {
+                if holds(cond, self.reg(rs1), self.reg(rs2)) {
+                    next = target;
+                }
+            }
+            Inst::Jal { rd, target } => {
+                self.set_reg(rd, text_addr(next));
+                next = target;
+            }
+            Inst::Jalr { rd, rs1, offset } => {
+                let addr = self.reg(rs1).wrapping_add(offset as u32) & !1;
+                self.set_reg(rd, text_addr(next));
+                if addr == EXIT_ADDR {
+                    return Ok(true);
+                }
+                next = text_index(addr)
+                    .filter(|&index| index < self.program.insts.len())
+                    .ok_or_else(|| anyhow!("Jump to invalid address {:#x}", addr))?;
+            }
+            Inst::Host(func) => self.call_host(func)?,
+        }
+        self.pc = next;
+        Ok(false)
+    }
+
+    pub(super) fn reg(&self, reg: u8) -> u32 {
+        self.regs[reg as usize]
+    }
+
+    pub(super) fn set_reg(&mut self, reg: u8, value: u32) {
+        if reg != 0 {
+            self.regs[reg as usize] = value;
+        }
+    }
+
+    /// 地址所在的内存区域 (数据段或栈) 中的 `len` 个字节
+    fn bytes(&mut self, addr: u32, len: u32) -> Result<&mut [u8]> {
+        ensure!(addr.is_multiple_of(len), "Misaligned access at {:#x}", addr);
+        let stack_base = STACK_TOP - STACK_SIZE;
+        let (region, offset) = if addr >= stack_base {
+            (&mut self.stack, addr - stack_base)
+        } else if addr >= DATA_BASE {
+            (&mut self.data, addr - DATA_BASE)
+        } else {
+            return Err(anyhow!("Invalid memory access at {:#x}", addr));
+        };
+        let (start, end) = (offset as usize, (offset + len) as usize);
+        region
+            .get_mut(start..end)
+            .ok_or_else(|| anyhow!("Invalid memory access at {:#x}", addr))
+    }
+
+    pub(super) fn load(&mut self, addr: u32, width: Width) -> Result<u32> {
+        Ok(match width {
+            Width::Word => u32::from_le_bytes(self.bytes(addr, 4)?.try_into().unwrap()),
+            Width::Half => i16::from_le_bytes(self.bytes(addr, 2)?.try_into().unwrap()) as u32,
+            Width::HalfUnsigned => {
+                u16::from_le_bytes(self.bytes(addr, 2)?.try_into().unwrap()) as u32
+            }
+            Width::Byte => self.bytes(addr, 1)?[0] as i8 as u32,
+            Width::ByteUnsigned => self.bytes(addr, 1)?[0] as u32,
+        })
+    }
+
+    pub(super) fn store(&mut self, addr: u32, width: Width, value: u32) -> Result<()> {
+        let bytes = value.to_le_bytes();
+        let len = match width {
+            Width::Word => 4,
+            Width::Half | Width::HalfUnsigned => 2,
+            Width::Byte | Width::ByteUnsigned => 1,
+        };
+        self.bytes(addr, len as u32)?.copy_from_slice(&bytes[..len]);
+        Ok(())
+    }
+}
+
+fn text_addr(index: usize) -> u32 {
+    TEXT_BASE + 4 * index as u32
+}
+
+fn text_index(addr: u32) -> Option<usize> {
+    let offset = addr.checked_sub(TEXT_BASE)?;
+    offset.is_multiple_of(4).then_some((offset / 4) as usize)
+}
+
+fn holds(cond: Cond, lhs: u32, rhs: u32) -> bool {
+    match cond {
+        Cond::Eq => lhs == rhs,
+        Cond::Ne => lhs != rhs,
+        Cond::Lt => (lhs as i32) < (rhs as i32),
+        Cond::Ge => (lhs as i32) >= (rhs as i32),
+        Cond::Ltu => lhs < rhs,
+        Cond::Geu => lhs >= rhs,
+    }
+}
+
+fn alu(op: AluOp, lhs: u32, rhs: u32) -> u32 {
+    let (slhs, srhs) = (lhs as i32, rhs as i32);
+    match op {
+        AluOp::Add => lhs.wrapping_add(rhs),
+        AluOp::Sub => lhs.wrapping_sub(rhs),
+        AluOp::And => lhs & rhs,
+        AluOp::Or => lhs | rhs,
+        AluOp::Xor => lhs ^ rhs,
+        AluOp::Sll => lhs << (rhs & 31),
+        AluOp::Srl => lhs >> (rhs & 31),
+        AluOp::Sra => (slhs >> (rhs & 31)) as u32,
+        AluOp::Slt => (slhs < srhs) as u32,
+        AluOp::Sltu => (lhs < rhs) as u32,
+        AluOp::Mul => lhs.wrapping_mul(rhs),
+        AluOp::Mulh => ((slhs as i64 * srhs as i64) >> 32) as u32,
+        AluOp::Mulhu => ((lhs as u64 * rhs as u64) >> 32) as u32,
+        AluOp::Mulhsu => ((slhs as i64 * rhs as i64) >> 32) as u32,
+        // 除零时商为全 1, 余数为被除数; 有符号溢出时商为被除数, 余数为 0
+        AluOp::Div if rhs == 0 => u32::MAX,
+        AluOp::Div => slhs.wrapping_div(srhs) as u32,
+        AluOp::Divu if rhs == 0 => u32::MAX,
+        AluOp::Divu => lhs / rhs,
+        AluOp::Rem if rhs == 0 => lhs,
+        AluOp::Rem => slhs.wrapping_rem(srhs) as u32,
+        AluOp::Remu if rhs == 0 => lhs,
+        AluOp::Remu => lhs % rhs,
+    }
+}
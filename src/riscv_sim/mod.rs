@@ -0,0 +1,220 @@
+mod exec;
+mod parse;
+mod runtime_lib;
+
+use std::io::{BufRead, Write};
+
+use anyhow::{anyhow, Context};
+
+use crate::interpreter::TimeLimitExceeded;
+use crate::Result;
+pub use parse::parse_asm;
+use parse::AsmProgram;
+
+// RV32IM 指令级模拟器, 执行 AsmGenerator 输出的汇编文本.
+// 调用没有在代码段中定义的运行时库函数时, 直接在宿主上完成输入输出
+
+/// 代码段起始地址, 返回地址等指向代码的值都按 `TEXT_BASE + 4 * 下标` 计算
+const TEXT_BASE: u32 = 0x0001_0000;
+const DATA_BASE: u32 = 0x1000_0000;
+const STACK_TOP: u32 = 0x8000_0000;
+const STACK_SIZE: u32 = 64 << 20;
+/// main 的返回地址, 跳转到这里表示程序结束
+const EXIT_ADDR: u32 = 0;
+
+/// 一次运行的结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Execution {
+    /// main 返回时 a0 的值, 进程退出码是它的低 8 位
+    pub exit_code: i32,
+    /// 执行的指令条数, 伪指令和运行时库调用都算一条
+    pub instructions: u64,
+}
+
+/// 解析并执行汇编文本, 程序的输出写到 `output`.
+/// `step_limit` 限制执行的指令条数, 超过时返回 [`TimeLimitExceeded`]
+pub fn run_asm(
+    text: &str,
+    input: &mut dyn BufRead,
+    output: &mut dyn Write,
+    step_limit: Option<u64>,
+) -> Result<Execution> {
+    let program = parse_asm(text).context("Failed to parse the assembly")?;
+    let mut simulator = Simulator::new(&program, input, output);
+    simulator.step_limit = step_limit;
+    simulator.run()
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Inst {
+    Op {
+        op: AluOp,
+        rd: u8,
+        rs1: u8,
+        rs2: u8,
+    },
+    OpImm {
+        op: AluOp,
+        rd: u8,
+        rs1: u8,
+        imm: i32,
+    },
+    Li {
+        rd: u8,
+        imm: i32,
+    },
+    Load {
+        width: Width,
+        rd: u8,
+        base: u8,
+        offset: i32,
+    },
+    Store {
+        width: Width,
+        rs: u8,
+        base: u8,
+        offset: i32,
+    },
+    Branch {
+        cond: Cond,
+        rs1: u8,
+        rs2: u8,
+        target: usize,
+    },
+    Jal {
+        rd: u8,
+        target: usize,
+    },
+    Jalr {
+        rd: u8,
+        rs1: u8,
+        offset: i32,
+    },
+    Host(HostFn),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum AluOp {
+    Add,
+    Sub,
+    And,
+    Or,
+    Xor,
+    Sll,
+    Srl,
+    Sra,
+    Slt,
+    Sltu,
+    Mul,
+    Mulh,
+    Mulhu,
+    Mulhsu,
+    Div,
+    Divu,
+    Rem,
+    Remu,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Cond {
+    Eq,
+    Ne,
+    Lt,
+    Ge,
+    Ltu,
+    Geu,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Width {
+    Word,
+    Half,
+    HalfUnsigned,
+    Byte,
+    ByteUnsigned,
+}
+
+/// SysY 运行时库中的函数
+#[derive(Debug, Clone, Copy)]
+pub enum HostFn {
+    GetInt,
+    GetCh,
+    GetArray,
+    PutInt,
+    PutCh,
+    PutArray,
+    StartTime,
+    StopTime,
+}
+
+pub struct Simulator<'a> {
+    program: &'a AsmProgram,
+    regs: [u32; 32],
+    // 下一条指令的下标
+    pc: usize,
+    data: Vec<u8>,
+    stack: Vec<u8>,
+    instructions: u64,
+    step_limit: Option<u64>,
+    input: &'a mut dyn BufRead,
+    output: &'a mut dyn Write,
+}
+
+impl<'a> Simulator<'a> {
+    pub fn new(
+        program: &'a AsmProgram,
+        input: &'a mut dyn BufRead,
+        output: &'a mut dyn Write,
+    ) -> Self {
+        let mut regs = [0; 32];
+        regs[1] = EXIT_ADDR;
+        regs[2] = STACK_TOP;
+        Self {
+            program,
+            regs,
+            pc: 0,
+            data: program.data.clone(),
+            stack: vec![0; STACK_SIZE as usize],
+            instructions: 0,
+            step_limit: None,
+            input,
+            output,
+        }
+    }
+
+    /// 最多执行 `limit` 条指令
+    pub fn with_step_limit(mut self, limit: u64) -> Self {
+        self.step_limit = Some(limit);
+        self
+    }
+
+    pub fn run(&mut self) -> Result<Execution> {
+        self.pc = *self
+            .program
+            .text_labels
+            .get("main")
+            .ok_or_else(|| anyhow!("Program has no main function"))?;
+        loop {
+            if self
+                .step_limit
+                .is_some_and(|limit| self.instructions >= limit)
+            {
+                return Err(TimeLimitExceeded.into());
+            }
+            let exited = self
+                .step()
+                .with_context(|| match self.program.lines.get(self.pc) {
+                    Some(line) => format!("Runtime error at line {} of the assembly", line),
+                    None => "Runtime error".to_string(),
+                })?;
+            if exited {
+                break;
+            }
+        }
+        self.output.flush()?;
+        Ok(Execution {
+            exit_code: self.regs[10] as i32,
+            instructions: self.instructions,
+        })
+    }
+}
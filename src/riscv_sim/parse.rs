@@ -0,0 +1,557 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, bail, ensure, Context};
+
+use super::{AluOp, Cond, HostFn, Inst, Width, DATA_BASE, TEXT_BASE};
+use crate::Result;
+
+// 汇编文本的解析. 第一遍收集各段中的标号, 第二遍把指令和伪指令展开为 Inst,
+// 标号在第二遍中解析为指令下标或数据地址
+
+/// 解析后的程序: 代码段按指令下标寻址, 数据段 (包括 .bss) 从 DATA_BASE 开始
+pub struct AsmProgram {
+    pub insts: Vec<Inst>,
+    // 每条指令所在的源码行, 报错时使用
+    pub lines: Vec<usize>,
+    pub data: Vec<u8>,
+    pub text_labels: HashMap<String, usize>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Section {
+    Text,
+    Data,
+}
+
+/// 一行去掉注释和标号后的内容
+struct Line<'a> {
+    number: usize,
+    op: &'a str,
+    operands: Vec<&'a str>,
+}
+
+pub fn parse_asm(text: &str) -> Result<AsmProgram> {
+    // 第一遍: 标号和数据段的内容
+    let mut lines = Vec::new();
+    let mut section = Section::Text;
+    let mut text_labels = HashMap::new();
+    let mut data_labels = HashMap::new();
+    let mut data = Vec::new();
+    let mut inst_count = 0;
+    for (i, raw) in text.lines().enumerate() {
+        let number = i + 1;
+        let mut rest = raw.split('#').next().unwrap_or_default().trim();
+        while let Some((label, after)) = split_label(rest) {
+            let redefined = match section {
+                Section::Text => text_labels.insert(label.to_string(), inst_count).is_some(),
+                Section::Data => data_labels
+                    .insert(label.to_string(), DATA_BASE + data.len() as u32)
+                    .is_some(),
+            };
+            ensure!(
+                !redefined,
+                "Line {}: label {} is defined more than once",
+                number,
+                label
+            );
+            rest = after.trim();
+        }
+        if rest.is_empty() {
+            continue;
+        }
+        let (op, operands) = match rest.split_once(char::is_whitespace) {
+            Some((op, operands)) => (op, operands.split(',').map(str::trim).collect()),
+            None => (rest, Vec::new()),
+        };
+        match op {
+            ".text" => section = Section::Text,
+            ".data" | ".bss" | ".rodata" => section = Section::Data,
+            ".section" => {
+                section = match operands.first().copied() {
+                    Some(".text") => Section::Text,
+                    _ => Section::Data,
+                }
+            }
+            ".global" | ".globl" | ".extern" | ".type" | ".size" | ".file" => {}
+            ".word" | ".half" | ".byte" | ".zero" | ".space" | ".align" | ".p2align" => {
+                ensure!(
+                    section == Section::Data,
+                    "Line {}: {} outside of a data section",
+                    number,
+                    op
+                );
+                emit_data(op, &operands, &mut data)
+                    .with_context(|| format!("Line {}: {}", number, rest))?;
+            }
+            _ if op.starts_with('.') => bail!("Line {}: unknown directive {}", number, op),
+            _ => {
+                ensure!(
+                    section == Section::Text,
+                    "Line {}: instruction outside of .text",
+                    number
+                );
+                inst_count += 1;
+                lines.push(Line {
+                    number,
+                    op,
+                    operands,
+                });
+            }
+        }
+    }
+
+    // 第二遍: 指令
+    let resolver = Resolver {
+        text_labels: &text_labels,
+        data_labels: &data_labels,
+    };
+    let mut insts = Vec::with_capacity(lines.len());
+    for line in &lines {
+        let inst = resolver
+            .parse_inst(line.op, &line.operands)
+            .with_context(|| {
+                format!(
+                    "Line {}: {} {}",
+                    line.number,
+                    line.op,
+                    line.operands.join(", ")
+                )
+            })?;
+        insts.push(inst);
+    }
+    Ok(AsmProgram {
+        insts,
+        lines: lines.iter().map(|line| line.number).collect(),
+        data,
+        text_labels,
+    })
+}
+
+/// `label: rest` 拆成标号和剩余部分
+fn split_label(line: &str) -> Option<(&str, &str)> {
+    let (label, rest) = line.split_once(':')?;
+    let is_label = !label.is_empty()
+        && label
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '$'));
+    is_label.then_some((label, rest))
+}
+
+fn emit_data(op: &str, operands: &[&str], data: &mut Vec<u8>) -> Result<()> {
+    for operand in operands {
+        let value = parse_imm(operand)?;
+        match op {
+            ".word" => data.extend_from_slice(&value.to_le_bytes()),
+            ".half" => data.extend_from_slice(&(value as i16).to_le_bytes()),
+            ".byte" => data.push(value as u8),
+            ".zero" | ".space" => data.resize(data.len() + value as usize, 0),
+            // .align 和 .p2align 的参数都是 2 的幂次
+            _ => {
+                let align = 1usize << value;
+                data.resize(data.len().next_multiple_of(align), 0);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn parse_imm(text: &str) -> Result<i32> {
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text),
+    };
+    let value = match digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+    {
+        Some(hex) => i64::from_str_radix(hex, 16),
+        None => digits.parse::<i64>(),
+    }
+    .map_err(|_| anyhow!("Invalid immediate {}", text))?;
+    let value = if negative { -value } else { value };
+    ensure!(
+        (i32::MIN as i64..=u32::MAX as i64).contains(&value),
+        "Immediate {} does not fit in 32 bits",
+        text
+    );
+    Ok(value as i32)
+}
+
+/// I 型和 S 型指令的立即数只有 12 位, 汇编器会拒绝超出范围的值
+fn parse_imm12(text: &str) -> Result<i32> {
+    let value = parse_imm(text)?;
+    ensure!(
+        (-2048..=2047).contains(&value),
+        "Immediate {} does not fit in 12 bits",
+        text
+    );
+    Ok(value)
+}
+
+/// 移位量只有 5 位
+fn parse_shamt(text: &str) -> Result<i32> {
+    let value = parse_imm(text)?;
+    ensure!(
+        (0..32).contains(&value),
+        "Shift amount {} is out of range",
+        text
+    );
+    Ok(value)
+}
+
+fn parse_reg(text: &str) -> Result<u8> {
+    const ABI_NAMES: [&str; 32] = [
+        "zero", "ra", "sp", "gp", "tp", "t0", "t1", "t2", "s0", "s1", "a0", "a1", "a2", "a3", "a4",
+        "a5", "a6", "a7", "s2", "s3", "s4", "s5", "s6", "s7", "s8", "s9", "s10", "s11", "t3", "t4",
+        "t5", "t6",
+    ];
+    if let Some(index) = ABI_NAMES.iter().position(|&name| name == text) {
+        return Ok(index as u8);
+    }
+    if text == "fp" {
+        return Ok(8);
+    }
+    text.strip_prefix('x')
+        .and_then(|n| n.parse::<u8>().ok())
+        .filter(|&n| n < 32)
+        .ok_or_else(|| anyhow!("Unknown register {}", text))
+}
+
+/// `offset(base)` 形式的内存操作数
+fn parse_mem(text: &str) -> Result<(i32, u8)> {
+    let (offset, base) = text
+        .strip_suffix(')')
+        .and_then(|text| text.split_once('('))
+        .ok_or_else(|| anyhow!("Expected offset(base), got {}", text))?;
+    let offset = if offset.is_empty() {
+        0
+    } else {
+        parse_imm12(offset)?
+    };
+    Ok((offset, parse_reg(base.trim())?))
+}
+
+struct Resolver<'a> {
+    text_labels: &'a HashMap<String, usize>,
+    data_labels: &'a HashMap<String, u32>,
+}
+
+impl Resolver<'_> {
+    fn target(&self, label: &str) -> Result<usize> {
+        self.text_labels
+            .get(label)
+            .copied()
+            .ok_or_else(|| anyhow!("Unknown label {}", label))
+    }
+
+    /// `la` 的地址: 数据标号或者代码标号
+    fn address(&self, label: &str) -> Result<u32> {
+        if let Some(&addr) = self.data_labels.get(label) {
+            return Ok(addr);
+        }
+        Ok(TEXT_BASE + 4 * self.target(label)? as u32)
+    }
+
+    fn parse_inst(&self, op: &str, operands: &[&str]) -> Result<Inst> {
+        let reg = |i: usize| -> Result<u8> { parse_reg(operand(operands, i)?) };
+        let imm = |i: usize| -> Result<i32> { parse_imm(operand(operands, i)?) };
+        let label = |i: usize| -> Result<usize> { self.target(operand(operands, i)?) };
+        let (min, max) = operand_count(op).ok_or_else(|| anyhow!("Unknown instruction {}", op))?;
+        ensure!(
+            (min..=max).contains(&operands.len()),
+            "{} expects {} operands, got {}",
+            op,
+            max,
+            operands.len()
+        );
+
+        let inst = if let Some(alu) = reg_op(op) {
+            Inst::Op {
+                op: alu,
+                rd: reg(0)?,
+                rs1: reg(1)?,
+                rs2: reg(2)?,
+            }
+        } else if let Some(alu) = imm_op(op) {
+            let imm = operand(operands, 2)?;
+            Inst::OpImm {
+                op: alu,
+                rd: reg(0)?,
+                rs1: reg(1)?,
+                imm: match alu {
+                    AluOp::Sll | AluOp::Srl | AluOp::Sra => parse_shamt(imm)?,
+                    _ => parse_imm12(imm)?,
+                },
+            }
+        } else if let Some((cond, swap)) = branch_cond(op) {
+            let (rs1, rs2) = (reg(0)?, reg(1)?);
+            let (rs1, rs2) = if swap { (rs2, rs1) } else { (rs1, rs2) };
+            Inst::Branch {
+                cond,
+                rs1,
+                rs2,
+                target: label(2)?,
+            }
+        } else if let Some(cond) = branch_zero_cond(op) {
+            // bgtz/blez 与 0 比较时交换操作数
+            let (rs1, rs2) = match op {
+                "bgtz" | "blez" => (0, reg(0)?),
+                _ => (reg(0)?, 0),
+            };
+            Inst::Branch {
+                cond,
+                rs1,
+                rs2,
+                target: label(1)?,
+            }
+        } else if let Some(width) = load_width(op) {
+            let (offset, base) = parse_mem(operand(operands, 1)?)?;
+            Inst::Load {
+                width,
+                rd: reg(0)?,
+                base,
+                offset,
+            }
+        } else if let Some(width) = store_width(op) {
+            let (offset, base) = parse_mem(operand(operands, 1)?)?;
+            Inst::Store {
+                width,
+                rs: reg(0)?,
+                base,
+                offset,
+            }
+        } else {
+            match op {
+                "li" => Inst::Li {
+                    rd: reg(0)?,
+                    imm: imm(1)?,
+                },
+                "lui" => {
+                    let imm = imm(1)?;
+                    ensure!(
+                        (0..1 << 20).contains(&imm),
+                        "Immediate {} does not fit in 20 bits",
+                        imm
+                    );
+                    Inst::Li {
+                        rd: reg(0)?,
+                        imm: imm << 12,
+                    }
+                }
+                "la" => Inst::Li {
+                    rd: reg(0)?,
+                    imm: self.address(operand(operands, 1)?)? as i32,
+                },
+                "mv" => Inst::OpImm {
+                    op: AluOp::Add,
+                    rd: reg(0)?,
+                    rs1: reg(1)?,
+                    imm: 0,
+                },
+                "not" => Inst::OpImm {
+                    op: AluOp::Xor,
+                    rd: reg(0)?,
+                    rs1: reg(1)?,
+                    imm: -1,
+                },
+                "neg" => Inst::Op {
+                    op: AluOp::Sub,
+                    rd: reg(0)?,
+                    rs1: 0,
+                    rs2: reg(1)?,
+                },
+                "seqz" => Inst::OpImm {
+                    op: AluOp::Sltu,
+                    rd: reg(0)?,
+                    rs1: reg(1)?,
+                    imm: 1,
+                },
+                "snez" => Inst::Op {
+                    op: AluOp::Sltu,
+                    rd: reg(0)?,
+                    rs1: 0,
+                    rs2: reg(1)?,
+                },
+                "sltz" => Inst::Op {
+                    op: AluOp::Slt,
+                    rd: reg(0)?,
+                    rs1: reg(1)?,
+                    rs2: 0,
+                },
+                "sgtz" => Inst::Op {
+                    op: AluOp::Slt,
+                    rd: reg(0)?,
+                    rs1: 0,
+                    rs2: reg(1)?,
+                },
+                "nop" => Inst::OpImm {
+                    op: AluOp::Add,
+                    rd: 0,
+                    rs1: 0,
+                    imm: 0,
+                },
+                "j" => Inst::Jal {
+                    rd: 0,
+                    target: label(0)?,
+                },
+                "jal" if operands.len() == 1 => Inst::Jal {
+                    rd: 1,
+                    target: label(0)?,
+                },
+                "jal" => Inst::Jal {
+                    rd: reg(0)?,
+                    target: label(1)?,
+                },
+                "jr" => Inst::Jalr {
+                    rd: 0,
+                    rs1: reg(0)?,
+                    offset: 0,
+                },
+                "jalr" => {
+                    let (offset, rs1) = parse_mem(operand(operands, 1)?)?;
+                    Inst::Jalr {
+                        rd: reg(0)?,
+                        rs1,
+                        offset,
+                    }
+                }
+                "ret" => Inst::Jalr {
+                    rd: 0,
+                    rs1: 1,
+                    offset: 0,
+                },
+                // 没有定义在代码段中的函数由运行时库提供
+                "call" | "tail" => {
+                    let callee = operand(operands, 0)?;
+                    match self.text_labels.get(callee) {
+                        Some(&target) => Inst::Jal {
+                            rd: if op == "call" { 1 } else { 0 },
+                            target,
+                        },
+                        None => Inst::Host(
+                            HostFn::from_name(callee)
+                                .ok_or_else(|| anyhow!("Unknown function {}", callee))?,
+                        ),
+                    }
+                }
+                _ => bail!("Unknown instruction {}", op),
+            }
+        };
+        Ok(inst)
+    }
+}
+
+fn operand<'a>(operands: &[&'a str], i: usize) -> Result<&'a str> {
+    operands
+        .get(i)
+        .copied()
+        .ok_or_else(|| anyhow!("Missing operand {}", i + 1))
+}
+
+/// 操作数个数的范围, `jal` 可以省略目的寄存器
+fn operand_count(op: &str) -> Option<(usize, usize)> {
+    let count = if reg_op(op).is_some() || imm_op(op).is_some() || branch_cond(op).is_some() {
+        3
+    } else if branch_zero_cond(op).is_some()
+        || load_width(op).is_some()
+        || store_width(op).is_some()
+    {
+        2
+    } else {
+        match op {
+            "jal" => return Some((1, 2)),
+            "li" | "lui" | "la" | "mv" | "not" | "neg" | "seqz" | "snez" | "sltz" | "sgtz"
+            | "jalr" => 2,
+            "j" | "jr" | "call" | "tail" => 1,
+            "nop" | "ret" => 0,
+            _ => return None,
+        }
+    };
+    Some((count, count))
+}
+
+fn reg_op(op: &str) -> Option<AluOp> {
+    Some(match op {
+        "add" => AluOp::Add,
+        "sub" => AluOp::Sub,
+        "and" => AluOp::And,
+        "or" => AluOp::Or,
+        "xor" => AluOp::Xor,
+        "sll" => AluOp::Sll,
+        "srl" => AluOp::Srl,
+        "sra" => AluOp::Sra,
+        "slt" => AluOp::Slt,
+        "sltu" => AluOp::Sltu,
+        "mul" => AluOp::Mul,
+        "mulh" => AluOp::Mulh,
+        "mulhu" => AluOp::Mulhu,
+        "mulhsu" => AluOp::Mulhsu,
+        "div" => AluOp::Div,
+        "divu" => AluOp::Divu,
+        "rem" => AluOp::Rem,
+        "remu" => AluOp::Remu,
+        _ => return None,
+    })
+}
+
+fn imm_op(op: &str) -> Option<AluOp> {
+    Some(match op {
+        "addi" => AluOp::Add,
+        "andi" => AluOp::And,
+        "ori" => AluOp::Or,
+        "xori" => AluOp::Xor,
+        "slli" => AluOp::Sll,
+        "srli" => AluOp::Srl,
+        "srai" => AluOp::Sra,
+        "slti" => AluOp::Slt,
+        "sltiu" => AluOp::Sltu,
+        _ => return None,
+    })
+}
+
+/// 条件分支, 第二项表示是否需要交换操作数 (bgt/ble 等伪指令)
+fn branch_cond(op: &str) -> Option<(Cond, bool)> {
+    Some(match op {
+        "beq" => (Cond::Eq, false),
+        "bne" => (Cond::Ne, false),
+        "blt" => (Cond::Lt, false),
+        "bge" => (Cond::Ge, false),
+        "bltu" => (Cond::Ltu, false),
+        "bgeu" => (Cond::Geu, false),
+        "bgt" => (Cond::Lt, true),
+        "ble" => (Cond::Ge, true),
+        "bgtu" => (Cond::Ltu, true),
+        "bleu" => (Cond::Geu, true),
+        _ => return None,
+    })
+}
+
+fn branch_zero_cond(op: &str) -> Option<Cond> {
+    Some(match op {
+        "beqz" => Cond::Eq,
+        "bnez" => Cond::Ne,
+        "bltz" | "bgtz" => Cond::Lt,
+        "bgez" | "blez" => Cond::Ge,
+        _ => return None,
+    })
+}
+
+fn load_width(op: &str) -> Option<Width> {
+    Some(match op {
+        "lw" => Width::Word,
+        "lh" => Width::Half,
+        "lhu" => Width::HalfUnsigned,
+        "lb" => Width::Byte,
+        "lbu" => Width::ByteUnsigned,
+        _ => return None,
+    })
+}
+
+fn store_width(op: &str) -> Option<Width> {
+    Some(match op {
+        "sw" => Width::Word,
+        "sh" => Width::Half,
+        "sb" => Width::Byte,
+        _ => return None,
+    })
+}
@@ -0,0 +1,75 @@
+use super::{HostFn, Simulator, Width};
+use crate::interpreter::{read_byte, read_int};
+use crate::Result;
+
+// 运行时库函数在宿主上执行, 参数在 a0, a1 中, 返回值写回 a0.
+// 输入输出的格式与 Koopa 解释器一致. 真实的库函数会使用调用者保存的寄存器,
+// 所以返回后把它们都改成无意义的值, 依赖这些寄存器跨调用保持不变的代码会出错
+
+impl HostFn {
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "getint" => HostFn::GetInt,
+            "getch" => HostFn::GetCh,
+            "getarray" => HostFn::GetArray,
+            "putint" => HostFn::PutInt,
+            "putch" => HostFn::PutCh,
+            "putarray" => HostFn::PutArray,
+            "starttime" | "_sysy_starttime" => HostFn::StartTime,
+            "stoptime" | "_sysy_stoptime" => HostFn::StopTime,
+            _ => return None,
+        })
+    }
+}
+
+const A0: u8 = 10;
+const A1: u8 = 11;
+/// 调用者保存的寄存器 t0-t6 和 a1-a7, a0 只在没有返回值时被改写
+const CALLER_SAVED: [u8; 14] = [5, 6, 7, 28, 29, 30, 31, 11, 12, 13, 14, 15, 16, 17];
+
+impl Simulator<'_> {
+    pub(super) fn call_host(&mut self, func: HostFn) -> Result<()> {
+        let (a0, a1) = (self.reg(A0), self.reg(A1));
+        self.clobber_caller_saved(func);
+        match func {
+            HostFn::GetInt => {
+                let value = read_int(self.input)?;
+                self.set_reg(A0, value as u32);
+            }
+            HostFn::GetCh => {
+                let ch = read_byte(self.input)?.map_or(-1, i32::from);
+                self.set_reg(A0, ch as u32);
+            }
+            HostFn::GetArray => {
+                let len = read_int(self.input)?;
+                for i in 0..len {
+                    let value = read_int(self.input)?;
+                    self.store(a0.wrapping_add(4 * i as u32), Width::Word, value as u32)?;
+                }
+                self.set_reg(A0, len as u32);
+            }
+            HostFn::PutInt => write!(self.output, "{}", a0 as i32)?,
+            HostFn::PutCh => self.output.write_all(&[a0 as u8])?,
+            HostFn::PutArray => {
+                write!(self.output, "{}:", a0 as i32)?;
+                for i in 0..a0 as i32 {
+                    let value = self.load(a1.wrapping_add(4 * i as u32), Width::Word)?;
+                    write!(self.output, " {}", value as i32)?;
+                }
+                writeln!(self.output)?;
+            }
+            HostFn::StartTime | HostFn::StopTime => {}
+        }
+        Ok(())
+    }
+
+    fn clobber_caller_saved(&mut self, func: HostFn) {
+        let returns_value = matches!(func, HostFn::GetInt | HostFn::GetCh | HostFn::GetArray);
+        let regs = CALLER_SAVED
+            .into_iter()
+            .chain((!returns_value).then_some(A0));
+        for reg in regs {
+            self.set_reg(reg, 0xdead_0000 | reg as u32);
+        }
+    }
+}
@@ -0,0 +1,259 @@
+use std::collections::{HashMap, HashSet};
+
+use super::lint::{Lint, LintConfig};
+use super::{Symbol, SymbolId, SymbolKind, Type};
+use crate::ast::Exp;
+use crate::diagnostic::{Diagnostic, Severity, Span};
+use crate::traits::semantic::{ExpAnalyze, SymbolTable};
+use crate::Result;
+use crate::{bail_at, ensure_at};
+
+/// 语义分析的状态: 符号表、当前函数和循环, 以及收集到的警告
+pub struct Analyzer {
+    symbols: Vec<Symbol>,
+    scopes: Vec<HashMap<String, SymbolId>>,
+    ret_type: Option<Type>,
+    loop_depth: usize,
+    // 常量表达式中不允许出现变量和函数调用, 除零是错误
+    const_context: bool,
+    // `0 && e` 和 `1 || e` 中的 `e` 不会被求值, 其中的溢出不报告
+    evaluated: bool,
+    // 在表达式中被读取过的符号, 用于未使用检查
+    used: HashSet<SymbolId>,
+    lints: LintConfig,
+    warnings: Vec<Diagnostic>,
+}
+
+impl Default for Analyzer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Analyzer {
+    pub fn new() -> Self {
+        Self::with_lints(LintConfig::default())
+    }
+
+    pub fn with_lints(lints: LintConfig) -> Self {
+        let mut analyzer = Self {
+            symbols: Vec::new(),
+            scopes: vec![HashMap::new()],
+            ret_type: None,
+            loop_depth: 0,
+            const_context: false,
+            evaluated: true,
+            used: HashSet::new(),
+            lints,
+            warnings: Vec::new(),
+        };
+        analyzer.declare_runtime_lib();
+        analyzer
+    }
+
+    pub fn symbol(&self, id: SymbolId) -> &Symbol {
+        &self.symbols[id.0]
+    }
+
+    /// 分析结束后把符号表交给 IRBuilder
+    pub fn into_symbols(self) -> Vec<Symbol> {
+        self.symbols
+    }
+
+    pub fn lint_enabled(&self, lint: Lint) -> bool {
+        self.lints.is_enabled(lint)
+    }
+
+    /// 报告一条警告, 消息末尾带上对应的开关, 例如 `[-Wshadow]`
+    pub fn warn(&mut self, lint: Lint, span: Span, message: impl Into<String>) {
+        if self.lint_enabled(lint) {
+            let message = format!("{} [-W{}]", message.into(), lint.name());
+            self.warnings.push(Diagnostic::warning(span, message));
+        }
+    }
+
+    /// 按源码顺序取出目前为止产生的所有警告, `-Werror` 时它们都是错误
+    pub fn take_warnings(&mut self) -> Vec<Diagnostic> {
+        let mut warnings = std::mem::take(&mut self.warnings);
+        warnings.sort_by_key(|warning| warning.span.start);
+        if self.lints.werror {
+            for warning in &mut warnings {
+                warning.severity = Severity::Error;
+            }
+        }
+        warnings
+    }
+
+    pub fn mark_used(&mut self, id: SymbolId) {
+        self.used.insert(id);
+    }
+
+    pub fn is_used(&self, id: SymbolId) -> bool {
+        self.used.contains(&id)
+    }
+
+    /// 在当前作用域定义符号, `what` 用于重复定义时的报错
+    pub fn define(&mut self, symbol: Symbol, what: &str) -> Result<SymbolId> {
+        let scope = self.scopes.last().expect("No active scope");
+        if let Some(&id) = scope.get(&symbol.name) {
+            ensure_at!(
+                !matches!(self.symbol(id).kind, SymbolKind::RuntimeFunction),
+                symbol.span,
+                "{} is a SysY runtime library function and cannot be redefined",
+                symbol.name
+            );
+            bail_at!(symbol.span, "Duplicate {}: {}", what, symbol.name);
+        }
+        if self.lookup(&symbol.name).is_ok() {
+            self.warn(
+                Lint::Shadow,
+                symbol.span,
+                format!(
+                    "Declaration of {} shadows an outer declaration",
+                    symbol.name
+                ),
+            );
+        }
+        self.add_symbol(symbol)
+    }
+
+    pub fn is_const_context(&self) -> bool {
+        self.const_context
+    }
+
+    pub fn is_evaluated(&self) -> bool {
+        self.evaluated
+    }
+
+    /// 分析不会被求值的表达式 (`0 && e` 和 `1 || e` 中的 `e`): 仍然解析名字和检查类型,
+    /// 但不按常量表达式报告除零、溢出等问题
+    pub(super) fn analyze_unevaluated(&mut self, exp: &mut Exp) -> Result<Type> {
+        let outer = std::mem::replace(&mut self.const_context, false);
+        let outer_evaluated = std::mem::replace(&mut self.evaluated, false);
+        let ty = exp.analyze(self);
+        self.const_context = outer;
+        self.evaluated = outer_evaluated;
+        ty
+    }
+
+    /// 按常量表达式分析 `exp`, 成功时它已被折叠成整数
+    pub fn eval_const(&mut self, exp: &mut Exp) -> Result<i32> {
+        let outer = std::mem::replace(&mut self.const_context, true);
+        let ty = exp.analyze(self);
+        self.const_context = outer;
+        let ty = ty?;
+        ensure_at!(
+            ty == Type::Int,
+            exp.span(),
+            "Type mismatch: expected i32, got {}",
+            ty
+        );
+        match exp.as_number() {
+            Some(value) => Ok(value),
+            None => bail_at!(exp.span(), "Expression is not a constant"),
+        }
+    }
+
+    /// 数组每一维的长度必须是正的常量表达式
+    pub fn eval_dims(&mut self, dims: &mut [Exp]) -> Result<Vec<usize>> {
+        dims.iter_mut()
+            .map(|dim| {
+                let len = self.eval_const(dim)?;
+                ensure_at!(
+                    len > 0,
+                    dim.span(),
+                    "Array dimension must be positive, got {}",
+                    len
+                );
+                Ok(len as usize)
+            })
+            .collect()
+    }
+
+    pub fn enter_function(&mut self, ret_type: Type) {
+        self.ret_type = Some(ret_type);
+    }
+
+    pub fn exit_function(&mut self) {
+        self.ret_type = None;
+    }
+
+    pub fn current_ret_type(&self) -> &Type {
+        self.ret_type.as_ref().expect("No active function")
+    }
+
+    pub fn enter_loop(&mut self) {
+        self.loop_depth += 1;
+    }
+
+    pub fn exit_loop(&mut self) {
+        self.loop_depth -= 1;
+    }
+
+    pub fn in_loop(&self) -> bool {
+        self.loop_depth > 0
+    }
+}
+
+impl SymbolTable for Analyzer {
+    fn enter_scope(&mut self) -> Result<()> {
+        self.scopes.push(HashMap::new());
+        Ok(())
+    }
+
+    /// 离开作用域时检查其中从未被读取的变量和常量
+    fn exit_scope(&mut self) -> Result<()> {
+        let scope = self
+            .scopes
+            .pop()
+            .ok_or_else(|| anyhow::anyhow!("No scope to exit"))?;
+        let mut ids: Vec<_> = scope
+            .into_values()
+            .filter(|id| !self.is_used(*id))
+            .collect();
+        ids.sort_by_key(|id| id.0);
+        for id in ids {
+            let symbol = self.symbol(id);
+            let (lint, message) = match symbol.kind {
+                SymbolKind::Variable => (
+                    Lint::UnusedVariable,
+                    format!("Variable {} is never read", symbol.name),
+                ),
+                SymbolKind::Const(_) | SymbolKind::ConstArray(_) => (
+                    Lint::UnusedConst,
+                    format!("Unused constant {}", symbol.name),
+                ),
+                _ => continue,
+            };
+            let span = symbol.span;
+            self.warn(lint, span, message);
+        }
+        Ok(())
+    }
+
+    fn lookup(&self, name: &str) -> Result<SymbolId> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name).copied())
+            .ok_or_else(|| anyhow::anyhow!("Symbol {} not found", name))
+    }
+
+    fn add_symbol(&mut self, symbol: Symbol) -> Result<SymbolId> {
+        let id = SymbolId(self.symbols.len());
+        let scope = self
+            .scopes
+            .last_mut()
+            .ok_or_else(|| anyhow::anyhow!("No active scope"))?;
+        if scope.contains_key(&symbol.name) {
+            return Err(anyhow::anyhow!("Symbol {} already exists", symbol.name));
+        }
+        scope.insert(symbol.name.clone(), id);
+        self.symbols.push(symbol);
+        Ok(id)
+    }
+
+    fn current_scope_level(&self) -> usize {
+        self.scopes.len() - 1
+    }
+}
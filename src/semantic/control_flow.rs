@@ -0,0 +1,83 @@
+use super::lint::Lint;
+use super::{Analyzer, Type};
+use crate::ast::{Block, BlockItem, FuncDef, Stmt};
+use crate::diagnostic::Span;
+
+// 检查语句能否正常执行到结尾: return/break/continue 之后的代码不可达,
+// 非 void 函数的函数体能执行到结尾时缺少返回值 (IRBuilder 会补上 `ret 0`)
+
+impl Analyzer {
+    pub(crate) fn check_control_flow(&mut self, func_def: &FuncDef, ret: &Type) {
+        let falls_through = self.block_falls_through(&func_def.block);
+        if falls_through && *ret != Type::Void {
+            // 指向函数体的右花括号
+            let end = func_def.block.span.end;
+            self.warn(
+                Lint::MissingReturn,
+                Span::new(end - 1, end),
+                format!("Function {} may end without returning a value", func_def.id),
+            );
+        }
+    }
+
+    /// 每个块中只报告第一条不可达的语句, 其余的语句随之不再检查
+    fn block_falls_through(&mut self, block: &Block) -> bool {
+        for (i, item) in block.items.iter().enumerate() {
+            if !self.item_falls_through(item) {
+                if let Some(next) = block.items.get(i + 1) {
+                    self.warn(Lint::Unreachable, next.span(), "Unreachable code");
+                }
+                return false;
+            }
+        }
+        true
+    }
+
+    fn item_falls_through(&mut self, item: &BlockItem) -> bool {
+        match item {
+            BlockItem::Decl(_) => true,
+            BlockItem::Stmt(stmt) => self.stmt_falls_through(stmt),
+        }
+    }
+
+    fn stmt_falls_through(&mut self, stmt: &Stmt) -> bool {
+        match stmt {
+            Stmt::Return(..) | Stmt::Break(_) | Stmt::Continue(_) => false,
+            Stmt::Exp(..) | Stmt::Assign(..) => true,
+            Stmt::Block(block) => self.block_falls_through(block),
+            Stmt::If(cond, then, els, _) => {
+                let then_falls = self.stmt_falls_through(then);
+                let else_falls = match els {
+                    Some(els) => self.stmt_falls_through(els),
+                    None => true,
+                };
+                // 条件已折叠为常量时只有一个分支可达
+                match cond.as_number() {
+                    Some(0) => else_falls,
+                    Some(_) => then_falls,
+                    None => then_falls || else_falls,
+                }
+            }
+            Stmt::While(cond, body, _) => {
+                self.stmt_falls_through(body);
+                // `while (1)` 只能经由 break 结束
+                !matches!(cond.as_number(), Some(value) if value != 0) || has_break(body)
+            }
+        }
+    }
+}
+
+/// 语句中是否有跳出当前这层循环的 break
+fn has_break(stmt: &Stmt) -> bool {
+    match stmt {
+        Stmt::Break(_) => true,
+        Stmt::Block(block) => block.items.iter().any(|item| match item {
+            BlockItem::Stmt(stmt) => has_break(stmt),
+            BlockItem::Decl(_) => false,
+        }),
+        Stmt::If(_, then, els, _) => has_break(then) || els.as_deref().is_some_and(has_break),
+        // 内层循环的 break 只跳出内层
+        Stmt::While(..) => false,
+        Stmt::Return(..) | Stmt::Exp(..) | Stmt::Assign(..) | Stmt::Continue(_) => false,
+    }
+}
@@ -0,0 +1,209 @@
+use super::{Analyzer, Symbol, SymbolKind, Type};
+use crate::ast::{ConstDef, ConstInitVal, Decl, Exp, InitVal, VarDef};
+use crate::diagnostic::Span;
+use crate::traits::semantic::{Analyze, ExpAnalyze, SymbolTable};
+use crate::Result;
+use crate::{bail_at, ensure_at};
+
+impl Analyze for Decl {
+    fn analyze(&mut self, analyzer: &mut Analyzer) -> Result<()> {
+        match self {
+            Decl::ConstDecl(_, defs, _) => {
+                defs.iter_mut().try_for_each(|def| def.analyze(analyzer))
+            }
+            Decl::VarDecl(_, defs, _) => defs.iter_mut().try_for_each(|def| def.analyze(analyzer)),
+        }
+    }
+}
+
+impl Analyze for ConstDef {
+    fn analyze(&mut self, analyzer: &mut Analyzer) -> Result<()> {
+        let dims = analyzer.eval_dims(&mut self.dims)?;
+        let (kind, ty) = if dims.is_empty() {
+            let value = match &mut self.value {
+                ConstInitVal::Exp(exp) => analyzer.eval_const(exp)?,
+                ConstInitVal::List(_, span) => {
+                    bail_at!(*span, "Initializer list can not be used as a scalar value")
+                }
+            };
+            (SymbolKind::Const(value), Type::Int)
+        } else {
+            // 常量数组: 编译期求出全部元素
+            let mut exps = Vec::new();
+            self.value.exps_mut(&mut exps);
+            for exp in exps {
+                analyzer.eval_const(exp)?;
+            }
+            let values = flatten_init(&self.value, &dims)?
+                .into_iter()
+                .map(|exp| exp.and_then(Exp::as_number).unwrap_or(0))
+                .collect();
+            (
+                SymbolKind::ConstArray(values),
+                Type::array(Type::Int, &dims),
+            )
+        };
+        // 常量在自己的初始值求出之后才可见
+        let symbol = Symbol {
+            name: self.id.clone(),
+            kind,
+            ty,
+            scope_level: analyzer.current_scope_level(),
+            span: self.span,
+        };
+        self.symbol = Some(analyzer.define(symbol, "const definition")?);
+        Ok(())
+    }
+}
+
+impl Analyze for VarDef {
+    fn analyze(&mut self, analyzer: &mut Analyzer) -> Result<()> {
+        let dims = analyzer.eval_dims(&mut self.dims)?;
+        let is_global = analyzer.current_scope_level() == 0;
+        let symbol = Symbol {
+            name: self.id.clone(),
+            kind: SymbolKind::Variable,
+            ty: Type::array(Type::Int, &dims),
+            scope_level: analyzer.current_scope_level(),
+            span: self.span,
+        };
+        self.symbol = Some(analyzer.define(symbol, "variable definition")?);
+
+        let Some(init) = &mut self.init_val else {
+            return Ok(());
+        };
+        let mut exps = Vec::new();
+        init.exps_mut(&mut exps);
+        for exp in exps {
+            // 全局变量的初始值必须是常量表达式
+            if is_global {
+                analyzer.eval_const(exp)?;
+                continue;
+            }
+            let ty = exp.analyze(analyzer)?;
+            ensure_at!(
+                ty == Type::Int,
+                exp.span(),
+                "Type mismatch: expected i32, got {}",
+                ty
+            );
+        }
+        flatten_init(init, &dims)?;
+        Ok(())
+    }
+}
+
+/// InitVal 与 ConstInitVal 共用的初始化列表展开逻辑
+pub(crate) trait InitList: Sized {
+    fn as_exp(&self) -> Option<&Exp>;
+    fn as_list(&self) -> Option<&[Self]>;
+    fn span(&self) -> Span;
+    /// 按出现顺序收集所有表达式, 供语义分析逐个检查和折叠
+    fn exps_mut<'a>(&'a mut self, exps: &mut Vec<&'a mut Exp>);
+}
+
+impl InitList for InitVal {
+    fn as_exp(&self) -> Option<&Exp> {
+        match self {
+            InitVal::Exp(exp) => Some(exp),
+            InitVal::List(..) => None,
+        }
+    }
+    fn as_list(&self) -> Option<&[Self]> {
+        match self {
+            InitVal::Exp(_) => None,
+            InitVal::List(list, _) => Some(list),
+        }
+    }
+    fn span(&self) -> Span {
+        InitVal::span(self)
+    }
+    fn exps_mut<'a>(&'a mut self, exps: &mut Vec<&'a mut Exp>) {
+        match self {
+            InitVal::Exp(exp) => exps.push(exp),
+            InitVal::List(list, _) => list.iter_mut().for_each(|item| item.exps_mut(exps)),
+        }
+    }
+}
+
+impl InitList for ConstInitVal {
+    fn as_exp(&self) -> Option<&Exp> {
+        match self {
+            ConstInitVal::Exp(exp) => Some(exp),
+            ConstInitVal::List(..) => None,
+        }
+    }
+    fn as_list(&self) -> Option<&[Self]> {
+        match self {
+            ConstInitVal::Exp(_) => None,
+            ConstInitVal::List(list, _) => Some(list),
+        }
+    }
+    fn span(&self) -> Span {
+        ConstInitVal::span(self)
+    }
+    fn exps_mut<'a>(&'a mut self, exps: &mut Vec<&'a mut Exp>) {
+        match self {
+            ConstInitVal::Exp(exp) => exps.push(exp),
+            ConstInitVal::List(list, _) => list.iter_mut().for_each(|item| item.exps_mut(exps)),
+        }
+    }
+}
+
+/// 按行优先顺序展开初始化列表, 未显式给出的元素为 None (即 0)
+pub(crate) fn flatten_init<'a, T: InitList>(
+    init: &'a T,
+    dims: &[usize],
+) -> Result<Vec<Option<&'a Exp>>> {
+    if dims.is_empty() {
+        return match init.as_exp() {
+            Some(exp) => Ok(vec![Some(exp)]),
+            None => bail_at!(init.span(), "Scalar can not be initialized with a list"),
+        };
+    }
+    let Some(items) = init.as_list() else {
+        bail_at!(init.span(), "Array must be initialized with a list");
+    };
+    let mut elems = Vec::with_capacity(dims.iter().product());
+    flatten_list(items, dims, &mut elems)?;
+    Ok(elems)
+}
+
+fn flatten_list<'a, T: InitList>(
+    items: &'a [T],
+    dims: &[usize],
+    elems: &mut Vec<Option<&'a Exp>>,
+) -> Result<()> {
+    let size: usize = dims.iter().product();
+    let start = elems.len();
+    for item in items {
+        let pos = elems.len() - start;
+        ensure_at!(
+            pos < size,
+            item.span(),
+            "Too many initializers for array of size {}",
+            size
+        );
+        match (item.as_exp(), item.as_list()) {
+            (Some(exp), _) => elems.push(Some(exp)),
+            (_, Some(list)) => {
+                // 嵌套的花括号对应当前位置能对齐的最大子数组
+                let sub_dims = (1..dims.len())
+                    .map(|k| &dims[k..])
+                    .find(|sub| pos.is_multiple_of(sub.iter().product::<usize>()));
+                match sub_dims {
+                    Some(sub_dims) => flatten_list(list, sub_dims, elems)?,
+                    // 一维数组里的 `{x}` 视为带花括号的标量
+                    None if dims.len() == 1 => flatten_list(list, &[1], elems)?,
+                    None => bail_at!(
+                        item.span(),
+                        "Initializer list is not aligned to a sub-array boundary"
+                    ),
+                }
+            }
+            (None, None) => unreachable!(),
+        }
+    }
+    elems.resize(start + size, None);
+    Ok(())
+}
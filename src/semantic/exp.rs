@@ -0,0 +1,293 @@
+use koopa::ir::BinaryOp;
+
+use super::lint::Lint;
+use super::{Analyzer, SymbolKind, Type};
+use crate::ast::{Exp, FuncCall, LVal, PrimaryExp, UnaryExp, UnaryOp};
+use crate::diagnostic::Span;
+use crate::traits::semantic::{ExpAnalyze, SymbolTable};
+use crate::Result;
+use crate::{bail_at, ensure_at};
+
+// 各个表达式在分析子表达式之后, 如果子表达式都已折叠成整数, 就把自己也替换成整数
+
+impl ExpAnalyze for Exp {
+    fn analyze(&mut self, analyzer: &mut Analyzer) -> Result<Type> {
+        let span = self.span();
+        let (lhs, op, rhs) = match self {
+            Exp::Primary(primary_exp) => return primary_exp.analyze(analyzer),
+            Exp::UnaryExp(unary_exp) => return unary_exp.analyze(analyzer),
+            Exp::Binary(lhs, op, rhs) => (lhs, *op, rhs),
+        };
+        let lhs_ty = lhs.analyze(analyzer)?;
+        // 短路: 左侧已经决定结果时, 右侧不会被求值
+        let decided = match (op, lhs.as_number()) {
+            (BinaryOp::And, Some(0)) => Some(0),
+            (BinaryOp::Or, Some(value)) if value != 0 => Some(1),
+            _ => None,
+        };
+        let rhs_ty = match decided {
+            Some(_) => analyzer.analyze_unevaluated(rhs)?,
+            None => rhs.analyze(analyzer)?,
+        };
+        ensure_at!(
+            lhs_ty == Type::Int && rhs_ty == Type::Int,
+            span,
+            "Type mismatch in binary op: {} vs {}",
+            lhs_ty,
+            rhs_ty
+        );
+        if let Some(value) = decided {
+            *self = Exp::Primary(PrimaryExp::Number(value, span));
+        } else if let (Some(lhs_val), Some(rhs_val)) = (lhs.as_number(), rhs.as_number()) {
+            if let Some(value) = analyzer.fold_binary(op, lhs_val, rhs_val, rhs.span(), span)? {
+                *self = Exp::Primary(PrimaryExp::Number(value, span));
+            }
+        }
+        Ok(Type::Int)
+    }
+}
+
+impl ExpAnalyze for UnaryExp {
+    fn analyze(&mut self, analyzer: &mut Analyzer) -> Result<Type> {
+        let (op, operand, span) = match self {
+            UnaryExp::PrimaryExp(primary_exp) => return primary_exp.analyze(analyzer),
+            UnaryExp::Call(call) => return call.analyze(analyzer),
+            UnaryExp::UnaryOp(op, operand, span) => (op.clone(), operand, *span),
+        };
+        // -2147483648 是对 2147483648 (回绕为 i32::MIN) 取负, 字面量和取负都不算溢出
+        let is_int_min = matches!(op, UnaryOp::Minus)
+            && matches!(
+                **operand,
+                UnaryExp::PrimaryExp(PrimaryExp::Number(i32::MIN, _))
+            );
+        let ty = if is_int_min {
+            Type::Int
+        } else {
+            operand.analyze(analyzer)?
+        };
+        ensure_at!(
+            ty == Type::Int,
+            span,
+            "Type mismatch in unary op: expected i32, got {}",
+            ty
+        );
+        let Some(val) = operand.as_number() else {
+            return Ok(Type::Int);
+        };
+        let value = match op {
+            UnaryOp::Plus => val,
+            UnaryOp::Minus => {
+                let (result, overflow) = val.overflowing_neg();
+                if overflow && !is_int_min && analyzer.is_evaluated() {
+                    analyzer.warn(Lint::Overflow, span, overflow_message(result));
+                }
+                result
+            }
+            UnaryOp::Not => (val == 0) as i32,
+        };
+        *self = UnaryExp::PrimaryExp(PrimaryExp::Number(value, span));
+        Ok(Type::Int)
+    }
+}
+
+impl ExpAnalyze for PrimaryExp {
+    fn analyze(&mut self, analyzer: &mut Analyzer) -> Result<Type> {
+        let (ty, value) = match self {
+            // 字面量按 u32 解析后回绕, 负值说明它超出了 int 的范围
+            PrimaryExp::Number(value, span) => {
+                if *value < 0 {
+                    let message = format!(
+                        "Integer literal {} does not fit in int, wraps to {}",
+                        *value as u32, value
+                    );
+                    analyzer.warn(Lint::Overflow, *span, message);
+                }
+                return Ok(Type::Int);
+            }
+            PrimaryExp::Exp(exp, _) => (exp.analyze(analyzer)?, exp.as_number()),
+            PrimaryExp::LVal(lval) => {
+                let ty = lval.analyze(analyzer)?;
+                analyzer.mark_used(lval.symbol.expect("LVal is resolved above"));
+                (ty, lval.const_value(analyzer)?)
+            }
+        };
+        if let Some(value) = value {
+            *self = PrimaryExp::Number(value, self.span());
+        }
+        Ok(ty)
+    }
+}
+
+impl ExpAnalyze for LVal {
+    /// 数组按下标取到的子数组作为值使用时退化为指向首元素的指针
+    fn analyze(&mut self, analyzer: &mut Analyzer) -> Result<Type> {
+        let Ok(id) = analyzer.lookup(&self.id) else {
+            bail_at!(self.span, "Undefined identifier: {}", self.id);
+        };
+        self.symbol = Some(id);
+        for index in &mut self.indices {
+            let ty = index.analyze(analyzer)?;
+            ensure_at!(
+                ty == Type::Int,
+                index.span(),
+                "Array index must be an int, got {}",
+                ty
+            );
+        }
+        let symbol = analyzer.symbol(id);
+        ensure_at!(
+            !matches!(
+                symbol.kind,
+                SymbolKind::Function | SymbolKind::RuntimeFunction
+            ),
+            self.span,
+            "{} is not a variable",
+            self.id
+        );
+        let mut ty = symbol.ty.clone();
+        for _ in &self.indices {
+            ty = match ty {
+                Type::Array(elem, _) | Type::Pointer(elem) => *elem,
+                _ => bail_at!(self.span, "{} is not an array", self.id),
+            };
+        }
+        Ok(match ty {
+            Type::Array(elem, _) => Type::Pointer(elem),
+            ty => ty,
+        })
+    }
+}
+
+impl LVal {
+    /// 常量, 以及下标都是常量的常量数组元素, 在编译期取值;
+    /// 常量表达式中出现其他左值时报错
+    fn const_value(&self, analyzer: &Analyzer) -> Result<Option<i32>> {
+        let in_const = analyzer.is_const_context();
+        let symbol = analyzer.symbol(self.symbol.expect("LVal is resolved above"));
+        let values = match &symbol.kind {
+            SymbolKind::Const(value) => return Ok(Some(*value)),
+            SymbolKind::ConstArray(values) => values,
+            _ => {
+                ensure_at!(!in_const, self.span, "{} is not a constant", self.id);
+                return Ok(None);
+            }
+        };
+        let dims = symbol.ty.dims();
+        if self.indices.len() != dims.len() {
+            ensure_at!(
+                !in_const,
+                self.span,
+                "Array {} must be fully indexed in a constant expression",
+                self.id
+            );
+            return Ok(None);
+        }
+        let mut flat = 0;
+        for (index_exp, &dim) in self.indices.iter().zip(&dims) {
+            let Some(index) = index_exp.as_number() else {
+                return Ok(None);
+            };
+            if !(0..dim as i32).contains(&index) {
+                ensure_at!(
+                    !in_const,
+                    index_exp.span(),
+                    "Index {} out of bounds for {} (size {})",
+                    index,
+                    self.id,
+                    dim
+                );
+                return Ok(None);
+            }
+            flat = flat * dim + index as usize;
+        }
+        Ok(Some(values[flat]))
+    }
+}
+
+impl ExpAnalyze for FuncCall {
+    fn analyze(&mut self, analyzer: &mut Analyzer) -> Result<Type> {
+        ensure_at!(
+            !analyzer.is_const_context(),
+            self.span,
+            "Function call {} is not a constant expression",
+            self.id
+        );
+        let Ok(id) = analyzer.lookup(&self.id) else {
+            bail_at!(self.span, "Undefined function: {}", self.id);
+        };
+        let Type::Function(params, ret) = analyzer.symbol(id).ty.clone() else {
+            bail_at!(self.span, "{} is not a function", self.id);
+        };
+        self.symbol = Some(id);
+        ensure_at!(
+            params.len() == self.args.len(),
+            self.span,
+            "Function {} expects {} arguments, got {}",
+            self.id,
+            params.len(),
+            self.args.len()
+        );
+        for (i, (param, arg)) in params.iter().zip(&mut self.args).enumerate() {
+            let arg_ty = arg.analyze(analyzer)?;
+            ensure_at!(
+                *param == arg_ty,
+                arg.span(),
+                "Argument {} of {}: expected {}, got {}",
+                i + 1,
+                self.id,
+                param,
+                arg_ty
+            );
+        }
+        Ok(*ret)
+    }
+}
+
+impl Analyzer {
+    /// 与 RISC-V 的 add/sub/mul/div/rem 一样按补码回绕,
+    /// 例如 INT_MIN / -1 = INT_MIN, INT_MIN % -1 = 0.
+    /// 运行时的除零不折叠, 常量表达式中的除零是错误
+    fn fold_binary(
+        &mut self,
+        op: BinaryOp,
+        lhs: i32,
+        rhs: i32,
+        rhs_span: Span,
+        span: Span,
+    ) -> Result<Option<i32>> {
+        if matches!(op, BinaryOp::Div | BinaryOp::Mod) && rhs == 0 {
+            ensure_at!(
+                !self.is_const_context(),
+                rhs_span,
+                "Division by zero in constant expression"
+            );
+            return Ok(None);
+        }
+        let (value, overflow) = match op {
+            BinaryOp::Add => lhs.overflowing_add(rhs),
+            BinaryOp::Sub => lhs.overflowing_sub(rhs),
+            BinaryOp::Mul => lhs.overflowing_mul(rhs),
+            BinaryOp::Div => lhs.overflowing_div(rhs),
+            // INT_MIN % -1 在 RISC-V 上得到 0, 结果正确, 不算溢出
+            BinaryOp::Mod => (lhs.wrapping_rem(rhs), false),
+            BinaryOp::Eq => ((lhs == rhs) as i32, false),
+            BinaryOp::NotEq => ((lhs != rhs) as i32, false),
+            BinaryOp::Ge => ((lhs >= rhs) as i32, false),
+            BinaryOp::Gt => ((lhs > rhs) as i32, false),
+            BinaryOp::Le => ((lhs <= rhs) as i32, false),
+            BinaryOp::Lt => ((lhs < rhs) as i32, false),
+            BinaryOp::And => (((lhs != 0) && (rhs != 0)) as i32, false),
+            BinaryOp::Or => (((lhs != 0) || (rhs != 0)) as i32, false),
+            _ => bail_at!(span, "Unsupported binary operation"),
+        };
+        // 不会被求值的表达式中的溢出不报告
+        if overflow && self.is_evaluated() {
+            self.warn(Lint::Overflow, span, overflow_message(value));
+        }
+        Ok(Some(value))
+    }
+}
+
+fn overflow_message(result: i32) -> String {
+    format!("Integer overflow, result wraps to {}", result)
+}
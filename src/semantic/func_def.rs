@@ -0,0 +1,77 @@
+use super::{Analyzer, Symbol, SymbolKind, Type};
+use crate::ast::{CompUnit, CompUnitItem, FuncDef, FuncFParam};
+use crate::traits::semantic::{Analyze, SymbolTable};
+use crate::Result;
+
+impl Analyze for CompUnit {
+    fn analyze(&mut self, analyzer: &mut Analyzer) -> Result<()> {
+        self.items
+            .iter_mut()
+            .try_for_each(|item| item.analyze(analyzer))
+    }
+}
+
+impl Analyze for CompUnitItem {
+    fn analyze(&mut self, analyzer: &mut Analyzer) -> Result<()> {
+        match self {
+            CompUnitItem::FuncDef(func_def) => func_def.analyze(analyzer),
+            CompUnitItem::Decl(decl) => decl.analyze(analyzer),
+        }
+    }
+}
+
+impl Analyze for FuncDef {
+    fn analyze(&mut self, analyzer: &mut Analyzer) -> Result<()> {
+        let params = self
+            .params
+            .iter_mut()
+            .map(|param| param.param_type(analyzer))
+            .collect::<Result<Vec<_>>>()?;
+        let ret = self.func_type.to_type();
+        let symbol = Symbol {
+            name: self.id.clone(),
+            kind: SymbolKind::Function,
+            ty: Type::Function(params.clone(), Box::new(ret.clone())),
+            scope_level: analyzer.current_scope_level(),
+            span: self.span,
+        };
+        self.symbol = Some(analyzer.define(symbol, "function definition")?);
+
+        // 形参与函数体最外层的声明处于同一个作用域
+        analyzer.enter_function(ret.clone());
+        analyzer.enter_scope()?;
+        for (param, ty) in self.params.iter_mut().zip(params) {
+            let symbol = Symbol {
+                name: param.id.clone(),
+                kind: SymbolKind::Variable,
+                ty,
+                scope_level: analyzer.current_scope_level(),
+                span: param.span,
+            };
+            let id = analyzer.define(symbol, "parameter")?;
+            // 未使用的形参不报警告
+            analyzer.mark_used(id);
+            param.symbol = Some(id);
+        }
+        for item in &mut self.block.items {
+            item.analyze(analyzer)?;
+        }
+        analyzer.exit_scope()?;
+        analyzer.exit_function();
+        analyzer.check_dead_stores(&self.block);
+        analyzer.check_uninitialized(&self.block);
+        analyzer.check_control_flow(self, &ret);
+        Ok(())
+    }
+}
+
+impl FuncFParam {
+    /// `int a[][3]` 的类型是 `*[i32, 3]`
+    fn param_type(&mut self, analyzer: &mut Analyzer) -> Result<Type> {
+        let Some(dims) = &mut self.dims else {
+            return Ok(Type::Int);
+        };
+        let elem = Type::array(Type::Int, &analyzer.eval_dims(dims)?);
+        Ok(Type::Pointer(Box::new(elem)))
+    }
+}
@@ -0,0 +1,102 @@
+use std::collections::HashSet;
+
+use anyhow::bail;
+
+use crate::Result;
+
+/// 可以用 `-W<名字>` / `-Wno-<名字>` 单独开关的警告
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Lint {
+    UnusedVariable,
+    UnusedConst,
+    UnusedAssignment,
+    Shadow,
+    Overflow,
+    Uninitialized,
+    MissingReturn,
+    Unreachable,
+}
+
+impl Lint {
+    pub const ALL: [Lint; 8] = [
+        Lint::UnusedVariable,
+        Lint::UnusedConst,
+        Lint::UnusedAssignment,
+        Lint::Shadow,
+        Lint::Overflow,
+        Lint::Uninitialized,
+        Lint::MissingReturn,
+        Lint::Unreachable,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Lint::UnusedVariable => "unused-variable",
+            Lint::UnusedConst => "unused-const",
+            Lint::UnusedAssignment => "unused-assignment",
+            Lint::Shadow => "shadow",
+            Lint::Overflow => "overflow",
+            Lint::Uninitialized => "uninitialized",
+            Lint::MissingReturn => "missing-return",
+            Lint::Unreachable => "unreachable-code",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Lint> {
+        Lint::ALL.into_iter().find(|lint| lint.name() == name)
+    }
+}
+
+/// 警告开关, 默认全部打开; `werror` 时警告按错误处理
+#[derive(Debug, Clone)]
+pub struct LintConfig {
+    enabled: HashSet<Lint>,
+    pub werror: bool,
+}
+
+impl Default for LintConfig {
+    fn default() -> Self {
+        Self {
+            enabled: Lint::ALL.into_iter().collect(),
+            werror: false,
+        }
+    }
+}
+
+impl LintConfig {
+    /// 关闭所有警告, autotest 只关心编译结果
+    pub fn silenced() -> Self {
+        Self {
+            enabled: HashSet::new(),
+            werror: false,
+        }
+    }
+
+    pub fn is_enabled(&self, lint: Lint) -> bool {
+        self.enabled.contains(&lint)
+    }
+
+    /// 处理 `-W<名字>`、`-Wno-<名字>`、`-Werror` 和 `-Wno-error`
+    pub fn apply_flag(&mut self, flag: &str) -> Result<()> {
+        let Some(rest) = flag.strip_prefix("-W") else {
+            bail!("Not a warning flag: {}", flag);
+        };
+        let (name, enable) = match rest.strip_prefix("no-") {
+            Some(name) => (name, false),
+            None => (rest, true),
+        };
+        if name == "error" {
+            self.werror = enable;
+            return Ok(());
+        }
+        let Some(lint) = Lint::from_name(name) else {
+            bail!("Unknown warning: {}", flag);
+        };
+        if enable {
+            self.enabled.insert(lint);
+        } else {
+            self.enabled.remove(&lint);
+        }
+        Ok(())
+    }
+}
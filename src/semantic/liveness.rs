@@ -0,0 +1,202 @@
+use std::collections::HashSet;
+
+use super::lint::Lint;
+use super::{Analyzer, SymbolId, SymbolKind, Type};
+use crate::ast::{Block, BlockItem, Decl, Exp, InitVal, LVal, PrimaryExp, Stmt, UnaryExp};
+use crate::diagnostic::Span;
+
+// 在 AST 上做反向活跃变量分析, 找出赋值之后在任何路径上都不会被读取的写入.
+// 只跟踪局部的标量变量; 从未被读取的变量已经由 unused-variable 报告, 不再重复
+
+type Live = HashSet<SymbolId>;
+
+impl Analyzer {
+    /// 在函数体分析完成后检查其中无用的赋值
+    pub(crate) fn check_dead_stores(&mut self, body: &Block) {
+        if !self.lint_enabled(Lint::UnusedAssignment) {
+            return;
+        }
+        let mut liveness = Liveness {
+            analyzer: self,
+            breaks: Vec::new(),
+            continues: Vec::new(),
+            report: true,
+        };
+        liveness.block(body, Live::new());
+    }
+}
+
+struct Liveness<'a> {
+    analyzer: &'a mut Analyzer,
+    // 循环出口和循环头处的活跃变量, 分别对应 break 和 continue
+    breaks: Vec<Live>,
+    continues: Vec<Live>,
+    // 循环求不动点的中间轮次不报告
+    report: bool,
+}
+
+impl Liveness<'_> {
+    fn tracked(&self, symbol: Option<SymbolId>) -> Option<SymbolId> {
+        let id = symbol?;
+        let symbol = self.analyzer.symbol(id);
+        let scalar = matches!(symbol.kind, SymbolKind::Variable) && symbol.ty == Type::Int;
+        (scalar && !symbol.is_global() && self.analyzer.is_used(id)).then_some(id)
+    }
+
+    /// 对变量的一次写入: 写入之后不活跃则报告, 写入之前该变量不再活跃
+    fn define(&mut self, id: SymbolId, span: Span, live: &mut Live) {
+        if self.report && !live.contains(&id) {
+            let message = format!(
+                "Value assigned to {} is never read",
+                self.analyzer.symbol(id).name
+            );
+            self.analyzer.warn(Lint::UnusedAssignment, span, message);
+        }
+        live.remove(&id);
+    }
+
+    fn block(&mut self, block: &Block, mut live: Live) -> Live {
+        for item in block.items.iter().rev() {
+            live = match item {
+                BlockItem::Stmt(stmt) => self.stmt(stmt, live),
+                BlockItem::Decl(decl) => self.decl(decl, live),
+            };
+        }
+        live
+    }
+
+    fn decl(&mut self, decl: &Decl, mut live: Live) -> Live {
+        let Decl::VarDecl(_, defs, _) = decl else {
+            return live;
+        };
+        for def in defs.iter().rev() {
+            if let Some(id) = self.tracked(def.symbol) {
+                match &def.init_val {
+                    Some(_) => self.define(id, def.span, &mut live),
+                    None => {
+                        live.remove(&id);
+                    }
+                }
+            }
+            if let Some(init) = &def.init_val {
+                self.init_uses(init, &mut live);
+            }
+        }
+        live
+    }
+
+    fn stmt(&mut self, stmt: &Stmt, mut live: Live) -> Live {
+        match stmt {
+            Stmt::Return(exp, _) => {
+                let mut live = Live::new();
+                if let Some(exp) = exp {
+                    self.exp_uses(exp, &mut live);
+                }
+                live
+            }
+            Stmt::Exp(exp, _) => {
+                if let Some(exp) = exp {
+                    self.exp_uses(exp, &mut live);
+                }
+                live
+            }
+            Stmt::Block(block) => self.block(block, live),
+            Stmt::Assign(lval, exp, span) => {
+                match self.tracked(lval.symbol) {
+                    Some(id) => self.define(id, *span, &mut live),
+                    None => self.lval_uses(lval, &mut live),
+                }
+                self.exp_uses(exp, &mut live);
+                live
+            }
+            Stmt::If(cond, then, els, _) => {
+                let mut result = match els {
+                    Some(els) => self.stmt(els, live.clone()),
+                    None => live.clone(),
+                };
+                result.extend(self.stmt(then, live));
+                self.exp_uses(cond, &mut result);
+                result
+            }
+            Stmt::While(cond, body, _) => self.while_loop(cond, body, live),
+            Stmt::Break(_) => self.breaks.last().cloned().unwrap_or_default(),
+            Stmt::Continue(_) => self.continues.last().cloned().unwrap_or_default(),
+        }
+    }
+
+    /// 循环头的活跃集合 = 条件的使用 ∪ 循环出口 ∪ 循环体入口, 迭代到不动点后再报告
+    fn while_loop(&mut self, cond: &Exp, body: &Stmt, exit: Live) -> Live {
+        let report = self.report;
+        self.report = false;
+        let mut head = exit.clone();
+        self.exp_uses(cond, &mut head);
+        loop {
+            let mut next = self.loop_body(body, &exit, &head);
+            next.extend(exit.iter().copied());
+            self.exp_uses(cond, &mut next);
+            if next == head {
+                break;
+            }
+            head = next;
+        }
+        self.report = report;
+        if report {
+            self.loop_body(body, &exit, &head);
+        }
+        head
+    }
+
+    fn loop_body(&mut self, body: &Stmt, exit: &Live, head: &Live) -> Live {
+        self.breaks.push(exit.clone());
+        self.continues.push(head.clone());
+        let live = self.stmt(body, head.clone());
+        self.breaks.pop();
+        self.continues.pop();
+        live
+    }
+
+    fn init_uses(&self, init: &InitVal, live: &mut Live) {
+        match init {
+            InitVal::Exp(exp) => self.exp_uses(exp, live),
+            InitVal::List(list, _) => list.iter().for_each(|item| self.init_uses(item, live)),
+        }
+    }
+
+    fn exp_uses(&self, exp: &Exp, live: &mut Live) {
+        match exp {
+            Exp::Primary(primary) => self.primary_uses(primary, live),
+            Exp::UnaryExp(unary) => self.unary_uses(unary, live),
+            Exp::Binary(lhs, _, rhs) => {
+                self.exp_uses(lhs, live);
+                self.exp_uses(rhs, live);
+            }
+        }
+    }
+
+    fn unary_uses(&self, unary: &UnaryExp, live: &mut Live) {
+        match unary {
+            UnaryExp::PrimaryExp(primary) => self.primary_uses(primary, live),
+            UnaryExp::UnaryOp(_, operand, _) => self.unary_uses(operand, live),
+            UnaryExp::Call(call) => call.args.iter().for_each(|arg| self.exp_uses(arg, live)),
+        }
+    }
+
+    fn primary_uses(&self, primary: &PrimaryExp, live: &mut Live) {
+        match primary {
+            PrimaryExp::Number(..) => {}
+            PrimaryExp::Exp(exp, _) => self.exp_uses(exp, live),
+            PrimaryExp::LVal(lval) => {
+                if let Some(id) = self.tracked(lval.symbol) {
+                    live.insert(id);
+                }
+                self.lval_uses(lval, live);
+            }
+        }
+    }
+
+    fn lval_uses(&self, lval: &LVal, live: &mut Live) {
+        lval.indices
+            .iter()
+            .for_each(|index| self.exp_uses(index, live));
+    }
+}
@@ -1,22 +1,99 @@
-use koopa::ir::{Function, Value};
+mod analyzer;
+mod control_flow;
+mod decl;
+mod exp;
+mod func_def;
+mod lint;
+mod liveness;
+mod runtime_lib;
+mod stmt;
+mod uninit;
+use std::fmt;
+
+pub use analyzer::Analyzer;
+pub(crate) use decl::flatten_init;
+pub use lint::{Lint, LintConfig};
+
+use crate::diagnostic::Span;
+
+/// 符号在符号表中的下标, 语义分析把它写回 AST, IR 生成时直接按下标取符号
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SymbolId(pub usize);
+
+#[derive(Debug, Clone)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    pub ty: Type,
+    pub scope_level: usize,
+    pub span: Span,
+}
+
 #[derive(Debug, Clone)]
 pub enum SymbolKind {
-    Const { value: i32, scope_level: usize },
-    Variable { value: Value, scope_level: usize },
-    Function { func: Function, scope_level: usize },
+    Const(i32),
+    // 常量数组既要能在编译期取值, 也要能用变量下标在运行时访问
+    ConstArray(Vec<i32>),
+    Variable,
+    Function,
+    // 运行时库函数只有声明
+    RuntimeFunction,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Type {
     Int,
+    Void,
+    Array(Box<Type>, usize),
+    // 数组形参省略第一维, 退化为指向剩余部分的指针
+    Pointer(Box<Type>),
+    Function(Vec<Type>, Box<Type>),
+}
+
+impl Type {
+    /// `int a[2][3]` 对应 `[[i32, 3], 2]`
+    pub fn array(base: Type, dims: &[usize]) -> Type {
+        dims.iter()
+            .rev()
+            .fold(base, |ty, &len| Type::Array(Box::new(ty), len))
+    }
+
+    /// 数组各维的长度, 标量为空
+    pub fn dims(&self) -> Vec<usize> {
+        let mut dims = Vec::new();
+        let mut ty = self;
+        while let Type::Array(base, len) = ty {
+            dims.push(*len);
+            ty = base;
+        }
+        dims
+    }
+
+    pub fn to_koop(&self) -> koopa::ir::Type {
+        match self {
+            Type::Int => koopa::ir::Type::get_i32(),
+            Type::Void => koopa::ir::Type::get_unit(),
+            Type::Array(base, len) => koopa::ir::Type::get_array(base.to_koop(), *len),
+            Type::Pointer(base) => koopa::ir::Type::get_pointer(base.to_koop()),
+            Type::Function(params, ret) => koopa::ir::Type::get_function(
+                params.iter().map(Type::to_koop).collect(),
+                ret.to_koop(),
+            ),
+        }
+    }
 }
 
-impl SymbolKind {
-    pub fn get_scope_level(&self) -> usize {
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            SymbolKind::Const { scope_level, .. } => *scope_level,
-            SymbolKind::Variable { scope_level, .. } => *scope_level,
-            SymbolKind::Function { scope_level, .. } => *scope_level,
+            Type::Void => write!(f, "void"),
+            _ => write!(f, "{}", self.to_koop()),
         }
     }
 }
+
+impl Symbol {
+    pub fn is_global(&self) -> bool {
+        self.scope_level == 0
+    }
+}
@@ -0,0 +1,30 @@
+use super::{Analyzer, Symbol, SymbolKind, Type};
+use crate::diagnostic::Span;
+use crate::traits::semantic::SymbolTable;
+
+impl Analyzer {
+    /// 在全局作用域预先声明 SysY 运行时库函数, 对应 Koopa 中的 `decl`
+    pub(super) fn declare_runtime_lib(&mut self) {
+        let int_ptr = || Type::Pointer(Box::new(Type::Int));
+        let lib = [
+            ("getint", vec![], Type::Int),
+            ("getch", vec![], Type::Int),
+            ("getarray", vec![int_ptr()], Type::Int),
+            ("putint", vec![Type::Int], Type::Void),
+            ("putch", vec![Type::Int], Type::Void),
+            ("putarray", vec![Type::Int, int_ptr()], Type::Void),
+            ("starttime", vec![], Type::Void),
+            ("stoptime", vec![], Type::Void),
+        ];
+        for (name, params, ret) in lib {
+            self.add_symbol(Symbol {
+                name: name.to_string(),
+                kind: SymbolKind::RuntimeFunction,
+                ty: Type::Function(params, Box::new(ret)),
+                scope_level: 0,
+                span: Span::default(),
+            })
+            .expect("Runtime library functions must have unique names");
+        }
+    }
+}
@@ -0,0 +1,128 @@
+use super::{Analyzer, SymbolKind, Type};
+use crate::ast::{Block, BlockItem, Exp, Stmt};
+use crate::ensure_at;
+use crate::traits::semantic::{Analyze, ExpAnalyze, SymbolTable};
+use crate::Result;
+
+impl Analyze for Stmt {
+    fn analyze(&mut self, analyzer: &mut Analyzer) -> Result<()> {
+        match self {
+            Stmt::Return(exp, span) => {
+                let ret_ty = analyzer.current_ret_type().clone();
+                match exp {
+                    Some(exp) => {
+                        ensure_at!(
+                            ret_ty != Type::Void,
+                            *span,
+                            "Void function should not return a value"
+                        );
+                        let val_ty = exp.analyze(analyzer)?;
+                        ensure_at!(
+                            val_ty == ret_ty,
+                            exp.span(),
+                            "Return type mismatch: expected {}, got {}",
+                            ret_ty,
+                            val_ty
+                        );
+                    }
+                    None => ensure_at!(
+                        ret_ty == Type::Void,
+                        *span,
+                        "Non-void function should return a value"
+                    ),
+                }
+            }
+            Stmt::Assign(lval, exp, _) => {
+                let lval_ty = lval.analyze(analyzer)?;
+                let symbol = analyzer.symbol(lval.symbol.expect("LVal is resolved above"));
+                ensure_at!(
+                    !matches!(
+                        symbol.kind,
+                        SymbolKind::Const(_) | SymbolKind::ConstArray(_)
+                    ),
+                    lval.span,
+                    "Cannot assign to a constant {}",
+                    lval.id
+                );
+                ensure_at!(
+                    lval_ty == Type::Int,
+                    lval.span,
+                    "Cannot assign to array {}",
+                    lval.id
+                );
+                let exp_ty = exp.analyze(analyzer)?;
+                ensure_at!(
+                    exp_ty == Type::Int,
+                    exp.span(),
+                    "Type mismatch: expected i32, got {}",
+                    exp_ty
+                );
+            }
+            Stmt::Exp(exp, _) => {
+                if let Some(exp) = exp {
+                    exp.analyze(analyzer)?;
+                }
+            }
+            Stmt::Block(block) => block.analyze(analyzer)?,
+            Stmt::If(cond, then, els, _) => {
+                analyze_cond(cond, analyzer)?;
+                then.analyze(analyzer)?;
+                if let Some(els) = els {
+                    els.analyze(analyzer)?;
+                }
+            }
+            Stmt::While(cond, body, _) => {
+                analyze_cond(cond, analyzer)?;
+                analyzer.enter_loop();
+                let result = body.analyze(analyzer);
+                analyzer.exit_loop();
+                result?;
+            }
+            Stmt::Break(span) => {
+                ensure_at!(
+                    analyzer.in_loop(),
+                    *span,
+                    "break statement not within a loop"
+                );
+            }
+            Stmt::Continue(span) => {
+                ensure_at!(
+                    analyzer.in_loop(),
+                    *span,
+                    "continue statement not within a loop"
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+fn analyze_cond(cond: &mut Exp, analyzer: &mut Analyzer) -> Result<()> {
+    let ty = cond.analyze(analyzer)?;
+    ensure_at!(
+        ty == Type::Int,
+        cond.span(),
+        "Condition must be an int, got {}",
+        ty
+    );
+    Ok(())
+}
+
+impl Analyze for Block {
+    fn analyze(&mut self, analyzer: &mut Analyzer) -> Result<()> {
+        analyzer.enter_scope()?;
+        for item in &mut self.items {
+            item.analyze(analyzer)?;
+        }
+        analyzer.exit_scope()
+    }
+}
+
+impl Analyze for BlockItem {
+    fn analyze(&mut self, analyzer: &mut Analyzer) -> Result<()> {
+        match self {
+            BlockItem::Stmt(stmt) => stmt.analyze(analyzer),
+            BlockItem::Decl(decl) => decl.analyze(analyzer),
+        }
+    }
+}
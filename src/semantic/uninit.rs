@@ -0,0 +1,221 @@
+use std::collections::HashSet;
+
+use super::lint::Lint;
+use super::{Analyzer, SymbolId, SymbolKind, Type};
+use crate::ast::{Block, BlockItem, Decl, Exp, InitVal, LVal, PrimaryExp, Stmt, UnaryExp};
+
+// 在 AST 上做正向的确定赋值分析, 找出可能在任何写入之前被读取的局部变量.
+// 只跟踪没有初始值的局部标量; 全局变量默认为 0, 数组元素不做跟踪
+
+/// 一定已被赋值的变量; None 表示不可达 (return/break/continue 之后)
+type Assigned = Option<HashSet<SymbolId>>;
+
+impl Analyzer {
+    /// 在函数体分析完成后检查其中可能未初始化的读取
+    pub(crate) fn check_uninitialized(&mut self, body: &Block) {
+        if !self.lint_enabled(Lint::Uninitialized) {
+            return;
+        }
+        let mut checker = DefiniteAssignment {
+            analyzer: self,
+            tracked: HashSet::new(),
+            breaks: Vec::new(),
+            continues: Vec::new(),
+            report: true,
+        };
+        checker.block(body, Some(HashSet::new()));
+    }
+}
+
+struct DefiniteAssignment<'a> {
+    analyzer: &'a mut Analyzer,
+    // 已经遇到的无初始值声明
+    tracked: HashSet<SymbolId>,
+    // 各层循环中 break 和 continue 处状态的交集
+    breaks: Vec<Assigned>,
+    continues: Vec<Assigned>,
+    // 循环求不动点的中间轮次不报告
+    report: bool,
+}
+
+/// 控制流汇合处取交集, 不可达的一侧不影响结果
+fn meet(lhs: Assigned, rhs: Assigned) -> Assigned {
+    match (lhs, rhs) {
+        (Some(lhs), Some(rhs)) => Some(lhs.intersection(&rhs).copied().collect()),
+        (lhs, None) => lhs,
+        (None, rhs) => rhs,
+    }
+}
+
+impl DefiniteAssignment<'_> {
+    fn block(&mut self, block: &Block, mut state: Assigned) -> Assigned {
+        for item in &block.items {
+            state = match item {
+                BlockItem::Stmt(stmt) => self.stmt(stmt, state),
+                BlockItem::Decl(decl) => self.decl(decl, state),
+            };
+        }
+        state
+    }
+
+    fn decl(&mut self, decl: &Decl, mut state: Assigned) -> Assigned {
+        let Decl::VarDecl(_, defs, _) = decl else {
+            return state;
+        };
+        for def in defs {
+            let Some(id) = def.symbol else {
+                continue;
+            };
+            match &def.init_val {
+                Some(init) => {
+                    self.init_reads(init, &state);
+                    if let Some(assigned) = &mut state {
+                        assigned.insert(id);
+                    }
+                }
+                None => {
+                    let symbol = self.analyzer.symbol(id);
+                    let scalar =
+                        matches!(symbol.kind, SymbolKind::Variable) && symbol.ty == Type::Int;
+                    if scalar && !symbol.is_global() {
+                        self.tracked.insert(id);
+                        // 循环中再次执行到声明时, 变量重新变为未初始化
+                        if let Some(assigned) = &mut state {
+                            assigned.remove(&id);
+                        }
+                    }
+                }
+            }
+        }
+        state
+    }
+
+    fn stmt(&mut self, stmt: &Stmt, mut state: Assigned) -> Assigned {
+        match stmt {
+            Stmt::Return(exp, _) => {
+                if let Some(exp) = exp {
+                    self.exp_reads(exp, &state);
+                }
+                None
+            }
+            Stmt::Exp(exp, _) => {
+                if let Some(exp) = exp {
+                    self.exp_reads(exp, &state);
+                }
+                state
+            }
+            Stmt::Block(block) => self.block(block, state),
+            Stmt::Assign(lval, exp, _) => {
+                lval.indices
+                    .iter()
+                    .for_each(|index| self.exp_reads(index, &state));
+                self.exp_reads(exp, &state);
+                if let (Some(assigned), Some(id)) = (&mut state, lval.symbol) {
+                    assigned.insert(id);
+                }
+                state
+            }
+            Stmt::If(cond, then, els, _) => {
+                self.exp_reads(cond, &state);
+                let then_state = self.stmt(then, state.clone());
+                let else_state = match els {
+                    Some(els) => self.stmt(els, state),
+                    None => state,
+                };
+                meet(then_state, else_state)
+            }
+            Stmt::While(cond, body, _) => self.while_loop(cond, body, state),
+            Stmt::Break(_) => {
+                let top = self.breaks.pop().expect("break outside of a loop");
+                self.breaks.push(meet(top, state));
+                None
+            }
+            Stmt::Continue(_) => {
+                let top = self.continues.pop().expect("continue outside of a loop");
+                self.continues.push(meet(top, state));
+                None
+            }
+        }
+    }
+
+    /// 循环头的状态 = 进入循环时的状态 ∩ 循环体末尾 ∩ 各个 continue, 迭代到不动点后再报告
+    fn while_loop(&mut self, cond: &Exp, body: &Stmt, entry: Assigned) -> Assigned {
+        let report = self.report;
+        self.report = false;
+        let mut head = entry.clone();
+        loop {
+            let (end, _) = self.loop_body(cond, body, &head);
+            let next = meet(entry.clone(), end);
+            if next == head {
+                break;
+            }
+            head = next;
+        }
+        self.report = report;
+        let (_, exit) = self.loop_body(cond, body, &head);
+        exit
+    }
+
+    /// 返回循环体末尾 (含 continue) 的状态, 以及循环出口 (条件为假或 break) 的状态
+    fn loop_body(&mut self, cond: &Exp, body: &Stmt, head: &Assigned) -> (Assigned, Assigned) {
+        self.exp_reads(cond, head);
+        self.breaks.push(None);
+        self.continues.push(None);
+        let end = self.stmt(body, head.clone());
+        let breaks = self.breaks.pop().expect("pushed above");
+        let continues = self.continues.pop().expect("pushed above");
+        // 条件恒为真时只能经由 break 离开循环
+        let exit = match cond.as_number() {
+            Some(value) if value != 0 => breaks,
+            _ => meet(head.clone(), breaks),
+        };
+        (meet(end, continues), exit)
+    }
+
+    fn init_reads(&mut self, init: &InitVal, state: &Assigned) {
+        match init {
+            InitVal::Exp(exp) => self.exp_reads(exp, state),
+            InitVal::List(list, _) => list.iter().for_each(|item| self.init_reads(item, state)),
+        }
+    }
+
+    fn exp_reads(&mut self, exp: &Exp, state: &Assigned) {
+        match exp {
+            Exp::Primary(primary) => self.primary_reads(primary, state),
+            Exp::UnaryExp(unary) => self.unary_reads(unary, state),
+            Exp::Binary(lhs, _, rhs) => {
+                self.exp_reads(lhs, state);
+                self.exp_reads(rhs, state);
+            }
+        }
+    }
+
+    fn unary_reads(&mut self, unary: &UnaryExp, state: &Assigned) {
+        match unary {
+            UnaryExp::PrimaryExp(primary) => self.primary_reads(primary, state),
+            UnaryExp::UnaryOp(_, operand, _) => self.unary_reads(operand, state),
+            UnaryExp::Call(call) => call.args.iter().for_each(|arg| self.exp_reads(arg, state)),
+        }
+    }
+
+    fn primary_reads(&mut self, primary: &PrimaryExp, state: &Assigned) {
+        match primary {
+            PrimaryExp::Number(..) => {}
+            PrimaryExp::Exp(exp, _) => self.exp_reads(exp, state),
+            PrimaryExp::LVal(lval) => self.lval_read(lval, state),
+        }
+    }
+
+    fn lval_read(&mut self, lval: &LVal, state: &Assigned) {
+        lval.indices
+            .iter()
+            .for_each(|index| self.exp_reads(index, state));
+        let (Some(id), Some(assigned)) = (lval.symbol, state) else {
+            return;
+        };
+        if self.report && self.tracked.contains(&id) && !assigned.contains(&id) {
+            let message = format!("Variable {} may be used uninitialized", lval.id);
+            self.analyzer.warn(Lint::Uninitialized, lval.span, message);
+        }
+    }
+}
@@ -1,8 +1,14 @@
-use anyhow::Result;
 use koopa::ir::BinaryOp;
 pub trait InstructionGenerator {
     fn generate_binary(&mut self, op: BinaryOp, dst: &str, lhs: &str, rhs: &str) -> String;
     fn generate_return(&mut self, val_reg: Option<&str>) -> String;
     fn generate_load_immediate(&mut self, dst: &str, value: i32) -> String;
     fn generate_move(&mut self, dst: &str, src: &str) -> String;
+    fn generate_load(&mut self, dst: &str, offset: i32, base: &str) -> String;
+    fn generate_store(&mut self, src: &str, offset: i32, base: &str) -> String;
+    fn generate_addi(&mut self, dst: &str, src: &str, imm: i32) -> String;
+    /// dst = base + index * scale
+    fn generate_scaled_add(&mut self, dst: &str, base: &str, index: &str, scale: i32) -> String;
+    fn generate_branch(&mut self, cond: &str, true_label: &str, false_label: &str) -> String;
+    fn generate_jump(&mut self, label: &str) -> String;
 }
@@ -1,13 +1,20 @@
 use super::Result;
-use crate::{ir_builder::IRBuilder, semantic::SymbolKind};
-pub trait ConstEval {
-    fn eval_const(&self, builder: &IRBuilder) -> Result<i32>;
+use crate::semantic::{Analyzer, Symbol, SymbolId, Type};
+
+/// 语义分析: 解析名字、检查类型, 并把结果 (符号编号、折叠后的常量) 写回 AST
+pub trait Analyze {
+    fn analyze(&mut self, analyzer: &mut Analyzer) -> Result<()>;
+}
+
+/// 表达式的语义分析, 返回表达式的类型
+pub trait ExpAnalyze {
+    fn analyze(&mut self, analyzer: &mut Analyzer) -> Result<Type>;
 }
 
 pub trait SymbolTable {
     fn enter_scope(&mut self) -> Result<()>;
     fn exit_scope(&mut self) -> Result<()>;
-    fn lookup(&self, name: &str) -> Result<&SymbolKind>;
-    fn add_symbol(&mut self, name: &str, kind: SymbolKind) -> Result<()>;
+    fn lookup(&self, name: &str) -> Result<SymbolId>;
+    fn add_symbol(&mut self, symbol: Symbol) -> Result<SymbolId>;
     fn current_scope_level(&self) -> usize;
 }
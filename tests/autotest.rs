@@ -0,0 +1,48 @@
+// 在 tests/testcases 上运行 `sysY -autotest`, 两个 target 都必须全部通过
+
+use std::process::Command;
+
+#[test]
+fn testcases_pass_on_both_targets() {
+    let output = Command::new(env!("CARGO_BIN_EXE_sysY"))
+        .args([
+            "-autotest",
+            concat!(env!("CARGO_MANIFEST_DIR"), "/tests/testcases"),
+        ])
+        .output()
+        .unwrap();
+    let report = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        output.status.success(),
+        "{}\n{}",
+        report,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(report.contains("total"), "{}", report);
+}
+
+/// 死循环的用例按超时失败, 不会让 autotest 卡住
+#[test]
+fn infinite_loops_exceed_the_time_limit() {
+    let dir = std::env::temp_dir().join(format!("sysY-autotest-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(
+        dir.join("loop.c"),
+        "int main() {\n  while (1) {}\n  return 0;\n}\n",
+    )
+    .unwrap();
+    std::fs::write(dir.join("loop.out"), "0\n").unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_sysY"))
+        .args(["-autotest", dir.to_str().unwrap(), "-max-steps=100000"])
+        .output()
+        .unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+    let report = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(1), "{}", report);
+    assert_eq!(
+        report.matches("Time limit exceeded").count(),
+        2,
+        "{}",
+        report
+    );
+}
@@ -0,0 +1,325 @@
+// 编译小段 SysY 源码, 检查 `文件:行:列` 形式的诊断信息、警告和错误的区分以及退出码
+
+use std::fs::{create_dir_all, write};
+use std::path::PathBuf;
+use std::process::Command;
+
+/// 诊断信息输出到 stderr, 返回退出码和 stderr. 每个用例使用自己的文件名, 测试可以并行运行
+fn compile(name: &str, source: &str, flags: &[&str]) -> (i32, String) {
+    let dir: PathBuf =
+        std::env::temp_dir().join(format!("sysY-diagnostics-{}", std::process::id()));
+    create_dir_all(&dir).unwrap();
+    let file = format!("{}.c", name);
+    write(dir.join(&file), source).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_sysY"))
+        .args(["-koopa", &file, "-o", &format!("{}.koopa", name)])
+        .args(flags)
+        .current_dir(&dir)
+        .output()
+        .unwrap();
+    (
+        output.status.code().unwrap(),
+        String::from_utf8_lossy(&output.stderr).into_owned(),
+    )
+}
+
+#[test]
+fn semantic_errors_point_at_the_source() {
+    let source = "int main() {\n  int x = 1;\n  x = x + y;\n  return x;\n}\n";
+    let (code, stderr) = compile("undefined", source, &[]);
+    assert_eq!(code, 1, "{}", stderr);
+    assert!(
+        stderr.starts_with(
+            "undefined.c:3:11: error: Undefined identifier: y\n  |\n3 |   x = x + y;\n  |           ^\n"
+        ),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn parse_errors_are_recovered_at_semicolons() {
+    let source = "int main() {\n  int a = 1\n  int b = 2;\n  return a + b +;\n}\n";
+    let (code, stderr) = compile("semicolons", source, &[]);
+    assert_eq!(code, 1, "{}", stderr);
+    assert!(
+        stderr.contains("semicolons.c:3:3: error: Unexpected `int`"),
+        "{}",
+        stderr
+    );
+    assert!(
+        stderr.contains("semicolons.c:4:17: error: Unexpected `;`"),
+        "{}",
+        stderr
+    );
+    assert!(
+        stderr.ends_with("2 error(s) found while parsing\n"),
+        "{}",
+        stderr
+    );
+}
+
+/// 整数字面量有三个正则, 在候选列表中只出现一次
+#[test]
+fn expected_tokens_are_listed_once() {
+    let source = "int main() {\n  int x = ;\n  return x;\n}\n";
+    let (code, stderr) = compile("expected", source, &[]);
+    assert_eq!(code, 1, "{}", stderr);
+    assert!(
+        stderr.contains(
+            "expected.c:2:11: error: Unexpected `;`, expected one of \
+             `!`, `(`, `+`, `-`, `{`, identifier, integer literal\n"
+        ),
+        "{}",
+        stderr
+    );
+}
+
+/// 函数头或块内出错时跳到 "}", 后面的函数不会产生多余的错误
+#[test]
+fn parse_errors_are_recovered_at_closing_braces() {
+    let source = "int f( { return 0; }\nint g() { return 1; }\n";
+    let (code, stderr) = compile("function_header", source, &[]);
+    assert_eq!(code, 1, "{}", stderr);
+    assert!(
+        stderr.contains("function_header.c:1:8: error: Unexpected `{`"),
+        "{}",
+        stderr
+    );
+    assert!(
+        stderr.ends_with("1 error(s) found while parsing\n"),
+        "{}",
+        stderr
+    );
+
+    let source = "int main() {\n  int x = 1\n}\nint g() { return 1; }\n";
+    let (code, stderr) = compile("block", source, &[]);
+    assert_eq!(code, 1, "{}", stderr);
+    assert!(
+        stderr.contains("block.c:3:1: error: Unexpected `}`"),
+        "{}",
+        stderr
+    );
+    assert!(
+        stderr.ends_with("1 error(s) found while parsing\n"),
+        "{}",
+        stderr
+    );
+
+    let source =
+        "int main() {\n  int x = 1;\n  if (x { x = 2; }\n  return x;\n}\nint g() { return 1; }\n";
+    let (code, stderr) = compile("nested_block", source, &[]);
+    assert_eq!(code, 1, "{}", stderr);
+    assert!(
+        stderr.contains("nested_block.c:3:9: error: Unexpected `{`"),
+        "{}",
+        stderr
+    );
+    assert!(
+        stderr.ends_with("1 error(s) found while parsing\n"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn oversized_literals_are_errors() {
+    let source = "int main() {\n  int x = 0x100000000;\n  return -2147483648;\n}\n";
+    let (code, stderr) = compile("literal", source, &[]);
+    assert_eq!(code, 1, "{}", stderr);
+    assert!(
+        stderr.contains("literal.c:2:11: error: Integer literal is too large for 32 bits"),
+        "{}",
+        stderr
+    );
+    assert!(
+        stderr.ends_with("1 error(s) found while parsing\n"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn constant_division_by_zero_is_an_error() {
+    let source = "const int a = 1 / 0;\nint main() { return a; }\n";
+    let (code, stderr) = compile("div_zero", source, &[]);
+    assert_eq!(code, 1, "{}", stderr);
+    assert!(
+        stderr.contains("div_zero.c:1:19: error: Division by zero in constant expression"),
+        "{}",
+        stderr
+    );
+
+    // 短路求值时右侧不会被求值, 不报告除零
+    let source =
+        "const int a = 0 && 1 / 0;\nconst int b = 1 || 1 % 0;\nint main() { return a + b; }\n";
+    let (code, stderr) = compile("short_circuit", source, &[]);
+    assert_eq!(code, 0, "{}", stderr);
+    assert!(stderr.is_empty(), "{}", stderr);
+}
+
+#[test]
+fn uninitialized_reads_are_warnings() {
+    let source = "int main() {\n  int z;\n  putint(z);\n  return 0;\n}\n";
+    let (code, stderr) = compile("uninit", source, &[]);
+    assert_eq!(code, 0, "{}", stderr);
+    assert!(
+        stderr.contains(
+            "uninit.c:3:10: warning: Variable z may be used uninitialized [-Wuninitialized]"
+        ),
+        "{}",
+        stderr
+    );
+
+    // 两个分支都赋值后读取不报告
+    let source = "int main() {\n  int y;\n  if (getint()) y = 1; else y = 2;\n  putint(y);\n  return 0;\n}\n";
+    let (code, stderr) = compile("both_branches", source, &[]);
+    assert_eq!(code, 0, "{}", stderr);
+    assert!(stderr.is_empty(), "{}", stderr);
+}
+
+#[test]
+fn unreachable_code_and_missing_returns_are_warnings() {
+    let source = "int main() {\n  int x = 0;\n  return x;\n  x = 1;\n}\nint f() {\n  if (getint()) return 1;\n}\n";
+    let (code, stderr) = compile("flow", source, &[]);
+    assert_eq!(code, 0, "{}", stderr);
+    assert!(
+        stderr.contains("flow.c:4:3: warning: Unreachable code [-Wunreachable-code]"),
+        "{}",
+        stderr
+    );
+    assert!(
+        stderr.contains(
+            "flow.c:8:1: warning: Function f may end without returning a value [-Wmissing-return]"
+        ),
+        "{}",
+        stderr
+    );
+}
+
+/// 非 ASCII 字符按一个字符标出
+#[test]
+fn invalid_tokens_are_measured_in_characters() {
+    let source = "int main() {\n  int b = 2 é 3;\n  return 0;\n}\n";
+    let (code, stderr) = compile("invalid_token", source, &[]);
+    assert_eq!(code, 1, "{}", stderr);
+    assert!(
+        stderr.starts_with(
+            "invalid_token.c:2:13: error: Invalid token\n  |\n2 |   int b = 2 é 3;\n  |             ^\n"
+        ),
+        "{}",
+        stderr
+    );
+}
+
+const WARNINGS: &str = "int main() {
+  int unused = 1;
+  int x = 2;
+  {
+    int x = 3;
+    putint(x);
+  }
+  int y;
+  y = 4;
+  y = 5;
+  putint(y);
+  int z;
+  putint(z);
+  return x;
+  x = 1;
+}
+int f() {
+  if (getint()) return 1;
+}
+";
+
+#[test]
+fn warnings_do_not_fail_the_compilation() {
+    let (code, stderr) = compile("warnings", WARNINGS, &[]);
+    assert_eq!(code, 0, "{}", stderr);
+    for expected in [
+        "warnings.c:2:7: warning: Variable unused is never read [-Wunused-variable]",
+        "warnings.c:5:9: warning: Declaration of x shadows an outer declaration [-Wshadow]",
+        "warnings.c:9:3: warning: Value assigned to y is never read [-Wunused-assignment]",
+        "warnings.c:13:10: warning: Variable z may be used uninitialized [-Wuninitialized]",
+        "warnings.c:15:3: warning: Unreachable code [-Wunreachable-code]",
+        "warnings.c:19:1: warning: Function f may end without returning a value [-Wmissing-return]",
+    ] {
+        assert!(stderr.contains(expected), "{}\n{}", expected, stderr);
+    }
+    assert!(!stderr.contains("error"), "{}", stderr);
+}
+
+#[test]
+fn werror_turns_warnings_into_errors() {
+    let (code, stderr) = compile("werror", WARNINGS, &["-Werror"]);
+    assert_eq!(code, 1, "{}", stderr);
+    assert!(
+        stderr.contains("werror.c:2:7: error: Variable unused is never read [-Wunused-variable]"),
+        "{}",
+        stderr
+    );
+    assert!(!stderr.contains("warning:"), "{}", stderr);
+
+    // 后面的开关覆盖前面的
+    let (code, stderr) = compile("wno_error", WARNINGS, &["-Werror", "-Wno-error"]);
+    assert_eq!(code, 0, "{}", stderr);
+    assert!(stderr.contains("warning:"), "{}", stderr);
+}
+
+#[test]
+fn warnings_can_be_switched_individually() {
+    let (code, stderr) = compile(
+        "switches",
+        WARNINGS,
+        &["-Wno-shadow", "-Wno-unused-variable", "-Wunused-variable"],
+    );
+    assert_eq!(code, 0, "{}", stderr);
+    assert!(!stderr.contains("[-Wshadow]"), "{}", stderr);
+    assert!(stderr.contains("[-Wunused-variable]"), "{}", stderr);
+    assert!(stderr.contains("[-Wunused-assignment]"), "{}", stderr);
+
+    // 关掉的警告在 -Werror 下也不会导致失败
+    let flags = [
+        "-Werror",
+        "-Wno-unused-variable",
+        "-Wno-shadow",
+        "-Wno-unused-assignment",
+        "-Wno-uninitialized",
+        "-Wno-unreachable-code",
+        "-Wno-missing-return",
+    ];
+    let (code, stderr) = compile("all_off", WARNINGS, &flags);
+    assert_eq!(code, 0, "{}", stderr);
+    assert!(stderr.is_empty(), "{}", stderr);
+
+    let (code, stderr) = compile("unknown_flag", WARNINGS, &["-Wno-such-warning"]);
+    assert_ne!(code, 0);
+    assert!(
+        stderr.contains("Unknown warning: -Wno-such-warning"),
+        "{}",
+        stderr
+    );
+}
+
+/// 溢出警告不限于常量表达式, 但结果与 RISC-V 一致的运算 (如 INT_MIN % -1) 不报告
+#[test]
+fn only_real_overflows_are_warnings() {
+    let source = "int main() {\n  int a = -2147483648 % -1;\n  int b = -2147483648;\n  int c = 0 && 2147483647 + 1;\n  putint(a + b + c);\n  return 0;\n}\n";
+    let (code, stderr) = compile("no_overflow", source, &[]);
+    assert_eq!(code, 0, "{}", stderr);
+    assert!(stderr.is_empty(), "{}", stderr);
+
+    let source = "const int a = 2147483647 + 1;\nint main() {\n  int b = -2147483648 / -1;\n  int c = 2 * 1073741824;\n  putint(a + b + c);\n  return 2147483648;\n}\n";
+    let (code, stderr) = compile("overflow", source, &[]);
+    assert_eq!(code, 0, "{}", stderr);
+    for expected in [
+        "overflow.c:1:15: warning: Integer overflow, result wraps to -2147483648 [-Woverflow]",
+        "overflow.c:3:11: warning: Integer overflow, result wraps to -2147483648 [-Woverflow]",
+        "overflow.c:4:11: warning: Integer overflow, result wraps to -2147483648 [-Woverflow]",
+        "overflow.c:6:10: warning: Integer literal 2147483648 does not fit in int, wraps to -2147483648 [-Woverflow]",
+    ] {
+        assert!(stderr.contains(expected), "{}", stderr);
+    }
+    assert_eq!(stderr.matches("warning:").count(), 4, "{}", stderr);
+}
@@ -0,0 +1,137 @@
+// 差分测试: 随机程序在参考求值器、Koopa 解释器和 RISC-V 模拟器上的结果必须一致
+
+use std::process::Command;
+
+use koopa::ir::builder_traits::*;
+use koopa::ir::{BinaryOp, ValueKind};
+use sysY::ast::*;
+use sysY::diagnostic::Span;
+use sysY::fuzz::{check, generate, Compiled, FuzzConfig, Verdict};
+use sysY::ir_builder::IRBuilder;
+use sysY::semantic::{Analyzer, LintConfig};
+use sysY::traits::semantic::Analyze;
+use sysY::traits::ToIr;
+
+#[test]
+fn random_programs_agree_on_all_targets() {
+    // 不一致的程序会写到当前目录
+    let output = Command::new(env!("CARGO_BIN_EXE_sysY"))
+        .args(["-fuzz", "50", "seed=1"])
+        .current_dir(std::env::temp_dir())
+        .output()
+        .unwrap();
+    let report = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        output.status.success(),
+        "{}\n{}",
+        report,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(report.contains("50 programs"), "{}", report);
+}
+
+#[test]
+fn generation_is_deterministic_and_respects_features() {
+    let mut config = FuzzConfig::default();
+    config.apply_option("seed=42").unwrap();
+    assert_eq!(generate(&config), generate(&config));
+
+    for option in ["no-arrays", "no-functions", "no-loops"] {
+        config.apply_option(option).unwrap();
+    }
+    for seed in 0..20 {
+        config.seed = seed;
+        let source = generate(&config);
+        assert!(!source.contains("while"), "{}", source);
+        // 只剩 main 一个函数, 没有数组
+        assert!(!source.contains("int f"), "{}", source);
+        assert!(!source.contains("]"), "{}", source);
+    }
+    assert!(config.apply_option("no-such-feature").is_err());
+}
+
+fn number(value: i32) -> Exp {
+    Exp::Primary(PrimaryExp::Number(value, Span::new(0, 0)))
+}
+
+/// `int main() { int x = 7; return -x; }`
+fn negation() -> CompUnit {
+    let span = Span::new(0, 0);
+    let def = VarDef {
+        id: "x".to_string(),
+        ty: BType::Int,
+        dims: Vec::new(),
+        init_val: Some(InitVal::Exp(Box::new(number(7)))),
+        span,
+        symbol: None,
+    };
+    let x = PrimaryExp::LVal(LVal {
+        id: "x".to_string(),
+        indices: Vec::new(),
+        span,
+        symbol: None,
+    });
+    let neg = UnaryExp::UnaryOp(UnaryOp::Minus, Box::new(UnaryExp::PrimaryExp(x)), span);
+    let block = Block {
+        items: vec![
+            BlockItem::Decl(Decl::VarDecl(BType::Int, vec![def], span)),
+            BlockItem::Stmt(Stmt::Return(Some(Exp::UnaryExp(Box::new(neg))), span)),
+        ],
+        span,
+    };
+    CompUnit {
+        items: vec![CompUnitItem::FuncDef(FuncDef {
+            func_type: FuncType::Int,
+            id: "main".to_string(),
+            params: Vec::new(),
+            block,
+            span,
+            symbol: None,
+        })],
+        span,
+    }
+}
+
+/// 把负号重新生成为 `div 0, x` 后, 参考求值器与两个 target 的结果不一致
+#[test]
+fn catches_negation_lowered_as_division() {
+    let compile = |_: &str| -> sysY::Result<Compiled> {
+        let reference = negation();
+        let mut ast = reference.clone();
+        let mut analyzer = Analyzer::with_lints(LintConfig::silenced());
+        ast.analyze(&mut analyzer)?;
+        let mut builder = IRBuilder::new(analyzer.into_symbols());
+        ast.to_ir(&mut builder)?;
+        // 正常编译, 再把负号生成的 `sub 0, x` 换回 `div 0, x`
+        let mut program = builder.into_program();
+        for func in program.funcs_mut().values_mut() {
+            let negations: Vec<_> = func
+                .dfg()
+                .values()
+                .iter()
+                .filter_map(|(&value, data)| match data.kind() {
+                    ValueKind::Binary(bin) if bin.op() == BinaryOp::Sub => {
+                        Some((value, bin.lhs(), bin.rhs()))
+                    }
+                    _ => None,
+                })
+                .collect();
+            for (value, lhs, rhs) in negations {
+                func.dfg_mut()
+                    .replace_value_with(value)
+                    .binary(BinaryOp::Div, lhs, rhs);
+            }
+        }
+        Ok(Compiled {
+            ast: reference,
+            program,
+        })
+    };
+    match check("", &compile) {
+        // 参考结果是 -7, 即退出码 249
+        Verdict::Mismatch(reason) => {
+            assert!(reason.contains(r#"expected "249", got "0""#), "{}", reason)
+        }
+        verdict => panic!("Expected a mismatch, got {:?}", verdict),
+    }
+}
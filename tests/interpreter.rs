@@ -0,0 +1,130 @@
+// 用解释器执行手写的 Koopa IR, 检查输出和退出码
+
+use sysY::interpreter::{run_program, TimeLimitExceeded};
+use sysY::ir_printer::parse_program;
+
+fn run(ir: &str, input: &str) -> (i32, String) {
+    let program = parse_program(ir).unwrap();
+    let mut output = Vec::new();
+    let code = run_program(&program, &mut input.as_bytes(), &mut output, None).unwrap();
+    (code, String::from_utf8(output).unwrap())
+}
+
+const SUM: &str = r#"
+global @arr = alloc [i32, 4], zeroinit
+
+decl @getarray(*i32): i32
+decl @putint(i32)
+decl @putch(i32)
+decl @putarray(i32, *i32)
+
+fun @sum(%a: *i32, %n: i32): i32 {
+%entry:
+  %i = alloc i32
+  %s = alloc i32
+  store 0, %i
+  store 0, %s
+  jump %cond
+%cond:
+  %0 = load %i
+  %1 = lt %0, %n
+  br %1, %body, %end
+%body:
+  %2 = getptr %a, %0
+  %3 = load %2
+  %4 = load %s
+  %5 = add %4, %3
+  store %5, %s
+  %6 = add %0, 1
+  store %6, %i
+  jump %cond
+%end:
+  %7 = load %s
+  ret %7
+}
+
+fun @main(): i32 {
+%entry:
+  %p = getelemptr @arr, 0
+  %n = call @getarray(%p)
+  %s = call @sum(%p, %n)
+  call @putint(%s)
+  call @putch(10)
+  call @putarray(%n, %p)
+  %code = sub 0, %s
+  ret %code
+}
+"#;
+
+#[test]
+fn runs_loops_calls_and_runtime_functions() {
+    let (code, output) = run(SUM, "3\n 4 -5 6\n");
+    assert_eq!(output, "5\n3: 4 -5 6\n");
+    assert_eq!(code, -5);
+}
+
+#[test]
+fn reports_runtime_errors() {
+    let ir = r#"
+fun @main(): i32 {
+%entry:
+  %a = alloc [i32, 2]
+  %p = getelemptr %a, 2
+  store 1, %p
+  ret 0
+}
+"#;
+    let program = parse_program(ir).unwrap();
+    let err = run_program(&program, &mut "".as_bytes(), &mut Vec::new(), None).unwrap_err();
+    assert!(
+        format!("{:#}", err).contains("Out-of-bounds store"),
+        "{:#}",
+        err
+    );
+}
+
+#[test]
+fn stops_at_the_step_limit() {
+    let ir = r#"
+fun @main(): i32 {
+%entry:
+  jump %loop
+
+%loop:
+  jump %loop
+}
+"#;
+    let program = parse_program(ir).unwrap();
+    let err = run_program(&program, &mut "".as_bytes(), &mut Vec::new(), Some(1000)).unwrap_err();
+    assert!(
+        err.downcast_ref::<TimeLimitExceeded>().is_some(),
+        "{:#}",
+        err
+    );
+}
+
+/// 循环中的 alloc 每次执行得到的是同一个对象, 长循环不会不断分配内存
+#[test]
+fn allocs_in_loops_reuse_their_object() {
+    let ir = r#"
+fun @main(): i32 {
+%entry:
+  jump %body(0)
+
+%body(%i: i32):
+  %p = alloc i32
+  %0 = load %p
+  %1 = add %0, 1
+  store %1, %p
+  %2 = add %i, 1
+  %3 = lt %2, 1000
+  br %3, %body(%2), %exit
+
+%exit:
+  %4 = load %p
+  ret %4
+}
+"#;
+    let (code, _) = run(ir, "");
+    assert_eq!(code, 1000);
+}
@@ -0,0 +1,67 @@
+// 用 koopa 的 builder 构造不合法的 IR, 检查校验器的报错指明函数和基本块
+
+use koopa::ir::builder_traits::*;
+use koopa::ir::{BinaryOp, FunctionData, Program, Type};
+use sysY::ir_verifier::verify_program;
+
+fn message(program: &Program) -> String {
+    format!("{:#}", verify_program(program).unwrap_err())
+}
+
+#[test]
+fn reports_uses_before_definitions_with_their_block() {
+    let mut program = Program::new();
+    let main = program.new_func(FunctionData::new("@main".into(), vec![], Type::get_i32()));
+    let func = program.func_mut(main);
+    let entry = func.dfg_mut().new_bb().basic_block(Some("%entry".into()));
+    let one = func.dfg_mut().new_value().integer(1);
+    let sum = func.dfg_mut().new_value().binary(BinaryOp::Add, one, one);
+    let twice = func.dfg_mut().new_value().binary(BinaryOp::Add, sum, one);
+    let ret = func.dfg_mut().new_value().ret(Some(twice));
+    func.layout_mut().bbs_mut().extend([entry]);
+    func.layout_mut()
+        .bb_mut(entry)
+        .insts_mut()
+        .extend([twice, sum, ret]);
+
+    let message = message(&program);
+    assert!(message.contains("function @main"), "{}", message);
+    assert!(message.contains("basic block %entry"), "{}", message);
+    assert!(message.contains("before it is defined"), "{}", message);
+}
+
+/// 基本块参数只能在所属的块和它支配的块中使用
+#[test]
+fn checks_block_arguments_against_their_block() {
+    let mut program = Program::new();
+    let main = program.new_func(FunctionData::new("@main".into(), vec![], Type::get_i32()));
+    let func = program.func_mut(main);
+    let entry = func.dfg_mut().new_bb().basic_block(Some("%entry".into()));
+    let then = func
+        .dfg_mut()
+        .new_bb()
+        .basic_block_with_params(Some("%then".into()), vec![Type::get_i32()]);
+    let other = func.dfg_mut().new_bb().basic_block(Some("%other".into()));
+    let arg = func.dfg().bb(then).params()[0];
+    let one = func.dfg_mut().new_value().integer(1);
+    let br = func
+        .dfg_mut()
+        .new_value()
+        .branch_with_args(one, then, other, vec![one], vec![]);
+    let ret_then = func.dfg_mut().new_value().ret(Some(arg));
+    let ret_other = func.dfg_mut().new_value().ret(Some(arg));
+    func.layout_mut().bbs_mut().extend([entry, then, other]);
+    func.layout_mut().bb_mut(entry).insts_mut().extend([br]);
+    func.layout_mut()
+        .bb_mut(then)
+        .insts_mut()
+        .extend([ret_then]);
+    func.layout_mut()
+        .bb_mut(other)
+        .insts_mut()
+        .extend([ret_other]);
+
+    let message = message(&program);
+    assert!(message.contains("basic block %other"), "{}", message);
+    assert!(message.contains("before it is defined"), "{}", message);
+}
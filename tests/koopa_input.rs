@@ -0,0 +1,145 @@
+// 以文本形式的 Koopa IR 作为输入, 检查后端、解释器和校验器的结果以及退出码
+
+use std::fs::{create_dir_all, read_to_string, write};
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+/// 每个用例使用自己的文件名, 测试可以并行运行
+fn dir() -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("sysY-koopa-input-{}", std::process::id()));
+    create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// 运行 `sysY <mode> <name>.koopa -o <name>.S`, 返回退出码、stdout 和 stderr
+fn run(mode: &str, name: &str, ir: &str, input: &str) -> (i32, String, String) {
+    let dir = dir();
+    let file = format!("{}.koopa", name);
+    write(dir.join(&file), ir).unwrap();
+    let mut child = Command::new(env!("CARGO_BIN_EXE_sysY"))
+        .args([mode, &file, "-o", &format!("{}.S", name)])
+        .env("RUST_BACKTRACE", "0")
+        .current_dir(&dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    (
+        output.status.code().unwrap(),
+        String::from_utf8_lossy(&output.stdout).into_owned(),
+        String::from_utf8_lossy(&output.stderr).into_owned(),
+    )
+}
+
+const DOUBLE: &str = "decl @getint(): i32
+decl @putint(i32)
+
+fun @main(): i32 {
+%entry:
+  %x = call @getint()
+  %y = mul %x, 2
+  call @putint(%y)
+  ret 3
+}
+";
+
+#[test]
+fn compiles_koopa_to_riscv() {
+    let (code, _, stderr) = run("-riscv", "double", DOUBLE, "");
+    assert_eq!(code, 0, "{}", stderr);
+    let asm = read_to_string(dir().join("double.S")).unwrap();
+    assert!(asm.contains("main:"), "{}", asm);
+    assert!(asm.contains("call getint"), "{}", asm);
+    assert!(asm.contains("call putint"), "{}", asm);
+}
+
+#[test]
+fn runs_koopa_in_the_interpreter() {
+    let (code, stdout, stderr) = run("-run-koopa", "run_double", DOUBLE, "21\n");
+    assert_eq!(code, 3, "{}", stderr);
+    assert_eq!(stdout, "42");
+}
+
+/// 基本块参数和 undef 只能解释执行, RISC-V 后端拒绝编译
+#[test]
+fn backend_rejects_block_params_and_undef() {
+    let params = "fun @main(): i32 {
+%entry:
+  jump %exit(7)
+%exit(%v: i32):
+  ret %v
+}
+";
+    let (code, _, stderr) = run("-riscv", "params", params, "");
+    assert_eq!(code, 1, "{}", stderr);
+    assert!(
+        stderr.contains("The RISC-V backend can not compile this program"),
+        "{}",
+        stderr
+    );
+    assert!(
+        stderr.contains("Basic block arguments are not supported"),
+        "{}",
+        stderr
+    );
+    let (code, _, stderr) = run("-run-koopa", "run_params", params, "");
+    assert_eq!(code, 7, "{}", stderr);
+
+    let undef = "fun @main(): i32 {
+%entry:
+  %x = add undef, 1
+  ret %x
+}
+";
+    let (code, _, stderr) = run("-riscv", "undef", undef, "");
+    assert_eq!(code, 1, "{}", stderr);
+    assert!(
+        stderr.contains("The RISC-V backend can not compile this program"),
+        "{}",
+        stderr
+    );
+    assert!(stderr.contains("undef operands"), "{}", stderr);
+}
+
+/// 不合法的 IR 在进入后端之前报错, 并指出出错的位置
+#[test]
+fn invalid_ir_is_reported_with_its_location() {
+    let malformed = "fun @main(): i32 {
+%entry:
+  ret
+}
+";
+    let (code, _, stderr) = run("-riscv", "malformed", malformed, "");
+    assert_eq!(code, 1, "{}", stderr);
+    assert!(stderr.contains("malformed.koopa:3:3"), "{}", stderr);
+
+    let not_dominating = "fun @main(): i32 {
+%entry:
+  br 1, %then, %end
+%then:
+  %x = add 1, 2
+  jump %end
+%end:
+  ret %x
+}
+";
+    let (code, _, stderr) = run("-run-koopa", "not_dominating", not_dominating, "");
+    assert_eq!(code, 1, "{}", stderr);
+    assert!(stderr.contains("IR verification failed"), "{}", stderr);
+    assert!(stderr.contains("function @main"), "{}", stderr);
+    assert!(stderr.contains("basic block %end"), "{}", stderr);
+    assert!(
+        stderr.contains("uses %x before it is defined"),
+        "{}",
+        stderr
+    );
+}
@@ -0,0 +1,151 @@
+// 在模拟器中执行手写的汇编以及 AsmGenerator 的输出
+
+use sysY::asm_generator::AsmGenerator;
+use sysY::interpreter::{run_program, TimeLimitExceeded};
+use sysY::ir_printer::parse_program;
+use sysY::riscv_sim::{run_asm, Execution};
+
+fn run(asm: &str, input: &str) -> (Execution, String) {
+    let mut output = Vec::new();
+    let result = run_asm(asm, &mut input.as_bytes(), &mut output, None).unwrap();
+    (result, String::from_utf8(output).unwrap())
+}
+
+#[test]
+fn runs_hand_written_assembly() {
+    let asm = r#"
+  .data
+  .global n
+n:
+  .word 3
+  .bss
+buf:
+  .zero 8
+
+  .text
+  .extern putint
+  .global main
+main:
+  addi sp, sp, -16
+  sw ra, 12(sp)
+  la t0, n
+  lw a0, 0(t0)        # a0 = 3
+  call twice
+  li t1, -7
+  slt t2, t1, a0      # -7 < 6
+  seqz t3, t2
+  add a0, a0, t2
+  add a0, a0, t3
+  call putint
+  li t0, 0
+  div a0, a0, t0      # 除零得到 -1
+  lw ra, 12(sp)
+  addi sp, sp, 16
+  ret
+twice:
+  beqz a0, .Lzero
+  add a0, a0, a0
+.Lzero:
+  ret
+"#;
+    let (result, output) = run(asm, "");
+    assert_eq!(output, "7");
+    assert_eq!(result.exit_code, -1);
+    assert_eq!(result.instructions, 19);
+}
+
+#[test]
+fn reports_invalid_memory_access() {
+    let asm = "  .text\nmain:\n  li t0, 4\n  lw a0, 0(t0)\n  ret\n";
+    let err = run_asm(asm, &mut "".as_bytes(), &mut Vec::new(), None).unwrap_err();
+    let message = format!("{:#}", err);
+    assert!(message.contains("line 4"), "{}", message);
+    assert!(message.contains("Invalid memory access"), "{}", message);
+}
+
+#[test]
+fn stops_at_the_step_limit() {
+    let asm = "  .text\nmain:\n  j main\n";
+    let err = run_asm(asm, &mut "".as_bytes(), &mut Vec::new(), Some(1000)).unwrap_err();
+    assert!(
+        err.downcast_ref::<TimeLimitExceeded>().is_some(),
+        "{:#}",
+        err
+    );
+}
+
+/// 超出 12 位的立即数和偏移、超出 5 位的移位量都会被汇编器拒绝
+#[test]
+fn rejects_out_of_range_immediates() {
+    for inst in [
+        "addi a0, x0, 5000",
+        "lw t0, 4000(sp)",
+        "sw t0, -2049(sp)",
+        "slli a0, a0, 32",
+    ] {
+        let asm = format!("  .text\nmain:\n  {}\n  ret\n", inst);
+        let err = run_asm(&asm, &mut "".as_bytes(), &mut Vec::new(), None).unwrap_err();
+        let message = format!("{:#}", err);
+        assert!(message.contains("Line 3"), "{}: {}", inst, message);
+    }
+    let asm = "  .text\nmain:\n  addi a0, x0, 2047\n  addi a0, a0, -2048\n  ret\n";
+    let (result, _) = run(asm, "");
+    assert_eq!(result.exit_code, -1);
+}
+
+/// 运行时库函数返回后, 调用者保存的寄存器不再保留原来的值
+#[test]
+fn host_calls_clobber_caller_saved_registers() {
+    let asm = r#"
+  .text
+main:
+  addi sp, sp, -16
+  sw ra, 12(sp)
+  sw s0, 8(sp)
+  li t0, 5
+  li s0, 5
+  li a0, 1
+  call putint
+  sub a0, s0, t0
+  lw s0, 8(sp)
+  lw ra, 12(sp)
+  addi sp, sp, 16
+  ret
+"#;
+    let (result, output) = run(asm, "");
+    assert_eq!(output, "1");
+    assert_ne!(result.exit_code, 0);
+}
+
+/// 同一个 Koopa 程序经过后端后在模拟器中执行, 结果应与解释器一致
+#[test]
+fn generated_assembly_matches_interpreter() {
+    let ir = r#"
+global @arr = alloc [i32, 5], {1, 2, 3, 4, 5}
+
+decl @getint(): i32
+decl @putint(i32)
+
+fun @main(): i32 {
+%entry:
+  %n = call @getint()
+  %p = getelemptr @arr, %n
+  %v = load %p
+  %m = mul %v, 10
+  %q = getelemptr @arr, 0
+  store %m, %q
+  %first = load %q
+  call @putint(%first)
+  %c = gt %first, 20
+  ret %c
+}
+"#;
+    let program = parse_program(ir).unwrap();
+    let mut expected = Vec::new();
+    let code = run_program(&program, &mut "2".as_bytes(), &mut expected, None).unwrap();
+
+    let asm = AsmGenerator::new().generate_program(&program);
+    let (result, output) = run(&asm, "2");
+    assert_eq!(output.as_bytes(), expected);
+    assert_eq!(result.exit_code, code);
+}
@@ -0,0 +1,110 @@
+// IRPrinter 的输出必须能被 koopa::front::Driver 解析回与原程序相同的结构.
+// 这里手工构造 IRBuilder 目前不会生成的写法: 基本块参数、没有名字的值和基本块、
+// undef 与聚合常量、getptr 等
+
+use koopa::ir::builder_traits::*;
+use koopa::ir::{BinaryOp, FunctionData, Program, Type};
+use sysY::ir_printer::{check_round_trip, IRPrinter};
+
+/// global @arr = alloc [i32, 2], {1, 2}
+/// global @u = alloc i32, undef
+/// decl @putint(i32)
+/// fun @f(%x: i32): i32 { ... }
+fn build_program() -> Program {
+    let mut program = Program::new();
+    let one = program.new_value().integer(1);
+    let two = program.new_value().integer(2);
+    let init = program.new_value().aggregate(vec![one, two]);
+    let arr = program.new_value().global_alloc(init);
+    program.set_value_name(arr, Some("@arr".into()));
+    let undef = program.new_value().undef(Type::get_i32());
+    let global_u = program.new_value().global_alloc(undef);
+    program.set_value_name(global_u, Some("@u".into()));
+
+    let putint = program.new_func(FunctionData::new_decl(
+        "@putint".into(),
+        vec![Type::get_i32()],
+        Type::get_unit(),
+    ));
+    let func = program.new_func(FunctionData::with_param_names(
+        "@f".into(),
+        vec![(Some("%x".into()), Type::get_i32())],
+        Type::get_i32(),
+    ));
+    let data = program.func_mut(func);
+    let x = data.params()[0];
+    let entry = data.dfg_mut().new_bb().basic_block(Some("%entry".into()));
+    // 没有名字的基本块, 带一个参数
+    let merge = data
+        .dfg_mut()
+        .new_bb()
+        .basic_block_with_params(None, vec![Type::get_i32()]);
+    let other = data.dfg_mut().new_bb().basic_block(Some("%other".into()));
+    data.layout_mut().bbs_mut().extend([entry, merge, other]);
+
+    let dfg = data.dfg_mut();
+    let zero = dfg.new_value().integer(0);
+    let one = dfg.new_value().integer(1);
+    let first = dfg.new_value().get_elem_ptr(arr, zero);
+    let second = dfg.new_value().get_ptr(first, one);
+    let loaded = dfg.new_value().load(second);
+    let sum = dfg.new_value().binary(BinaryOp::Xor, loaded, x);
+    let call = dfg.new_value().call(putint, vec![sum]);
+    let local = dfg.new_value().alloc(Type::get_array(Type::get_i32(), 2));
+    dfg.set_value_name(local, Some("@local".into()));
+    let local_init = {
+        let a = dfg.new_value().integer(3);
+        let b = dfg.new_value().integer(4);
+        dfg.new_value().aggregate(vec![a, b])
+    };
+    let store = dfg.new_value().store(local_init, local);
+    let br = dfg
+        .new_value()
+        .branch_with_args(sum, merge, other, vec![sum], vec![]);
+    data.layout_mut()
+        .bb_mut(entry)
+        .insts_mut()
+        .extend([first, second, loaded, sum, call, local, store, br]);
+
+    let dfg = data.dfg_mut();
+    let param = dfg.bb(merge).params()[0];
+    let shifted = dfg.new_value().binary(BinaryOp::Shl, param, one);
+    let ret = dfg.new_value().ret(Some(shifted));
+    data.layout_mut()
+        .bb_mut(merge)
+        .insts_mut()
+        .extend([shifted, ret]);
+
+    let dfg = data.dfg_mut();
+    let seven = dfg.new_value().integer(7);
+    let jump = dfg.new_value().jump_with_args(merge, vec![seven]);
+    data.layout_mut().bb_mut(other).insts_mut().extend([jump]);
+    program
+}
+
+#[test]
+fn hand_built_program_round_trips() {
+    check_round_trip(&build_program()).unwrap();
+}
+
+#[test]
+fn prints_official_syntax() {
+    let text = IRPrinter::new().print_program(&build_program());
+    for line in [
+        "global @arr = alloc [i32, 2], {1, 2}",
+        "global @u = alloc i32, undef",
+        "decl @putint(i32)",
+        // 基本块参数先于指令命名
+        "%bb0(%0: i32):",
+        "%1 = getelemptr @arr, 0",
+        "%2 = getptr %1, 1",
+        "%4 = xor %3, %x",
+        "call @putint(%4)",
+        "store {3, 4}, @local",
+        "br %4, %bb0(%4), %other",
+        "%5 = shl %0, 1",
+        "jump %bb0(7)",
+    ] {
+        assert!(text.contains(line), "missing `{}` in:\n{}", line, text);
+    }
+}
@@ -0,0 +1,130 @@
+// 快照测试: tests/snapshots 中的每个 `.c` 分别以 -koopa 和 -riscv 编译,
+// 与同名的 `.koopa`/`.S` 比较. 输出有意改变时用 `BLESS=1 cargo test --test snapshot`
+// 重新生成期望文件, 再检查 git diff
+
+use std::fs::{read_dir, read_to_string, remove_file, write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const BLESS_VAR: &str = "BLESS";
+
+#[test]
+fn snapshots_match() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/snapshots");
+    let mut sources: Vec<PathBuf> = read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "c"))
+        .collect();
+    sources.sort();
+    assert!(
+        !sources.is_empty(),
+        "No snapshot inputs in {}",
+        dir.display()
+    );
+
+    let bless = std::env::var_os(BLESS_VAR).is_some();
+    let mut failures = Vec::new();
+    for source in &sources {
+        for (mode, ext) in [("-koopa", "koopa"), ("-riscv", "S")] {
+            let actual = compile(source, mode);
+            let snapshot = source.with_extension(ext);
+            if bless {
+                write(&snapshot, &actual).unwrap();
+                continue;
+            }
+            match read_to_string(&snapshot) {
+                Ok(expected) if expected == actual => {}
+                Ok(expected) => failures.push(format!(
+                    "{} differs:\n{}",
+                    snapshot.display(),
+                    diff(&expected, &actual)
+                )),
+                Err(_) => failures.push(format!("{} is missing", snapshot.display())),
+            }
+        }
+    }
+    assert!(
+        failures.is_empty(),
+        "{}\n\nRun with {}=1 to accept the new output",
+        failures.join("\n"),
+        BLESS_VAR
+    );
+}
+
+fn compile(source: &Path, mode: &str) -> String {
+    let output = std::env::temp_dir().join(format!(
+        "sysY-snapshot-{}-{}{}",
+        std::process::id(),
+        source.file_stem().unwrap().to_string_lossy(),
+        mode
+    ));
+    let status = Command::new(env!("CARGO_BIN_EXE_sysY"))
+        .arg(mode)
+        .arg(source)
+        .arg("-o")
+        .arg(&output)
+        .output()
+        .unwrap();
+    assert!(
+        status.status.success(),
+        "Failed to compile {} with {}:\n{}",
+        source.display(),
+        mode,
+        String::from_utf8_lossy(&status.stderr)
+    );
+    let text = read_to_string(&output).unwrap();
+    remove_file(&output).unwrap();
+    text
+}
+
+/// 按行比较的 diff, 只显示改动附近的几行
+fn diff(expected: &str, actual: &str) -> String {
+    const CONTEXT: usize = 2;
+    let old: Vec<&str> = expected.lines().collect();
+    let new: Vec<&str> = actual.lines().collect();
+
+    // lcs[i][j]: old[i..] 与 new[j..] 的最长公共子序列长度
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+    // (标记, 旧文件中的行号, 内容)
+    let mut lines = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            lines.push((' ', i, old[i]));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            lines.push(('-', i, old[i]));
+            i += 1;
+        } else {
+            lines.push(('+', i, new[j]));
+            j += 1;
+        }
+    }
+
+    let changed: Vec<usize> = (0..lines.len()).filter(|&k| lines[k].0 != ' ').collect();
+    let mut out = String::new();
+    let mut last_shown = None;
+    for (k, &(mark, line, text)) in lines.iter().enumerate() {
+        let near = changed.iter().any(|&c| c.abs_diff(k) <= CONTEXT);
+        if !near {
+            continue;
+        }
+        if last_shown.is_none_or(|last| last + 1 != k) {
+            out.push_str(&format!("@@ line {} @@\n", line + 1));
+        }
+        out.push_str(&format!("{}{}\n", mark, text));
+        last_shown = Some(k);
+    }
+    out
+}
@@ -0,0 +1,16 @@
+int add(int a, int b) { return a + b; }
+void nothing() { }
+int many(int a, int b, int c, int d, int e, int f, int g, int h, int i, int j) {
+  return a + b * 2 + c + d + e + f + g + h + i * 3 + j * 5;
+}
+int fib(int n) {
+  if (n < 2) return n;
+  return fib(n - 1) + fib(n - 2);
+}
+void early(int x) { if (x) return; x = 1; }
+int main() {
+  nothing();
+  early(1);
+  int x = add(1, 2) + add(3, 4);
+  return many(1, 2, 3, 4, 5, 6, 7, 8, 9, x) + fib(10);
+}
@@ -0,0 +1,15 @@
+int main() {
+  int i = 0, odd = 0, even = 0;
+  while (i < 10) {
+    if (i == 7) break;
+    i = i + 1;
+    if (i % 2) {
+      odd = odd + i;
+      continue;
+    } else {
+      even = even + i;
+    }
+  }
+  if (odd > even) return odd;
+  return even;
+}
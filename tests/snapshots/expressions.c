@@ -0,0 +1,7 @@
+int main() {
+  int a = 1, b = 2, c = 3, d = 4, e = 5, f = 6, g = 7, h = 8;
+  // 很多同时存活的中间结果, 寄存器分配的改动会反映在这里
+  int x = (a + b) * (c + d) - (e + f) * (g + h) + (a * b + c * d) / (e - f + g * h);
+  int y = !x || (a < b && c >= d) || e != f;
+  return x % 7 + y;
+}
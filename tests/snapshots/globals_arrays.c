@@ -0,0 +1,20 @@
+const int N = 4;
+int counter;
+int table[N][2] = {{1, 2}, {3}, 4, 5};
+
+int sum_row(int row[], int n) {
+  int s = 0, i = 0;
+  while (i < n) {
+    s = s + row[i];
+    i = i + 1;
+  }
+  return s;
+}
+
+int main() {
+  int local[3] = {counter, 7};
+  counter = counter + 1;
+  local[2] = sum_row(table[1], 2) + sum_row(local, 2);
+  putarray(3, local);
+  return table[N - 1][1] + counter;
+}
@@ -0,0 +1,7 @@
+int f(int x) { return x; }
+int main() {
+  int a[1000];
+  int i = 0;
+  while (i < 1000) { a[i] = f(i); i = i + 1; }
+  return a[999];
+}
@@ -0,0 +1,9 @@
+int main() {
+  int x = 7;
+  int y = -x;
+  putint(y);
+  putch(10);
+  putint(-(x - 10));
+  putch(10);
+  return -y + -(-x) - 1;
+}
@@ -0,0 +1,4 @@
+int main() {
+  // 注释
+  return 0x1f; /* 块注释 */
+}
@@ -0,0 +1,3 @@
+int main() {
+  return (1 + 2 * 3 - 4 / 2) % 5 + !0 + (3 > 2) + (2 <= 1) + (1 == 1 && 0 || 2 != 3);
+}
@@ -0,0 +1,7 @@
+const int N = 10, M = N * 2;
+int main() {
+  int a = M, b;
+  b = a + N;
+  a = b * 2;
+  return a;
+}
@@ -0,0 +1,7 @@
+int main() {
+  int a = 3, r = 0;
+  if (a > 2) r = 1;
+  if (a < 2) r = r + 10; else if (a == 3) r = r + 20; else r = r + 30;
+  if (a) if (0) r = 100; else r = r + 1;
+  return r;
+}
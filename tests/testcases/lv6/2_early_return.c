@@ -0,0 +1,17 @@
+int g[4] = {1, 2, 3, 4};
+
+// then 分支以 ret 结束, 之后的块不能沿用其中保存 @g 地址的寄存器
+int pick(int x) {
+  if (x == 0) {
+    return g[0];
+  }
+  return g[3] + x;
+}
+
+int main() {
+  putint(pick(0));
+  putch(10);
+  putint(pick(2));
+  putch(10);
+  return pick(5);
+}
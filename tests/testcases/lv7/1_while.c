@@ -0,0 +1,10 @@
+int main() {
+  int i = 0, sum = 0;
+  while (1) {
+    i = i + 1;
+    if (i > 100) break;
+    if (i % 2) continue;
+    sum = sum + i;
+  }
+  return sum % 256;
+}
@@ -0,0 +1,18 @@
+int fib(int n) {
+  if (n < 2) return n;
+  return fib(n - 1) + fib(n - 2);
+}
+
+void print_line(int x) {
+  putint(x);
+  putch(10);
+}
+
+int main() {
+  int n = getint(), i = 0;
+  while (i < n) {
+    print_line(fib(i));
+    i = i + 1;
+  }
+  return n;
+}
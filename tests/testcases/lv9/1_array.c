@@ -0,0 +1,31 @@
+const int LEN = 5;
+int g[2][3] = {{1, 2}, {3}};
+
+void fill(int a[], int n) {
+  int i = 0;
+  while (i < n) {
+    a[i] = a[i] + i * i;
+    i = i + 1;
+  }
+}
+
+int sum(int m[][3], int rows) {
+  int s = 0, i = 0;
+  while (i < rows) {
+    int j = 0;
+    while (j < 3) {
+      s = s + m[i][j];
+      j = j + 1;
+    }
+    i = i + 1;
+  }
+  return s;
+}
+
+int main() {
+  int a[LEN];
+  int n = getarray(a);
+  fill(a, n);
+  putarray(n, a);
+  return sum(g, 2);
+}